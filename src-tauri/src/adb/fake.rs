@@ -0,0 +1,82 @@
+//! Control of the fake adb in `tests/support/fake_adb.rs`, for testing adb flows without a device
+
+use super::Adb;
+use serde_json::{json, Value};
+use std::fs;
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+/// A copy of the `fake-adb` example in its own directory, with its own script.
+///
+/// Each test gets a separate copy, so tests running in parallel do not share
+/// scripts or call counts.
+pub struct FakeAdb {
+    dir: TempDir,
+    rules: Vec<Value>,
+}
+
+impl FakeAdb {
+    pub fn new(name: &str) -> Self {
+        let dir = tempfile::Builder::new().prefix(&format!("mirin_fake_adb_{}", name)).tempdir().unwrap();
+        fs::copy(fake_adb_exe(), dir.path().join(exe_name())).unwrap();
+
+        Self { dir, rules: Vec::new() }
+    }
+
+    /// Answer commands matching `args` with `stdout`, every time
+    pub fn on(self, args: &str, stdout: &str) -> Self {
+        self.on_calls(args, vec![json!({ "stdout": stdout })])
+    }
+
+    /// Make commands matching `args` fail with `stderr`
+    pub fn fail(self, args: &str, stderr: &str) -> Self {
+        self.on_calls(args, vec![json!({ "stderr": stderr, "exit_code": 1 })])
+    }
+
+    /// Answer successive calls matching `args` with these responses, the last one repeating.
+    /// A response has optional `stdout`, `stderr`, `exit_code` and `delay_ms` fields.
+    pub fn on_calls(mut self, args: &str, responses: Vec<Value>) -> Self {
+        self.rules.push(json!({ "args": args, "responses": responses }));
+        self
+    }
+
+    /// Write the script and get the path of the fake adb executable
+    pub fn path(&self) -> PathBuf {
+        let script = json!({ "rules": self.rules });
+        fs::write(self.dir.path().join("fake_adb.json"), script.to_string()).unwrap();
+        self.dir.path().join(exe_name())
+    }
+
+    /// Write the script and get an `Adb` running the fake
+    pub fn adb(&self) -> Adb {
+        Adb::new(self.path())
+    }
+
+    /// Command lines the fake was called with, oldest first
+    pub fn calls(&self) -> Vec<String> {
+        fs::read_to_string(self.dir.path().join("fake_adb.log"))
+            .unwrap_or_default()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// How many calls matched a command line exactly
+    pub fn count(&self, command_line: &str) -> usize {
+        self.calls().iter().filter(|call| *call == command_line).count()
+    }
+}
+
+/// The fake is built as an example, which `cargo test` builds next to the test binaries
+fn fake_adb_exe() -> PathBuf {
+    let exe = std::env::current_exe().unwrap()
+        .parent().and_then(|deps| deps.parent())
+        .map(|target| target.join("examples").join(format!("fake-adb{}", std::env::consts::EXE_SUFFIX)))
+        .unwrap();
+    assert!(exe.exists(), "{} not found, build it with `cargo build --example fake-adb`", exe.display());
+    exe
+}
+
+fn exe_name() -> String {
+    format!("adb{}", std::env::consts::EXE_SUFFIX)
+}
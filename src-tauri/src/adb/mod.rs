@@ -2,6 +2,9 @@ use std::process::Command;
 use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
 
+#[cfg(test)]
+pub mod fake;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AdbDevice {
     pub serial: String,
@@ -26,6 +29,12 @@ impl Adb {
 
     /// Execute an ADB command and return the output
     fn execute(&self, args: &[&str]) -> Result<String, String> {
+        let stdout = self.execute_raw(args)?;
+        Ok(String::from_utf8_lossy(&stdout).to_string())
+    }
+
+    /// Execute an ADB command and return the output as bytes
+    fn execute_raw(&self, args: &[&str]) -> Result<Vec<u8>, String> {
         #[cfg(target_os = "windows")]
         use std::os::windows::process::CommandExt;
 
@@ -44,8 +53,7 @@ impl Adb {
             return Err(format!("ADB command failed: {}", stderr.trim()));
         }
 
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        Ok(stdout)
+        Ok(output.stdout)
     }
 
     /// Get the ADB version
@@ -206,6 +214,12 @@ impl Adb {
         None
     }
 
+    /// State of a device as the adb server knows it ("device", "offline", ...).
+    /// Answered by the server, without a round trip to the device.
+    pub fn get_state(&self, device_serial: &str) -> Result<String, String> {
+        Ok(self.execute(&["-s", device_serial, "get-state"])?.trim().to_string())
+    }
+
     /// Execute a shell command on a device
     pub fn shell(&self, device_serial: Option<&str>, command: &str) -> Result<String, String> {
         let args = if let Some(serial) = device_serial {
@@ -216,6 +230,17 @@ impl Adb {
         self.execute(&args)
     }
 
+    /// Run a command on a device and return its raw output, without the
+    /// line ending conversion of `adb shell`
+    pub fn exec_out(&self, device_serial: Option<&str>, command: &str) -> Result<Vec<u8>, String> {
+        let args = if let Some(serial) = device_serial {
+            vec!["-s", serial, "exec-out", command]
+        } else {
+            vec!["exec-out", command]
+        };
+        self.execute_raw(&args)
+    }
+
     /// Get device properties
     pub fn get_prop(&self, device_serial: Option<&str>, property: &str) -> Result<String, String> {
         let command = format!("getprop {}", property);
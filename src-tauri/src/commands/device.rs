@@ -1,8 +1,10 @@
 use serde::{Deserialize, Serialize};
 use crate::adb::Adb;
+use crate::scrcpy::{OptionOverrides, ScrcpyOptions};
 use crate::utils;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Device {
@@ -12,14 +14,32 @@ pub struct Device {
     pub connection_type: ConnectionType,
     pub status: DeviceStatus,
     pub ip_address: Option<String>,
+    /// Options that always apply to this device when saved, such as a lower
+    /// max size for an old tablet, on top of the settings and preset
+    #[serde(default)]
+    pub option_overrides: Option<OptionOverrides>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[allow(clippy::upper_case_acronyms)] // Serialized by name for the frontend
 pub enum ConnectionType {
     USB,
     Wireless,
 }
 
+impl ConnectionType {
+    /// How a device is connected, judging by its serial: wireless for an
+    /// address ("192.168.1.42:5555") or a device found over mDNS
+    /// ("adb-R5CT12345-AbCdEf._adb-tls-connect._tcp")
+    pub fn of_serial(serial: &str) -> Self {
+        if serial.contains(':') || serial.trim_end_matches('.').ends_with("._tcp") {
+            ConnectionType::Wireless
+        } else {
+            ConnectionType::USB
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum DeviceStatus {
     Connected,
@@ -52,12 +72,7 @@ pub async fn get_connected_devices(app: tauri::AppHandle) -> Result<Vec<Device>,
     let mut devices = Vec::new();
 
     for adb_device in adb_devices {
-        // Determine connection type (wireless if serial contains ':')
-        let connection_type = if adb_device.serial.contains(':') {
-            ConnectionType::Wireless
-        } else {
-            ConnectionType::USB
-        };
+        let connection_type = ConnectionType::of_serial(&adb_device.serial);
 
         // Map ADB state to our DeviceStatus
         let status = match adb_device.state.as_str() {
@@ -116,6 +131,7 @@ pub async fn get_connected_devices(app: tauri::AppHandle) -> Result<Vec<Device>,
             connection_type,
             status,
             ip_address,
+            option_overrides: None,
         });
     }
 
@@ -133,8 +149,11 @@ pub async fn connect_wireless_device(
     
     // Get ADB path
     let adb_path = utils::get_adb_path(&app)?;
-    let adb = Adb::new(adb_path);
+    connect_wireless(Adb::new(adb_path), ip, port).await
+}
 
+/// Connect to `ip:port` with the given adb, mapping its output to a user-facing error
+async fn connect_wireless(adb: Adb, ip: String, port: u16) -> Result<bool, String> {
     // Connect to device (run in blocking task to avoid blocking async runtime)
     let adb_clone = adb.clone();
     let ip_clone = ip.clone();
@@ -194,8 +213,35 @@ pub async fn enable_wireless_mode(
 ) -> Result<String, String> {
     // Get ADB path
     let adb_path = utils::get_adb_path(&app)?;
-    let adb = Adb::new(adb_path);
+    enable_wireless(Adb::new(adb_path), device_id, ReconnectPolicy::default()).await
+}
+
+/// How long to wait for a device to come back after switching it to TCP/IP mode
+#[derive(Debug, Clone, Copy)]
+struct ReconnectPolicy {
+    attempts: u32,
+    /// Wait before the first look, the device takes a while to restart adbd
+    first_delay: Duration,
+    /// Wait before each further look
+    delay: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 5,
+            first_delay: Duration::from_millis(1500),
+            delay: Duration::from_millis(500),
+        }
+    }
+}
 
+/// Switch a USB device to TCP/IP mode with the given adb and return its IP address
+async fn enable_wireless(
+    adb: Adb,
+    device_id: String,
+    reconnect: ReconnectPolicy,
+) -> Result<String, String> {
     // First, get the device's current info (model name) so we can find it after reconnection
     let adb_clone = adb.clone();
     let device_id_clone = device_id.clone();
@@ -229,10 +275,10 @@ pub async fn enable_wireless_mode(
     // Try multiple times with small delays.
     let mut ip_address: Option<String> = None;
     
-    for attempt in 0..5 {
+    for attempt in 0..reconnect.attempts {
         // Wait for device to reconnect (longer on first attempt)
-        let wait_ms = if attempt == 0 { 1500 } else { 500 };
-        tokio::time::sleep(std::time::Duration::from_millis(wait_ms)).await;
+        let wait = if attempt == 0 { reconnect.first_delay } else { reconnect.delay };
+        tokio::time::sleep(wait).await;
         
         // Get current devices
         let adb_clone = adb.clone();
@@ -243,8 +289,8 @@ pub async fn enable_wireless_mode(
         if let Ok(devices) = devices_result {
             // Find a USB device (one that matches our original device or has same model)
             for dev in &devices {
-                // Skip wireless devices (they contain :)
-                if dev.serial.contains(':') {
+                // Skip wireless devices
+                if ConnectionType::of_serial(&dev.serial) == ConnectionType::Wireless {
                     continue;
                 }
                 
@@ -305,24 +351,47 @@ fn get_saved_devices_path() -> Result<PathBuf, String> {
     Ok(app_dir.join("saved_devices.json"))
 }
 
+fn read_saved_devices(devices_path: &Path) -> Result<Vec<Device>, String> {
+    if !devices_path.exists() {
+        return Ok(Vec::new());
+    }
+    
+    let content = fs::read_to_string(devices_path)
+        .map_err(|e| format!("Failed to read saved devices: {}", e))?;
+    
+    serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse saved devices: {}", e))
+}
+
+fn write_saved_devices(devices_path: &Path, devices: &[Device]) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(devices)
+        .map_err(|e| format!("Failed to serialize devices: {}", e))?;
+    
+    fs::write(devices_path, json)
+        .map_err(|e| format!("Failed to write saved devices: {}", e))
+}
+
+/// Find a saved device by ID
+pub fn find_saved_device(device_id: &str) -> Result<Option<Device>, String> {
+    let devices_path = get_saved_devices_path()?;
+    Ok(read_saved_devices(&devices_path)?.into_iter().find(|d| d.id == device_id))
+}
+
 /// Save a device to the saved devices list
 #[tauri::command]
 pub async fn save_device(device: Device) -> Result<bool, String> {
     let devices_path = get_saved_devices_path()?;
     
     // Read existing devices
-    let mut saved_devices: Vec<Device> = if devices_path.exists() {
-        let content = fs::read_to_string(&devices_path)
-            .map_err(|e| format!("Failed to read saved devices: {}", e))?;
-        serde_json::from_str(&content)
-            .map_err(|e| format!("Failed to parse saved devices: {}", e))?
-    } else {
-        Vec::new()
-    };
+    let mut saved_devices = read_saved_devices(&devices_path)?;
     
     // Check if device already exists (by ID)
     if let Some(pos) = saved_devices.iter().position(|d| d.id == device.id) {
-        // Update existing device
+        // Update existing device, keeping its overrides unless new ones are given
+        let mut device = device;
+        if device.option_overrides.is_none() {
+            device.option_overrides = saved_devices[pos].option_overrides.take();
+        }
         saved_devices[pos] = device;
     } else {
         // Add new device
@@ -330,11 +399,7 @@ pub async fn save_device(device: Device) -> Result<bool, String> {
     }
     
     // Write back to file
-    let json = serde_json::to_string_pretty(&saved_devices)
-        .map_err(|e| format!("Failed to serialize devices: {}", e))?;
-    
-    fs::write(&devices_path, json)
-        .map_err(|e| format!("Failed to write saved devices: {}", e))?;
+    write_saved_devices(&devices_path, &saved_devices)?;
     
     Ok(true)
 }
@@ -343,18 +408,7 @@ pub async fn save_device(device: Device) -> Result<bool, String> {
 #[tauri::command]
 pub async fn get_saved_devices() -> Result<Vec<Device>, String> {
     let devices_path = get_saved_devices_path()?;
-    
-    if !devices_path.exists() {
-        return Ok(Vec::new());
-    }
-    
-    let content = fs::read_to_string(&devices_path)
-        .map_err(|e| format!("Failed to read saved devices: {}", e))?;
-    
-    let devices: Vec<Device> = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse saved devices: {}", e))?;
-    
-    Ok(devices)
+    read_saved_devices(&devices_path)
 }
 
 /// Remove a device from saved devices
@@ -367,11 +421,7 @@ pub async fn remove_saved_device(device_id: String) -> Result<bool, String> {
     }
     
     // Read existing devices
-    let content = fs::read_to_string(&devices_path)
-        .map_err(|e| format!("Failed to read saved devices: {}", e))?;
-    
-    let mut saved_devices: Vec<Device> = serde_json::from_str(&content)
-        .map_err(|e| format!("Failed to parse saved devices: {}", e))?;
+    let mut saved_devices = read_saved_devices(&devices_path)?;
     
     // Remove device by ID
     let initial_len = saved_devices.len();
@@ -382,11 +432,170 @@ pub async fn remove_saved_device(device_id: String) -> Result<bool, String> {
     }
     
     // Write back to file
-    let json = serde_json::to_string_pretty(&saved_devices)
-        .map_err(|e| format!("Failed to serialize devices: {}", e))?;
-    
-    fs::write(&devices_path, json)
-        .map_err(|e| format!("Failed to write saved devices: {}", e))?;
+    write_saved_devices(&devices_path, &saved_devices)?;
     
     Ok(true)
 }
+
+/// Set the option overrides of a saved device, or clear them with None
+#[tauri::command]
+pub async fn set_device_overrides(
+    device_id: String,
+    overrides: Option<OptionOverrides>,
+) -> Result<Device, String> {
+    // Catch unknown fields and wrong types now rather than at the next start
+    if let Some(ref overrides) = overrides {
+        ScrcpyOptions::default().with_overrides(overrides)?;
+    }
+    
+    let devices_path = get_saved_devices_path()?;
+    let mut saved_devices = read_saved_devices(&devices_path)?;
+    
+    let device = saved_devices.iter_mut().find(|d| d.id == device_id)
+        .ok_or_else(|| format!("Device is not saved: {}", device_id))?;
+    device.option_overrides = overrides.filter(|o| !o.is_empty());
+    let device = device.clone();
+    
+    write_saved_devices(&devices_path, &saved_devices)?;
+    
+    Ok(device)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adb::fake::FakeAdb;
+    use serde_json::json;
+
+    const SERIAL: &str = "R5CT12345";
+    const DEVICE_LINE: &str = "R5CT12345      device usb:1-1 product:p3s model:SM_G998B device:p3s transport_id:3\n";
+    const IP_ROUTE: &str = "192.168.1.0/24 dev wlan0 proto kernel scope link src 192.168.1.42\n";
+
+    fn fast_reconnect(attempts: u32) -> ReconnectPolicy {
+        ReconnectPolicy {
+            attempts,
+            first_delay: Duration::from_millis(20),
+            delay: Duration::from_millis(10),
+        }
+    }
+
+    fn device_list(lines: &str) -> String {
+        format!("List of devices attached\n{}\n", lines)
+    }
+
+    fn connect(fake: &FakeAdb) -> Result<bool, String> {
+        tauri::async_runtime::block_on(connect_wireless(fake.adb(), "192.168.1.42".to_string(), 5555))
+    }
+
+    fn enable(fake: &FakeAdb, attempts: u32) -> Result<String, String> {
+        tauri::async_runtime::block_on(enable_wireless(fake.adb(), SERIAL.to_string(), fast_reconnect(attempts)))
+    }
+
+    #[test]
+    fn test_connection_type_of_serial() {
+        assert_eq!(ConnectionType::of_serial("R5CT12345"), ConnectionType::USB);
+        assert_eq!(ConnectionType::of_serial("192.168.1.42:5555"), ConnectionType::Wireless);
+        assert_eq!(ConnectionType::of_serial("[fe80::1]:5555"), ConnectionType::Wireless);
+        assert_eq!(ConnectionType::of_serial("adb-R5CT12345-AbCdEf._adb-tls-connect._tcp"), ConnectionType::Wireless);
+        assert_eq!(ConnectionType::of_serial("adb-R5CT12345-AbCdEf._adb-tls-connect._tcp."), ConnectionType::Wireless);
+    }
+
+    #[test]
+    fn test_connect_success() {
+        let fake = FakeAdb::new("connect_ok")
+            .on("connect 192.168.1.42:5555", "connected to 192.168.1.42:5555\n");
+        assert_eq!(connect(&fake), Ok(true));
+        assert_eq!(fake.calls(), vec!["connect 192.168.1.42:5555"]);
+
+        let fake = FakeAdb::new("connect_already")
+            .on("connect 192.168.1.42:5555", "already connected to 192.168.1.42:5555\n");
+        assert_eq!(connect(&fake), Ok(true));
+    }
+
+    #[test]
+    fn test_connect_error_mapping() {
+        // adb reports most connection failures on stdout with a zero exit code
+        let fake = FakeAdb::new("connect_refused")
+            .on("connect *", "cannot connect to 192.168.1.42:5555: Connection refused (111)\n");
+        let err = connect(&fake).unwrap_err();
+        assert!(err.starts_with("Unable to connect to 192.168.1.42:5555."), "{}", err);
+        assert!(err.contains("AP Isolation"), "{}", err);
+
+        let fake = FakeAdb::new("connect_timeout")
+            .on("connect *", "failed to connect to '192.168.1.42:5555': timeout\n");
+        let err = connect(&fake).unwrap_err();
+        assert!(err.starts_with("Connection timed out to 192.168.1.42:5555."), "{}", err);
+
+        let fake = FakeAdb::new("connect_other")
+            .on("connect *", "failed to authenticate to 192.168.1.42:5555\n");
+        assert_eq!(
+            connect(&fake),
+            Err("Failed to connect: failed to authenticate to 192.168.1.42:5555\n".to_string())
+        );
+    }
+
+    #[test]
+    fn test_connect_adb_failure() {
+        let fake = FakeAdb::new("connect_adb_failure")
+            .on_calls("connect *", vec![json!({ "stderr": "error: protocol fault\n", "exit_code": 1, "delay_ms": 50 })]);
+        assert_eq!(connect(&fake), Err("ADB command failed: error: protocol fault".to_string()));
+    }
+
+    #[test]
+    fn test_enable_wireless_waits_for_reconnect() {
+        let fake = FakeAdb::new("enable_reconnect")
+            .on("-s R5CT12345 shell getprop ro.product.model", "SM-G998B\n")
+            .on("-s R5CT12345 tcpip 5555", "restarting in TCP mode port: 5555\n")
+            // The device drops off while adbd restarts, then comes back
+            .on_calls("devices -l", vec![
+                json!({ "stdout": device_list("") }),
+                json!({ "stdout": device_list(""), "delay_ms": 30 }),
+                json!({ "stdout": device_list(DEVICE_LINE) }),
+            ])
+            .on("-s R5CT12345 shell ip route", IP_ROUTE);
+
+        assert_eq!(enable(&fake, 5), Ok("192.168.1.42".to_string()));
+        assert_eq!(fake.count("devices -l"), 3);
+        assert_eq!(fake.calls().last().map(String::as_str), Some("-s R5CT12345 shell ip route"));
+    }
+
+    #[test]
+    fn test_enable_wireless_retries_ip_lookup() {
+        // Wi-Fi is still coming up on the first look
+        let fake = FakeAdb::new("enable_ip_retry")
+            .on("-s * shell getprop ro.product.model", "SM-G998B\n")
+            .on("-s * tcpip 5555", "restarting in TCP mode port: 5555\n")
+            .on("devices -l", &device_list(DEVICE_LINE))
+            .on_calls("-s R5CT12345 shell ip route", vec![
+                json!({ "stdout": "" }),
+                json!({ "stdout": IP_ROUTE }),
+            ]);
+
+        assert_eq!(enable(&fake, 5), Ok("192.168.1.42".to_string()));
+        assert_eq!(fake.count("-s R5CT12345 shell ip route"), 2);
+    }
+
+    #[test]
+    fn test_enable_wireless_device_not_found() {
+        let fake = FakeAdb::new("enable_not_found")
+            .fail("-s * shell getprop ro.product.model", "error: device 'R5CT12345' not found\n")
+            .fail("-s * tcpip 5555", "error: device 'R5CT12345' not found\n");
+
+        let err = enable(&fake, 5).unwrap_err();
+        assert!(err.starts_with("Device 'R5CT12345' not found. Please refresh the device list"), "{}", err);
+        // No reconnect loop for a device that was never there
+        assert_eq!(fake.count("devices -l"), 0);
+    }
+
+    #[test]
+    fn test_enable_wireless_gives_up() {
+        let fake = FakeAdb::new("enable_gives_up")
+            .on("-s * shell getprop ro.product.model", "SM-G998B\n")
+            .on("-s * tcpip 5555", "restarting in TCP mode port: 5555\n")
+            .on("devices -l", &device_list(""));
+
+        let err = enable(&fake, 3).unwrap_err();
+        assert!(err.starts_with("Wireless mode enabled but couldn't retrieve IP address."), "{}", err);
+        assert_eq!(fake.count("devices -l"), 3);
+    }
+}
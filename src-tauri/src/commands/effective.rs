@@ -0,0 +1,237 @@
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use crate::adb::Adb;
+use crate::scrcpy::{self, OptionOverrides, ScrcpyOptions};
+use crate::utils;
+use super::{device, presets, quality, settings, windows};
+use super::presets::Preset;
+use super::quality::{QualityDecision, QualityMode};
+
+/// Configuration layer an option value came from, lowest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OptionSource {
+    Settings,
+    Preset,
+    /// Picked by the auto quality mode from a measurement of the link
+    Auto,
+    /// Title and placement remembered for the device's window
+    Window,
+    Device,
+    Explicit,
+}
+
+impl OptionSource {
+    fn describe(&self) -> &'static str {
+        match self {
+            OptionSource::Settings => "saved settings",
+            OptionSource::Preset => "preset",
+            OptionSource::Auto => "auto quality",
+            OptionSource::Window => "remembered window",
+            OptionSource::Device => "device overrides",
+            OptionSource::Explicit => "requested options",
+        }
+    }
+}
+
+/// Options a session on a device would start with, and where each value came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EffectiveOptions {
+    pub options: ScrcpyOptions,
+    /// Preset applied, named or default
+    pub preset: Option<String>,
+    /// Layer that last set each field; fields not listed keep the built-in default
+    pub sources: BTreeMap<String, OptionSource>,
+    /// Why scrcpy would refuse to start with these options, if it would
+    pub error: Option<String>,
+    /// What the auto quality mode measured and picked, when it is on
+    pub quality: Option<QualityDecision>,
+}
+
+/// Merge the saved settings, the preset (named, or the default one), the
+/// auto quality pick, the remembered window, the overrides of the saved
+/// device and the explicit options, in that order.
+///
+/// With the auto quality mode on, this measures the link to the device, so
+/// it blocks for a moment.
+pub fn resolve_options<R: tauri::Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    device_id: &str,
+    preset: Option<&str>,
+    explicit: Option<&OptionOverrides>,
+) -> Result<EffectiveOptions, String> {
+    let settings = settings::read_settings(app_handle)?;
+    let preset = presets::resolve_preset(app_handle, preset)?;
+    let device_overrides = device::find_saved_device(device_id)?
+        .and_then(|device| device.option_overrides);
+    let quality = match settings.quality {
+        QualityMode::Auto => Some(quality::auto_quality(&Adb::new(utils::get_adb_path(app_handle)?), device_id)),
+        QualityMode::Manual => None,
+    };
+    let window = match settings.remember_windows {
+        true => windows::window_layer(app_handle, device_id)?,
+        false => None,
+    };
+
+    resolve_layers(
+        &settings.option_overrides()?,
+        preset.as_ref(),
+        quality,
+        window.as_ref(),
+        device_overrides.as_ref(),
+        explicit,
+    )
+}
+
+/// Run `resolve_options` off the async runtime
+pub async fn spawn_resolve_options(
+    app_handle: tauri::AppHandle,
+    device_id: String,
+    preset: Option<String>,
+    explicit: Option<OptionOverrides>,
+) -> Result<EffectiveOptions, String> {
+    tokio::task::spawn_blocking(move || {
+        resolve_options(&app_handle, &device_id, preset.as_deref(), explicit.as_ref())
+    })
+    .await
+    .map_err(|e| format!("Option resolution task failed: {}", e))?
+}
+
+fn resolve_layers(
+    settings: &OptionOverrides,
+    preset: Option<&Preset>,
+    quality: Option<QualityDecision>,
+    window: Option<&OptionOverrides>,
+    device: Option<&OptionOverrides>,
+    explicit: Option<&OptionOverrides>,
+) -> Result<EffectiveOptions, String> {
+    let quality_overrides = quality.as_ref().map(QualityDecision::to_overrides);
+    let layers = [
+        (OptionSource::Settings, Some(settings)),
+        (OptionSource::Preset, preset.map(|preset| &preset.options)),
+        (OptionSource::Auto, quality_overrides.as_ref()),
+        (OptionSource::Window, window),
+        (OptionSource::Device, device),
+        (OptionSource::Explicit, explicit),
+    ];
+
+    let mut options = ScrcpyOptions::default();
+    let mut sources = BTreeMap::new();
+    for (source, overrides) in layers {
+        let Some(overrides) = overrides else {
+            continue;
+        };
+
+        options = options.with_overrides(overrides)
+            .map_err(|e| format!("Failed to apply {}: {}", source.describe(), e))?;
+        for key in overrides.keys() {
+            sources.insert(scrcpy::option_field(key).to_string(), source);
+        }
+    }
+
+    Ok(EffectiveOptions {
+        error: options.validate().err(),
+        options,
+        preset: preset.map(|preset| preset.name.clone()),
+        sources,
+        quality,
+    })
+}
+
+/// Show the options starting a session on a device would use, and where each value comes from
+#[tauri::command]
+pub async fn get_effective_options(
+    app_handle: tauri::AppHandle,
+    device_id: String,
+    preset: Option<String>,
+    options: Option<OptionOverrides>,
+) -> Result<EffectiveOptions, String> {
+    spawn_resolve_options(app_handle, device_id, preset, options).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn overrides(value: serde_json::Value) -> OptionOverrides {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_layers_apply_in_order() {
+        let settings = overrides(json!({ "bit_rate": 4_000_000, "max_fps": 30, "always_on_top": true }));
+        let preset = Preset {
+            name: "Demo 1080p60".to_string(),
+            description: None,
+            options: overrides(json!({ "max_fps": 60 })),
+        };
+        // An old tablet that cannot encode 1920
+        let device = overrides(json!({ "max_size": 1280, "video_encoder": "OMX.qcom.video.encoder.avc" }));
+        let window = overrides(json!({ "window_x": 1380, "window_y": 40, "window_title": "Lab tablet 2" }));
+        let explicit = overrides(json!({ "turn_screen_off": true }));
+
+        let effective = resolve_layers(&settings, Some(&preset), None, Some(&window), Some(&device), Some(&explicit)).unwrap();
+        let options = &effective.options;
+        assert_eq!(options.max_fps, Some(60));
+        // Fields the preset leaves out keep the settings' values
+        assert_eq!(options.bit_rate, Some(4_000_000));
+        assert!(options.always_on_top);
+        assert_eq!(options.max_size, Some(1280));
+        assert!(options.turn_screen_off);
+        assert_eq!(options.window_x, Some(1380));
+        assert_eq!(options.window_title.as_deref(), Some("Lab tablet 2"));
+
+        assert_eq!(effective.preset.as_deref(), Some("Demo 1080p60"));
+        assert_eq!(effective.sources["max_fps"], OptionSource::Preset);
+        assert_eq!(effective.sources["bit_rate"], OptionSource::Settings);
+        assert_eq!(effective.sources["max_size"], OptionSource::Device);
+        assert_eq!(effective.sources["window_title"], OptionSource::Window);
+        assert_eq!(effective.sources["turn_screen_off"], OptionSource::Explicit);
+        assert!(effective.error.is_none());
+    }
+
+    #[test]
+    fn test_settings_without_preset() {
+        let settings = overrides(json!({ "bit_rate": 4_000_000 }));
+        let explicit = overrides(json!({ "display_id": 2, "bit_rate": 2_000_000, "record_file": "/tmp/demo.mp4" }));
+
+        let effective = resolve_layers(&settings, None, None, None, None, Some(&explicit)).unwrap();
+        assert_eq!(effective.sources["record_path"], OptionSource::Explicit);
+        assert_eq!(effective.options.bit_rate, Some(2_000_000));
+        assert_eq!(effective.options.display_id, Some(2));
+        assert_eq!(effective.sources["bit_rate"], OptionSource::Explicit);
+        assert!(!effective.sources.contains_key("max_size"));
+        assert!(effective.preset.is_none());
+    }
+
+    #[test]
+    fn test_auto_quality_layer() {
+        let settings = overrides(json!({ "bit_rate": 8_000_000, "max_size": null }));
+        let quality = quality::choose_quality(
+            device::ConnectionType::Wireless,
+            Ok(quality::LinkMeasurement { latency_ms: 8, throughput_bps: 9_000_000 }),
+        );
+        let device = overrides(json!({ "max_size": 1024 }));
+
+        let effective = resolve_layers(&settings, None, Some(quality), None, Some(&device), None).unwrap();
+        // Over the settings, under the device overrides
+        assert_eq!(effective.options.bit_rate, Some(4_000_000));
+        assert_eq!(effective.options.max_size, Some(1024));
+        assert_eq!(effective.sources["bit_rate"], OptionSource::Auto);
+        assert_eq!(effective.sources["max_size"], OptionSource::Device);
+        assert_eq!(effective.quality.unwrap().tier, quality::QualityTier::Medium);
+    }
+
+    #[test]
+    fn test_reports_invalid_results() {
+        let settings = OptionOverrides::new();
+        let device = overrides(json!({ "max_sise": 1280 }));
+        let err = resolve_layers(&settings, None, None, None, Some(&device), None).unwrap_err();
+        assert_eq!(err, "Failed to apply device overrides: Unknown option: 'max_sise'");
+
+        // Each layer is fine on its own, but the result cannot start
+        let explicit = overrides(json!({ "no_playback": true }));
+        let effective = resolve_layers(&settings, None, None, None, None, Some(&explicit)).unwrap();
+        assert!(effective.error.is_some());
+    }
+}
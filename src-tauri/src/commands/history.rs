@@ -0,0 +1,433 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tauri::Manager;
+use crate::scrcpy::{ExitReason, ScrcpyOptions, SessionExit, SessionFailure};
+use super::store;
+
+/// Number of runs kept in the history; the oldest are dropped first
+const MAX_HISTORY_ENTRIES: usize = 1000;
+
+/// One run of scrcpy. A session relaunched by its restart policy gets one
+/// record per run, all with the same `session_id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub session_id: String,
+    /// Device serial
+    pub device_id: String,
+    pub model: Option<String>,
+    pub options: ScrcpyOptions,
+    /// RFC3339 timestamps
+    pub started_at: String,
+    pub ended_at: Option<String>,
+    pub duration_secs: Option<f64>,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    /// None while running, or if the app quit before the session ended
+    pub reason: Option<ExitReason>,
+    pub failure: Option<SessionFailure>,
+}
+
+impl SessionRecord {
+    pub fn new(session_id: &str, device_id: &str, options: &ScrcpyOptions, started_at: SystemTime) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+            device_id: device_id.to_string(),
+            model: None,
+            options: options.clone(),
+            started_at: DateTime::<Utc>::from(started_at).to_rfc3339(),
+            ended_at: None,
+            duration_secs: None,
+            exit_code: None,
+            signal: None,
+            reason: None,
+            failure: None,
+        }
+    }
+
+    /// Fill in how the run ended
+    pub fn finish(&mut self, exit: &SessionExit, ended_at: DateTime<Utc>) {
+        self.ended_at = Some(ended_at.to_rfc3339());
+        self.duration_secs = Some(exit.duration_secs);
+        self.exit_code = exit.code;
+        self.signal = exit.signal;
+        self.reason = Some(exit.reason);
+        self.failure = exit.failure;
+    }
+
+    fn started(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.started_at)
+            .ok()
+            .map(|started| started.with_timezone(&Utc))
+    }
+}
+
+/// Which runs `get_session_history` returns. All fields are optional.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HistoryFilter {
+    pub device_id: Option<String>,
+    /// RFC3339 bounds on the start time
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub reason: Option<ExitReason>,
+    pub failure: Option<SessionFailure>,
+    /// Maximum number of runs returned; totals cover every match
+    pub limit: Option<usize>,
+}
+
+/// Counts over the runs matching a filter
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HistoryTotals {
+    pub started: usize,
+    pub closed: usize,
+    pub stopped: usize,
+    pub failed: usize,
+    /// Still running, or ended while the app was not running
+    pub unfinished: usize,
+    pub total_duration_secs: f64,
+}
+
+/// Counters kept next to the history, which drops its oldest runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct HistoryStats {
+    /// Runs ever recorded
+    total_started: usize,
+}
+
+/// Result of `get_session_history`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionHistory {
+    /// Newest first
+    pub sessions: Vec<SessionRecord>,
+    pub totals: HistoryTotals,
+}
+
+fn get_history_path<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+    let app_data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+
+    Ok(app_data_dir.join("session_history.json"))
+}
+
+/// Add a started run to the history
+pub fn record_session_start<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, record: SessionRecord) -> Result<(), String> {
+    let history_path = get_history_path(app_handle)?;
+    append_to_history(&history_path, record)
+}
+
+/// Record how the latest run of a session ended
+pub fn record_session_end<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, session_id: &str, exit: &SessionExit) -> Result<(), String> {
+    let history_path = get_history_path(app_handle)?;
+    finish_in_history(&history_path, session_id, exit, Utc::now())
+}
+
+/// Number of runs ever recorded, including those dropped from the history
+pub fn count_sessions<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<usize, String> {
+    let history_path = get_history_path(app_handle)?;
+    let _lock = store::lock();
+    Ok(read_stats(&history_path)?.total_started)
+}
+
+fn get_stats_path(history_path: &Path) -> PathBuf {
+    history_path.with_file_name("session_stats.json")
+}
+
+fn read_stats(history_path: &Path) -> Result<HistoryStats, String> {
+    let stats_path = get_stats_path(history_path);
+    if !stats_path.exists() {
+        // Histories written before the counter existed have not dropped anything yet
+        return Ok(HistoryStats { total_started: read_history(history_path)?.len() });
+    }
+
+    let json = fs::read_to_string(&stats_path)
+        .map_err(|e| format!("Failed to read session stats: {}", e))?;
+
+    serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse session stats: {}", e))
+}
+
+fn write_stats(history_path: &Path, stats: &HistoryStats) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(stats)
+        .map_err(|e| format!("Failed to serialize session stats: {}", e))?;
+
+    fs::write(get_stats_path(history_path), json)
+        .map_err(|e| format!("Failed to write session stats: {}", e))
+}
+
+fn read_history(history_path: &Path) -> Result<Vec<SessionRecord>, String> {
+    if !history_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let json = fs::read_to_string(history_path)
+        .map_err(|e| format!("Failed to read session history: {}", e))?;
+
+    serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse session history: {}", e))
+}
+
+fn write_history(history_path: &Path, records: &[SessionRecord]) -> Result<(), String> {
+    if let Some(parent) = history_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create app data directory: {}", e))?;
+    }
+
+    let json = serde_json::to_string_pretty(records)
+        .map_err(|e| format!("Failed to serialize session history: {}", e))?;
+
+    fs::write(history_path, json)
+        .map_err(|e| format!("Failed to write session history: {}", e))
+}
+
+fn append_to_history(history_path: &Path, record: SessionRecord) -> Result<(), String> {
+    let _lock = store::lock();
+    let mut stats = read_stats(history_path)?;
+    let mut records = read_history(history_path)?;
+    records.push(record);
+
+    if records.len() > MAX_HISTORY_ENTRIES {
+        let excess = records.len() - MAX_HISTORY_ENTRIES;
+        records.drain(..excess);
+    }
+
+    write_history(history_path, &records)?;
+    stats.total_started += 1;
+    write_stats(history_path, &stats)
+}
+
+fn finish_in_history(history_path: &Path, session_id: &str, exit: &SessionExit, now: DateTime<Utc>) -> Result<(), String> {
+    let _lock = store::lock();
+    let mut records = read_history(history_path)?;
+
+    // Exits can be reported twice (monitor and cleanup), so only open runs are updated
+    let Some(record) = records.iter_mut().rev()
+        .find(|r| r.session_id == session_id && r.ended_at.is_none())
+    else {
+        return Ok(());
+    };
+
+    record.finish(exit, now);
+    write_history(history_path, &records)
+}
+
+fn parse_bound(value: Option<&str>) -> Result<Option<DateTime<Utc>>, String> {
+    value
+        .map(|value| {
+            DateTime::parse_from_rfc3339(value)
+                .map(|date| date.with_timezone(&Utc))
+                .map_err(|e| format!("Invalid date in history filter '{}': {}", value, e))
+        })
+        .transpose()
+}
+
+fn query_history(records: Vec<SessionRecord>, filter: &HistoryFilter) -> Result<SessionHistory, String> {
+    let since = parse_bound(filter.since.as_deref())?;
+    let until = parse_bound(filter.until.as_deref())?;
+
+    let mut totals = HistoryTotals::default();
+    let mut sessions: Vec<SessionRecord> = records.into_iter()
+        .filter(|r| filter.device_id.as_ref().map_or(true, |id| &r.device_id == id))
+        .filter(|r| filter.reason.map_or(true, |reason| r.reason == Some(reason)))
+        .filter(|r| filter.failure.map_or(true, |failure| r.failure == Some(failure)))
+        .filter(|r| {
+            let started = r.started();
+            since.map_or(true, |since| started.is_some_and(|s| s >= since))
+                && until.map_or(true, |until| started.is_some_and(|s| s <= until))
+        })
+        .inspect(|r| {
+            totals.started += 1;
+            match r.reason {
+                Some(ExitReason::Closed) => totals.closed += 1,
+                Some(ExitReason::Stopped) => totals.stopped += 1,
+                Some(ExitReason::Failed) => totals.failed += 1,
+                None => totals.unfinished += 1,
+            }
+            totals.total_duration_secs += r.duration_secs.unwrap_or(0.0);
+        })
+        .collect();
+
+    sessions.reverse();
+    if let Some(limit) = filter.limit {
+        sessions.truncate(limit);
+    }
+
+    Ok(SessionHistory { sessions, totals })
+}
+
+/// Get past and running sessions, newest first, with totals for the filter
+#[tauri::command]
+pub async fn get_session_history(
+    app_handle: tauri::AppHandle,
+    filter: Option<HistoryFilter>,
+) -> Result<SessionHistory, String> {
+    let history_path = get_history_path(&app_handle)?;
+    let records = {
+        let _lock = store::lock();
+        read_history(&history_path)?
+    };
+    query_history(records, &filter.unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn record(session_id: &str, device_id: &str, started_at: SystemTime) -> SessionRecord {
+        SessionRecord::new(session_id, device_id, &ScrcpyOptions::default(), started_at)
+    }
+
+    fn exit(reason: ExitReason, failure: Option<SessionFailure>) -> SessionExit {
+        SessionExit {
+            code: Some(if reason == ExitReason::Failed { 1 } else { 0 }),
+            signal: None,
+            duration_secs: 30.0,
+            reason,
+            failure,
+        }
+    }
+
+    #[test]
+    fn test_start_and_finish() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session_history.json");
+        let started = SystemTime::now();
+
+        append_to_history(&path, record("s1", "192.168.1.20:5555", started)).unwrap();
+        let ended = DateTime::<Utc>::from(started) + chrono::Duration::seconds(30);
+        finish_in_history(&path, "s1", &exit(ExitReason::Failed, Some(SessionFailure::DeviceDisconnected)), ended).unwrap();
+
+        let records = read_history(&path).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].reason, Some(ExitReason::Failed));
+        assert_eq!(records[0].failure, Some(SessionFailure::DeviceDisconnected));
+        assert_eq!(records[0].exit_code, Some(1));
+        assert_eq!(records[0].duration_secs, Some(30.0));
+        assert_eq!(records[0].ended_at, Some(ended.to_rfc3339()));
+
+        // A second report of the same exit does not touch the finished run
+        finish_in_history(&path, "s1", &exit(ExitReason::Closed, None), Utc::now()).unwrap();
+        assert_eq!(read_history(&path).unwrap()[0].reason, Some(ExitReason::Failed));
+    }
+
+    #[test]
+    fn test_restarts_finish_latest_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session_history.json");
+        let now = SystemTime::now();
+
+        append_to_history(&path, record("s1", "d", now)).unwrap();
+        finish_in_history(&path, "s1", &exit(ExitReason::Failed, None), Utc::now()).unwrap();
+        append_to_history(&path, record("s1", "d", now + Duration::from_secs(5))).unwrap();
+        finish_in_history(&path, "s1", &exit(ExitReason::Stopped, None), Utc::now()).unwrap();
+
+        let records = read_history(&path).unwrap();
+        assert_eq!(records[0].reason, Some(ExitReason::Failed));
+        assert_eq!(records[1].reason, Some(ExitReason::Stopped));
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session_history.json");
+        let mut records: Vec<SessionRecord> = (0..MAX_HISTORY_ENTRIES)
+            .map(|i| record(&format!("s{}", i), "d", SystemTime::now()))
+            .collect();
+        write_history(&path, &records).unwrap();
+
+        append_to_history(&path, record("newest", "d", SystemTime::now())).unwrap();
+        records = read_history(&path).unwrap();
+        assert_eq!(records.len(), MAX_HISTORY_ENTRIES);
+        assert_eq!(records[0].session_id, "s1");
+        assert_eq!(records.last().unwrap().session_id, "newest");
+
+        // The total keeps counting the dropped runs
+        assert_eq!(read_stats(&path).unwrap().total_started, MAX_HISTORY_ENTRIES + 1);
+        append_to_history(&path, record("newer", "d", SystemTime::now())).unwrap();
+        assert_eq!(read_stats(&path).unwrap().total_started, MAX_HISTORY_ENTRIES + 2);
+    }
+
+    #[test]
+    fn test_concurrent_sessions_keep_their_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("session_history.json");
+
+        let handles: Vec<_> = (0..8).map(|i| {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                let session_id = format!("s{}", i);
+                append_to_history(&path, record(&session_id, "d", SystemTime::now())).unwrap();
+                finish_in_history(&path, &session_id, &exit(ExitReason::Closed, None), Utc::now()).unwrap();
+            })
+        }).collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let records = read_history(&path).unwrap();
+        assert_eq!(records.len(), 8);
+        assert!(records.iter().all(|r| r.reason == Some(ExitReason::Closed)));
+        assert_eq!(read_stats(&path).unwrap().total_started, 8);
+    }
+
+    #[test]
+    fn test_query_filters_and_totals() {
+        let base = SystemTime::now();
+        let mut records = Vec::new();
+        for (i, (device, reason)) in [
+            ("wifi:5555", Some(ExitReason::Failed)),
+            ("wifi:5555", Some(ExitReason::Closed)),
+            ("usb", Some(ExitReason::Stopped)),
+            ("wifi:5555", None),
+        ].into_iter().enumerate() {
+            let mut r = record(&format!("s{}", i), device, base + Duration::from_secs(i as u64 * 60));
+            if let Some(reason) = reason {
+                r.finish(&exit(reason, None), Utc::now());
+            }
+            records.push(r);
+        }
+
+        let all = query_history(records.clone(), &HistoryFilter::default()).unwrap();
+        assert_eq!(all.totals, HistoryTotals {
+            started: 4,
+            closed: 1,
+            stopped: 1,
+            failed: 1,
+            unfinished: 1,
+            total_duration_secs: 90.0,
+        });
+        assert_eq!(all.sessions[0].session_id, "s3");
+
+        let wifi = query_history(records.clone(), &HistoryFilter {
+            device_id: Some("wifi:5555".to_string()),
+            limit: Some(1),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(wifi.totals.started, 3);
+        assert_eq!(wifi.totals.failed, 1);
+        assert_eq!(wifi.sessions.len(), 1);
+
+        let since = DateTime::<Utc>::from(base + Duration::from_secs(90)).to_rfc3339();
+        let recent = query_history(records.clone(), &HistoryFilter {
+            since: Some(since),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(recent.totals.started, 2);
+
+        let failed = query_history(records.clone(), &HistoryFilter {
+            reason: Some(ExitReason::Failed),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(failed.sessions.len(), 1);
+        assert_eq!(failed.sessions[0].session_id, "s0");
+
+        assert!(query_history(records, &HistoryFilter {
+            until: Some("yesterday".to_string()),
+            ..Default::default()
+        }).is_err());
+    }
+}
@@ -1,8 +1,22 @@
 pub mod device;
+pub mod effective;
+pub mod history;
+pub mod orphans;
+pub mod presets;
+pub mod quality;
+pub mod recordings;
 pub mod scrcpy;
 pub mod settings;
+mod store;
+pub mod windows;
 
 // Re-export commands for easy access
 pub use device::*;
+pub use effective::*;
+pub use history::*;
+pub use presets::*;
+pub use quality::*;
+pub use recordings::*;
 pub use scrcpy::*;
 pub use settings::*;
+pub use windows::*;
@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+use crate::scrcpy::{self, RestartPolicy, ScrcpyOptions};
+
+/// A running scrcpy process, written to disk so it can be found again if the
+/// app crashes or is killed before stopping it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PidFile {
+    pub session_id: String,
+    pub pid: u32,
+    pub device_id: String,
+    /// RFC3339 timestamp
+    pub started_at: String,
+    /// scrcpy executable that was launched
+    pub exe_path: PathBuf,
+    pub options: ScrcpyOptions,
+    pub recording_id: Option<String>,
+    /// Restart policy the session was started with, kept when it is adopted
+    #[serde(default)]
+    pub restart: RestartPolicy,
+}
+
+fn get_pid_dir<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+    let app_data_dir = app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))?;
+
+    Ok(app_data_dir.join("sessions"))
+}
+
+/// Write the pid file of a session, replacing the one of its previous process
+pub fn write_pid_file<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, pid_file: &PidFile) -> Result<(), String> {
+    write_in_dir(&get_pid_dir(app_handle)?, pid_file)
+}
+
+/// Remove the pid file of a session that ended
+pub fn remove_pid_file<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, session_id: &str) -> Result<(), String> {
+    remove_in_dir(&get_pid_dir(app_handle)?, session_id)
+}
+
+/// Pid files of scrcpy processes still running from a previous run of the app.
+///
+/// Files of processes that are gone, or whose pid now belongs to another
+/// program, are deleted.
+pub fn find_orphans<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<Vec<PidFile>, String> {
+    find_in_dir(&get_pid_dir(app_handle)?, |pid_file| {
+        scrcpy::is_alive(pid_file.pid)
+            && scrcpy::is_scrcpy_process(pid_file.pid, &pid_file.exe_path, &pid_file.device_id)
+    })
+}
+
+/// Session ids of wireless devices contain ':', which Windows does not allow in file names
+fn pid_file_path(dir: &Path, session_id: &str) -> PathBuf {
+    let name: String = session_id.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    dir.join(format!("{}.json", name))
+}
+
+fn write_in_dir(dir: &Path, pid_file: &PidFile) -> Result<(), String> {
+    fs::create_dir_all(dir)
+        .map_err(|e| format!("Failed to create pid file directory: {}", e))?;
+
+    let json = serde_json::to_string_pretty(pid_file)
+        .map_err(|e| format!("Failed to serialize pid file: {}", e))?;
+
+    fs::write(pid_file_path(dir, &pid_file.session_id), json)
+        .map_err(|e| format!("Failed to write pid file: {}", e))
+}
+
+fn remove_in_dir(dir: &Path, session_id: &str) -> Result<(), String> {
+    match fs::remove_file(pid_file_path(dir, session_id)) {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Failed to remove pid file: {}", e)),
+    }
+}
+
+fn find_in_dir(dir: &Path, is_running: impl Fn(&PidFile) -> bool) -> Result<Vec<PidFile>, String> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries = fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read pid file directory: {}", e))?;
+
+    let mut orphans = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.extension().map_or(true, |ext| ext != "json") {
+            continue;
+        }
+
+        let pid_file = fs::read_to_string(&path)
+            .ok()
+            .and_then(|json| serde_json::from_str::<PidFile>(&json).ok());
+
+        match pid_file {
+            Some(pid_file) if is_running(&pid_file) => orphans.push(pid_file),
+            _ => {
+                if let Err(e) = fs::remove_file(&path) {
+                    eprintln!("Failed to remove stale pid file {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+
+    Ok(orphans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pid_file(session_id: &str, pid: u32) -> PidFile {
+        PidFile {
+            session_id: session_id.to_string(),
+            pid,
+            device_id: "192.168.1.20:5555".to_string(),
+            started_at: "2024-05-01T10:00:00+00:00".to_string(),
+            exe_path: PathBuf::from("/opt/mirin/scrcpy"),
+            options: ScrcpyOptions::default(),
+            recording_id: None,
+            restart: RestartPolicy::OnFailure { max_retries: 3, backoff_ms: 1000 },
+        }
+    }
+
+    #[test]
+    fn test_write_and_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let session_id = "session_192.168.1.20:5555_4242";
+
+        write_in_dir(dir.path(), &pid_file(session_id, 4242)).unwrap();
+        let path = pid_file_path(dir.path(), session_id);
+        assert!(path.exists());
+        assert!(!path.file_name().unwrap().to_string_lossy().contains(':'));
+
+        remove_in_dir(dir.path(), session_id).unwrap();
+        assert!(!path.exists());
+        // Removing twice is fine, sessions can end through several paths
+        remove_in_dir(dir.path(), session_id).unwrap();
+    }
+
+    #[test]
+    fn test_find_survivors_and_drop_stale() {
+        let dir = tempfile::tempdir().unwrap();
+        write_in_dir(dir.path(), &pid_file("alive", 100)).unwrap();
+        write_in_dir(dir.path(), &pid_file("dead", 200)).unwrap();
+        fs::write(dir.path().join("garbage.json"), "not json").unwrap();
+
+        let orphans = find_in_dir(dir.path(), |f| f.pid == 100).unwrap();
+        assert_eq!(orphans.len(), 1);
+        assert_eq!(orphans[0].session_id, "alive");
+        assert_eq!(orphans[0].restart, RestartPolicy::OnFailure { max_retries: 3, backoff_ms: 1000 });
+
+        // Only the surviving session keeps its file
+        assert!(pid_file_path(dir.path(), "alive").exists());
+        assert!(!pid_file_path(dir.path(), "dead").exists());
+        assert!(!dir.path().join("garbage.json").exists());
+    }
+
+    #[test]
+    fn test_pid_files_without_restart_policy() {
+        // Written before the policy was kept
+        let mut json = serde_json::to_value(pid_file("s", 1)).unwrap();
+        json.as_object_mut().unwrap().remove("restart");
+        let pid_file: PidFile = serde_json::from_value(json).unwrap();
+        assert_eq!(pid_file.restart, RestartPolicy::Never);
+    }
+
+    #[test]
+    fn test_missing_dir_has_no_orphans() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(find_in_dir(&dir.path().join("sessions"), |_| true).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,424 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+use crate::scrcpy::{OptionOverrides, ScrcpyOptions};
+use super::store;
+
+/// A named set of mirroring options, such as "Low bandwidth wireless"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Preset {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Options the preset sets; the others keep the value of the saved settings
+    pub options: OptionOverrides,
+}
+
+/// All saved presets, as stored in presets.json and in exported files
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PresetLibrary {
+    #[serde(default)]
+    pub presets: Vec<Preset>,
+    /// Preset used by `start_mirroring` when not given one
+    #[serde(default)]
+    pub default_preset: Option<String>,
+}
+
+impl PresetLibrary {
+    fn find(&self, name: &str) -> Option<&Preset> {
+        self.presets.iter().find(|p| same_name(&p.name, name))
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.presets.iter().position(|p| same_name(&p.name, name))
+    }
+}
+
+/// A preset from an imported file that was not added
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedPreset {
+    pub name: String,
+    pub reason: String,
+}
+
+/// Outcome of importing a presets file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PresetImport {
+    pub imported: Vec<String>,
+    pub skipped: Vec<SkippedPreset>,
+}
+
+/// Preset names are compared case-insensitively, so "Demo" and "demo" cannot coexist
+fn same_name(a: &str, b: &str) -> bool {
+    a.trim().eq_ignore_ascii_case(b.trim())
+}
+
+fn get_presets_path<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+    app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))
+        .map(|path| path.join("presets.json"))
+}
+
+/// Read the saved presets, or an empty library if none were saved
+pub fn read_presets<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<PresetLibrary, String> {
+    let path = get_presets_path(app_handle)?;
+    let _lock = store::lock();
+    read_library(&path)
+}
+
+/// The named preset, or the default preset when no name is given.
+///
+/// Returns None when no name is given and no default preset is set.
+pub fn resolve_preset<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, name: Option<&str>) -> Result<Option<Preset>, String> {
+    resolve_in(&read_presets(app_handle)?, name)
+}
+
+fn resolve_in(library: &PresetLibrary, name: Option<&str>) -> Result<Option<Preset>, String> {
+    match name.or(library.default_preset.as_deref()) {
+        Some(name) => library.find(name)
+            .map(|preset| Some(preset.clone()))
+            .ok_or_else(|| format!("Preset not found: {}", name)),
+        None => Ok(None),
+    }
+}
+
+fn read_library(path: &Path) -> Result<PresetLibrary, String> {
+    if !path.exists() {
+        return Ok(PresetLibrary::default());
+    }
+
+    let json = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read presets file: {}", e))?;
+
+    serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse presets: {}", e))
+}
+
+fn write_library(path: &Path, library: &PresetLibrary) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create presets directory: {}", e))?;
+    }
+
+    let json = serde_json::to_string_pretty(library)
+        .map_err(|e| format!("Failed to serialize presets: {}", e))?;
+
+    fs::write(path, json)
+        .map_err(|e| format!("Failed to write presets file: {}", e))
+}
+
+fn validate_preset(preset: &Preset) -> Result<(), String> {
+    if preset.name.trim().is_empty() {
+        return Err("Preset name must not be empty".to_string());
+    }
+
+    ScrcpyOptions::default().with_overrides(&preset.options)
+        .and_then(|options| options.validate())
+        .map_err(|e| format!("Invalid options in preset '{}': {}", preset.name, e))
+}
+
+/// Add a preset, or replace the one with the same name
+fn save_in(path: &Path, mut preset: Preset) -> Result<Preset, String> {
+    preset.name = preset.name.trim().to_string();
+    validate_preset(&preset)?;
+
+    let _lock = store::lock();
+    let mut library = read_library(path)?;
+    match library.position(&preset.name) {
+        Some(pos) => library.presets[pos] = preset.clone(),
+        None => library.presets.push(preset.clone()),
+    }
+
+    write_library(path, &library)?;
+    Ok(preset)
+}
+
+fn rename_in(path: &Path, name: &str, new_name: &str) -> Result<Preset, String> {
+    let new_name = new_name.trim();
+    if new_name.is_empty() {
+        return Err("Preset name must not be empty".to_string());
+    }
+
+    let _lock = store::lock();
+    let mut library = read_library(path)?;
+    let pos = library.position(name)
+        .ok_or_else(|| format!("Preset not found: {}", name))?;
+
+    if library.position(new_name).is_some_and(|other| other != pos) {
+        return Err(format!("A preset named '{}' already exists", new_name));
+    }
+
+    if library.default_preset.as_deref().is_some_and(|default| same_name(default, name)) {
+        library.default_preset = Some(new_name.to_string());
+    }
+
+    library.presets[pos].name = new_name.to_string();
+    let preset = library.presets[pos].clone();
+    write_library(path, &library)?;
+    Ok(preset)
+}
+
+fn delete_in(path: &Path, name: &str) -> Result<bool, String> {
+    let _lock = store::lock();
+    let mut library = read_library(path)?;
+    let Some(pos) = library.position(name) else {
+        return Ok(false);
+    };
+
+    library.presets.remove(pos);
+    if library.default_preset.as_deref().is_some_and(|default| same_name(default, name)) {
+        library.default_preset = None;
+    }
+
+    write_library(path, &library)?;
+    Ok(true)
+}
+
+fn set_default_in(path: &Path, name: Option<&str>) -> Result<(), String> {
+    let _lock = store::lock();
+    let mut library = read_library(path)?;
+
+    library.default_preset = match name {
+        Some(name) => {
+            let preset = library.find(name)
+                .ok_or_else(|| format!("Preset not found: {}", name))?;
+            Some(preset.name.clone())
+        }
+        None => None,
+    };
+
+    write_library(path, &library)
+}
+
+/// Write the named presets (all when None) to a file another install can import
+fn export_to(path: &Path, destination: &Path, names: Option<&[String]>) -> Result<usize, String> {
+    let library = {
+        let _lock = store::lock();
+        read_library(path)?
+    };
+
+    let presets: Vec<Preset> = match names {
+        Some(names) => names.iter()
+            .map(|name| library.find(name).cloned().ok_or_else(|| format!("Preset not found: {}", name)))
+            .collect::<Result<_, _>>()?,
+        None => library.presets.clone(),
+    };
+
+    let default_preset = library.default_preset
+        .filter(|default| presets.iter().any(|p| same_name(&p.name, default)));
+
+    let exported = PresetLibrary { presets, default_preset };
+    write_library(destination, &exported)?;
+    Ok(exported.presets.len())
+}
+
+/// Add the presets of an exported file. Presets whose name is taken are
+/// skipped unless `overwrite` is set; the default preset is only taken over
+/// when none is set yet.
+fn import_from(path: &Path, source: &Path, overwrite: bool) -> Result<PresetImport, String> {
+    if !source.exists() {
+        return Err(format!("Presets file not found: {}", source.display()));
+    }
+
+    let incoming = read_library(source)?;
+    let _lock = store::lock();
+    let mut library = read_library(path)?;
+    let mut report = PresetImport::default();
+
+    for mut preset in incoming.presets {
+        preset.name = preset.name.trim().to_string();
+
+        if let Err(reason) = validate_preset(&preset) {
+            report.skipped.push(SkippedPreset { name: preset.name, reason });
+            continue;
+        }
+
+        match library.position(&preset.name) {
+            Some(pos) if overwrite => library.presets[pos] = preset.clone(),
+            Some(_) => {
+                report.skipped.push(SkippedPreset {
+                    reason: format!("A preset named '{}' already exists", preset.name),
+                    name: preset.name,
+                });
+                continue;
+            }
+            None => library.presets.push(preset.clone()),
+        }
+        report.imported.push(preset.name);
+    }
+
+    if library.default_preset.is_none() {
+        library.default_preset = incoming.default_preset
+            .filter(|default| report.imported.iter().any(|name| same_name(name, default)));
+    }
+
+    write_library(path, &library)?;
+    Ok(report)
+}
+
+/// List all saved presets and the default one
+#[tauri::command]
+pub async fn list_presets(app_handle: tauri::AppHandle) -> Result<PresetLibrary, String> {
+    read_presets(&app_handle)
+}
+
+/// Create a preset, or update the one with the same name
+#[tauri::command]
+pub async fn save_preset(app_handle: tauri::AppHandle, preset: Preset) -> Result<Preset, String> {
+    save_in(&get_presets_path(&app_handle)?, preset)
+}
+
+/// Rename a preset, keeping it the default if it was
+#[tauri::command]
+pub async fn rename_preset(
+    app_handle: tauri::AppHandle,
+    name: String,
+    new_name: String,
+) -> Result<Preset, String> {
+    rename_in(&get_presets_path(&app_handle)?, &name, &new_name)
+}
+
+/// Delete a preset, returning false if it did not exist
+#[tauri::command]
+pub async fn delete_preset(app_handle: tauri::AppHandle, name: String) -> Result<bool, String> {
+    delete_in(&get_presets_path(&app_handle)?, &name)
+}
+
+/// Mark a preset as the default, or clear the default with None
+#[tauri::command]
+pub async fn set_default_preset(app_handle: tauri::AppHandle, name: Option<String>) -> Result<bool, String> {
+    set_default_in(&get_presets_path(&app_handle)?, name.as_deref())?;
+    Ok(true)
+}
+
+/// Export presets (all when no names are given) to a JSON file, returning how many were written
+#[tauri::command]
+pub async fn export_presets(
+    app_handle: tauri::AppHandle,
+    path: String,
+    names: Option<Vec<String>>,
+) -> Result<usize, String> {
+    export_to(&get_presets_path(&app_handle)?, Path::new(&path), names.as_deref())
+}
+
+/// Import presets from a JSON file written by `export_presets`
+#[tauri::command]
+pub async fn import_presets(
+    app_handle: tauri::AppHandle,
+    path: String,
+    overwrite: Option<bool>,
+) -> Result<PresetImport, String> {
+    import_from(&get_presets_path(&app_handle)?, Path::new(&path), overwrite.unwrap_or(false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn preset(name: &str, max_size: u32) -> Preset {
+        Preset {
+            name: name.to_string(),
+            description: None,
+            options: json!({ "max_size": max_size }).as_object().unwrap().clone(),
+        }
+    }
+
+    #[test]
+    fn test_save_and_resolve() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("presets.json");
+
+        save_in(&path, preset("Low bandwidth wireless", 1024)).unwrap();
+        save_in(&path, preset("Demo 1080p60", 1920)).unwrap();
+        // Same name in another case replaces it
+        save_in(&path, preset(" low bandwidth WIRELESS ", 800)).unwrap();
+
+        let library = read_library(&path).unwrap();
+        assert_eq!(library.presets.len(), 2);
+        assert_eq!(library.presets[0].name, "low bandwidth WIRELESS");
+
+        assert!(resolve_in(&library, None).unwrap().is_none());
+        assert_eq!(resolve_in(&library, Some("Demo 1080p60")).unwrap().unwrap().options["max_size"], 1920);
+        assert!(resolve_in(&library, Some("Camera")).is_err());
+
+        set_default_in(&path, Some("demo 1080P60")).unwrap();
+        let library = read_library(&path).unwrap();
+        assert_eq!(library.default_preset.as_deref(), Some("Demo 1080p60"));
+        assert_eq!(resolve_in(&library, None).unwrap().unwrap().options["max_size"], 1920);
+    }
+
+    #[test]
+    fn test_rejects_invalid_presets() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("presets.json");
+
+        assert!(save_in(&path, preset("  ", 1024)).is_err());
+
+        let mut recording = preset("Recording", 1920);
+        recording.options.insert("no_playback".to_string(), true.into());
+        let err = save_in(&path, recording).unwrap_err();
+        assert!(err.starts_with("Invalid options in preset 'Recording'"), "{}", err);
+        assert!(!path.exists());
+
+        let mut typo = preset("Typo", 1920);
+        typo.options.insert("max_sise".to_string(), 1280.into());
+        assert!(save_in(&path, typo).unwrap_err().contains("Unknown option: 'max_sise'"));
+    }
+
+    #[test]
+    fn test_rename_and_delete_follow_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("presets.json");
+        save_in(&path, preset("Demo", 1920)).unwrap();
+        save_in(&path, preset("Camera", 1280)).unwrap();
+        set_default_in(&path, Some("Demo")).unwrap();
+
+        assert!(rename_in(&path, "Demo", "camera").is_err());
+        rename_in(&path, "demo", "Demo 1080p60").unwrap();
+        assert_eq!(read_library(&path).unwrap().default_preset.as_deref(), Some("Demo 1080p60"));
+
+        assert!(delete_in(&path, "Demo 1080p60").unwrap());
+        assert!(!delete_in(&path, "Demo 1080p60").unwrap());
+        let library = read_library(&path).unwrap();
+        assert!(library.default_preset.is_none());
+        assert_eq!(library.presets.len(), 1);
+    }
+
+    #[test]
+    fn test_export_and_import() {
+        let dir = tempfile::tempdir().unwrap();
+        let team = dir.path().join("team.json");
+        let exported = dir.path().join("export.json");
+        let mine = dir.path().join("mine.json");
+
+        save_in(&team, preset("Demo 1080p60", 1920)).unwrap();
+        save_in(&team, preset("Low bandwidth wireless", 1024)).unwrap();
+        set_default_in(&team, Some("Demo 1080p60")).unwrap();
+        assert_eq!(export_to(&team, &exported, None).unwrap(), 2);
+        assert!(export_to(&team, &dir.path().join("x.json"), Some(&["Missing".to_string()])).is_err());
+
+        save_in(&mine, preset("demo 1080p60", 640)).unwrap();
+        let report = import_from(&mine, &exported, false).unwrap();
+        assert_eq!(report.imported, vec!["Low bandwidth wireless"]);
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].name, "Demo 1080p60");
+
+        let library = read_library(&mine).unwrap();
+        assert_eq!(library.presets.len(), 2);
+        assert_eq!(library.find("Demo 1080p60").unwrap().options["max_size"], 640);
+        // The team default was not imported, so it is not taken over
+        assert!(library.default_preset.is_none());
+
+        let report = import_from(&mine, &exported, true).unwrap();
+        assert_eq!(report.imported.len(), 2);
+        let library = read_library(&mine).unwrap();
+        assert_eq!(library.find("Demo 1080p60").unwrap().options["max_size"], 1920);
+        assert_eq!(library.default_preset.as_deref(), Some("Demo 1080p60"));
+
+        assert!(import_from(&mine, &dir.path().join("missing.json"), false).is_err());
+    }
+}
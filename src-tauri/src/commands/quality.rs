@@ -0,0 +1,311 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+use crate::adb::Adb;
+use crate::scrcpy::OptionOverrides;
+use crate::utils;
+use super::device::ConnectionType;
+
+/// How the resolution, bitrate and FPS of a session are chosen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QualityMode {
+    /// Use the values from the settings
+    #[default]
+    Manual,
+    /// Measure the link to the device before each session and pick values that fit
+    Auto,
+}
+
+/// Bytes read from the device to measure throughput
+pub const SAMPLE_BYTES: usize = 1024 * 1024;
+
+/// Times each command is run to measure latency; the quickest run counts
+const LATENCY_PROBES: u32 = 3;
+
+/// Wireless latency above which sessions are capped at 30 FPS
+const HIGH_LATENCY_MS: u32 = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QualityTier {
+    High,
+    Medium,
+    Low,
+    Minimal,
+}
+
+struct TierSettings {
+    tier: QualityTier,
+    label: &'static str,
+    bit_rate: u32,
+    max_size: u32,
+    max_fps: u32,
+}
+
+/// Best first
+const TIERS: [TierSettings; 4] = [
+    TierSettings { tier: QualityTier::High, label: "1080p", bit_rate: 8_000_000, max_size: 1920, max_fps: 60 },
+    TierSettings { tier: QualityTier::Medium, label: "720p", bit_rate: 4_000_000, max_size: 1280, max_fps: 60 },
+    TierSettings { tier: QualityTier::Low, label: "576p", bit_rate: 2_000_000, max_size: 1024, max_fps: 30 },
+    TierSettings { tier: QualityTier::Minimal, label: "450p", bit_rate: 1_000_000, max_size: 800, max_fps: 30 },
+];
+
+/// Speed of the adb link to a device
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LinkMeasurement {
+    /// Quickest round trip to the device, without the cost of starting adb, in milliseconds
+    pub latency_ms: u32,
+    /// Bits per second of a bulk transfer from the device
+    pub throughput_bps: u64,
+}
+
+/// Quality picked by the auto mode, and why
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityDecision {
+    pub connection_type: ConnectionType,
+    /// None when the link could not be measured
+    pub measurement: Option<LinkMeasurement>,
+    pub tier: QualityTier,
+    pub bit_rate: u32,
+    pub max_size: u32,
+    pub max_fps: u32,
+    /// Explanation for the user, such as "Wireless link measured 9.6 Mbps ..."
+    pub reason: String,
+}
+
+impl QualityDecision {
+    /// The chosen values as an option layer
+    pub fn to_overrides(&self) -> OptionOverrides {
+        let mut overrides = OptionOverrides::new();
+        overrides.insert("bit_rate".to_string(), self.bit_rate.into());
+        overrides.insert("max_size".to_string(), self.max_size.into());
+        overrides.insert("max_fps".to_string(), self.max_fps.into());
+        overrides
+    }
+}
+
+/// Quickest of a few runs of an adb command
+fn quickest<T>(mut run: impl FnMut() -> Result<T, String>) -> Result<Duration, String> {
+    let mut quickest = Duration::MAX;
+    for _ in 0..LATENCY_PROBES {
+        let start = Instant::now();
+        run()?;
+        quickest = quickest.min(start.elapsed());
+    }
+    Ok(quickest)
+}
+
+/// Time a few no-op commands and a transfer of `sample_bytes` from the device
+pub fn measure_link(adb: &Adb, serial: &str, sample_bytes: usize) -> Result<LinkMeasurement, String> {
+    // Every command pays for starting adb and reaching its server, which
+    // `get-state` does without going on to the device; what a no-op on the
+    // device takes on top of that is the round trip over the link
+    let local = quickest(|| adb.get_state(serial))?;
+    let round_trip = quickest(|| adb.exec_out(Some(serial), "echo"))?;
+    let latency = round_trip.saturating_sub(local);
+
+    let start = Instant::now();
+    let received = adb.exec_out(Some(serial), &format!("head -c {} /dev/zero", sample_bytes))?.len();
+    let transfer = start.elapsed().saturating_sub(round_trip).max(Duration::from_millis(1));
+
+    if received == 0 {
+        return Err("Failed to measure throughput: the device sent no data".to_string());
+    }
+
+    Ok(LinkMeasurement {
+        latency_ms: latency.as_millis().min(u32::MAX as u128) as u32,
+        throughput_bps: (received as f64 * 8.0 / transfer.as_secs_f64()) as u64,
+    })
+}
+
+/// Pick the best tier whose bitrate the measured throughput can carry, with
+/// twice the headroom over Wi-Fi since its throughput swings
+pub fn choose_quality(connection_type: ConnectionType, measurement: Result<LinkMeasurement, String>) -> QualityDecision {
+    let link = match connection_type {
+        ConnectionType::USB => "USB",
+        ConnectionType::Wireless => "Wireless",
+    };
+
+    let measured = match measurement {
+        Ok(measured) => measured,
+        Err(e) => {
+            // USB has bandwidth to spare; Wi-Fi gets the middle ground
+            let settings = match connection_type {
+                ConnectionType::USB => &TIERS[0],
+                ConnectionType::Wireless => &TIERS[1],
+            };
+            let reason = format!(
+                "Could not measure the {} link ({}), using {}",
+                link.to_lowercase(), e, describe(settings, settings.max_fps)
+            );
+            return decision(connection_type, None, settings, settings.max_fps, reason);
+        }
+    };
+
+    let headroom = match connection_type {
+        ConnectionType::USB => 1,
+        ConnectionType::Wireless => 2,
+    };
+    let settings = TIERS.iter()
+        .find(|settings| measured.throughput_bps >= settings.bit_rate as u64 * headroom)
+        .unwrap_or(&TIERS[TIERS.len() - 1]);
+
+    let capped = connection_type == ConnectionType::Wireless
+        && measured.latency_ms > HIGH_LATENCY_MS
+        && settings.max_fps > 30;
+    let max_fps = if capped { 30 } else { settings.max_fps };
+
+    let mut reason = format!(
+        "{} link measured {:.1} Mbps with {} ms latency, using {}",
+        link, measured.throughput_bps as f64 / 1_000_000.0, measured.latency_ms, describe(settings, max_fps)
+    );
+    if capped {
+        reason.push_str(" to keep up with the latency");
+    }
+
+    decision(connection_type, Some(measured), settings, max_fps, reason)
+}
+
+fn describe(settings: &TierSettings, max_fps: u32) -> String {
+    format!(
+        "{} at {} Mbps and {} fps",
+        settings.label, settings.bit_rate / 1_000_000, max_fps
+    )
+}
+
+fn decision(
+    connection_type: ConnectionType,
+    measurement: Option<LinkMeasurement>,
+    settings: &TierSettings,
+    max_fps: u32,
+    reason: String,
+) -> QualityDecision {
+    QualityDecision {
+        connection_type,
+        measurement,
+        tier: settings.tier,
+        bit_rate: settings.bit_rate,
+        max_size: settings.max_size,
+        max_fps,
+        reason,
+    }
+}
+
+/// Measure the link to a device and pick the quality for it
+pub fn auto_quality(adb: &Adb, device_id: &str) -> QualityDecision {
+    choose_quality(ConnectionType::of_serial(device_id), measure_link(adb, device_id, SAMPLE_BYTES))
+}
+
+/// Measure the link to a device and show the quality the auto mode would pick
+#[tauri::command]
+pub async fn measure_quality(app: tauri::AppHandle, device_id: String) -> Result<QualityDecision, String> {
+    let adb = Adb::new(utils::get_adb_path(&app)?);
+    tokio::task::spawn_blocking(move || auto_quality(&adb, &device_id))
+        .await
+        .map_err(|e| format!("Measurement task failed: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adb::fake::FakeAdb;
+    use serde_json::json;
+
+    const SERIAL: &str = "192.168.1.42:5555";
+    const SAMPLE_BYTES: usize = 64 * 1024;
+
+    fn measured(mbps: f64, latency_ms: u32) -> Result<LinkMeasurement, String> {
+        Ok(LinkMeasurement {
+            latency_ms,
+            throughput_bps: (mbps * 1_000_000.0) as u64,
+        })
+    }
+
+    #[test]
+    fn test_choose_quality_usb() {
+        let decision = choose_quality(ConnectionType::USB, measured(280.0, 2));
+        assert_eq!(decision.tier, QualityTier::High);
+        assert_eq!((decision.bit_rate, decision.max_size, decision.max_fps), (8_000_000, 1920, 60));
+        assert_eq!(decision.reason, "USB link measured 280.0 Mbps with 2 ms latency, using 1080p at 8 Mbps and 60 fps");
+
+        // Same throughput is enough over USB but not over Wi-Fi
+        assert_eq!(choose_quality(ConnectionType::USB, measured(9.0, 3)).tier, QualityTier::High);
+        assert_eq!(choose_quality(ConnectionType::Wireless, measured(9.0, 3)).tier, QualityTier::Medium);
+    }
+
+    #[test]
+    fn test_choose_quality_wireless() {
+        let decision = choose_quality(ConnectionType::Wireless, measured(9.6, 12));
+        assert_eq!(decision.tier, QualityTier::Medium);
+        assert_eq!((decision.bit_rate, decision.max_size, decision.max_fps), (4_000_000, 1280, 60));
+        assert_eq!(decision.reason, "Wireless link measured 9.6 Mbps with 12 ms latency, using 720p at 4 Mbps and 60 fps");
+        assert_eq!(decision.to_overrides()["max_size"], 1280);
+
+        assert_eq!(choose_quality(ConnectionType::Wireless, measured(32.0, 5)).tier, QualityTier::High);
+        assert_eq!(choose_quality(ConnectionType::Wireless, measured(4.5, 5)).tier, QualityTier::Low);
+        // Slower than every tier still gets the smallest one
+        assert_eq!(choose_quality(ConnectionType::Wireless, measured(0.4, 5)).tier, QualityTier::Minimal);
+
+        // Busy Wi-Fi with a slow round trip
+        let decision = choose_quality(ConnectionType::Wireless, measured(12.0, 120));
+        assert_eq!(decision.tier, QualityTier::Medium);
+        assert_eq!(decision.max_fps, 30);
+        assert!(decision.reason.ends_with("720p at 4 Mbps and 30 fps to keep up with the latency"), "{}", decision.reason);
+    }
+
+    #[test]
+    fn test_choose_quality_unmeasured() {
+        let error = || Err("ADB command failed: error: closed".to_string());
+
+        let decision = choose_quality(ConnectionType::Wireless, error());
+        assert!(decision.measurement.is_none());
+        assert_eq!(decision.tier, QualityTier::Medium);
+        assert_eq!(
+            decision.reason,
+            "Could not measure the wireless link (ADB command failed: error: closed), using 720p at 4 Mbps and 60 fps"
+        );
+
+        assert_eq!(choose_quality(ConnectionType::USB, error()).tier, QualityTier::High);
+    }
+
+    #[test]
+    fn test_measure_link() {
+        // 64 KiB in at least 400 ms is 1.3 Mbps; the bound leaves room for the
+        // startup cost subtracted from it varying by up to 100 ms on a loaded machine
+        let fake = FakeAdb::new("measure_link")
+            .on("-s * get-state", "device\n")
+            .on_calls("-s * exec-out echo", vec![json!({ "stdout": "\n", "delay_ms": 80 })])
+            .on_calls("-s * exec-out head -c 65536 /dev/zero", vec![
+                json!({ "stdout": "0".repeat(SAMPLE_BYTES), "delay_ms": 400 }),
+            ]);
+
+        let measured = measure_link(&fake.adb(), SERIAL, SAMPLE_BYTES).unwrap();
+        assert!(measured.throughput_bps > 0);
+        assert!(measured.throughput_bps <= 1_750_000, "{:?}", measured);
+        // The device's 80 ms, without what starting adb costs every command
+        assert!((60..200).contains(&measured.latency_ms), "{:?}", measured);
+        assert_eq!(fake.count("-s 192.168.1.42:5555 get-state"), 3);
+        assert_eq!(fake.count("-s 192.168.1.42:5555 exec-out echo"), 3);
+
+        let decision = choose_quality(ConnectionType::Wireless, Ok(measured));
+        assert!(matches!(decision.tier, QualityTier::Low | QualityTier::Minimal), "{:?}", decision);
+        assert_eq!(decision.measurement, Some(measured));
+    }
+
+    #[test]
+    fn test_measure_link_failures() {
+        let fake = FakeAdb::new("measure_link_offline")
+            .fail("-s * get-state", "error: device offline\n");
+        assert_eq!(
+            measure_link(&fake.adb(), SERIAL, SAMPLE_BYTES),
+            Err("ADB command failed: error: device offline".to_string())
+        );
+
+        let fake = FakeAdb::new("measure_link_empty")
+            .on("-s * get-state", "device\n")
+            .on("-s * exec-out echo", "\n")
+            .on("-s * exec-out head -c 65536 /dev/zero", "");
+        assert_eq!(
+            measure_link(&fake.adb(), SERIAL, SAMPLE_BYTES),
+            Err("Failed to measure throughput: the device sent no data".to_string())
+        );
+    }
+}
@@ -0,0 +1,417 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::{Manager, State};
+use crate::scrcpy::{RecordFormat, ScrcpyOptions, ScrcpyState};
+use super::store;
+
+/// A recorded session registered in the local library
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recording {
+    pub id: String,
+    pub session_id: String,
+    pub device_id: String,
+    pub file_path: String,
+    pub format: Option<RecordFormat>,
+    pub options: ScrcpyOptions,
+    /// RFC3339 timestamps
+    pub started_at: String,
+    pub stopped_at: Option<String>,
+    pub duration_secs: Option<f64>,
+    pub size_bytes: Option<u64>,
+}
+
+fn get_app_data_dir<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+    app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))
+}
+
+fn get_recordings_index_path<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+    Ok(get_app_data_dir(app_handle)?.join("recordings.json"))
+}
+
+/// Make a relative record path absolute, placing it in the app recordings directory.
+///
+/// scrcpy runs from its resource directory, so a bare file name would
+/// otherwise end up next to the bundled binaries.
+pub fn prepare_record_path<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, options: &mut ScrcpyOptions) -> Result<(), String> {
+    let Some(record_path) = options.record_path.as_ref() else {
+        return Ok(());
+    };
+
+    let mut path = PathBuf::from(record_path);
+    if path.is_relative() {
+        path = get_app_data_dir(app_handle)?.join("recordings").join(path);
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create recordings directory: {}", e))?;
+    }
+
+    if path.exists() {
+        return Err(format!("Recording file already exists: {}", path.display()));
+    }
+
+    options.record_path = Some(path.to_string_lossy().to_string());
+    Ok(())
+}
+
+/// Add a new in-progress recording to the library
+pub fn register_recording<R: tauri::Runtime>(
+    app_handle: &tauri::AppHandle<R>,
+    session_id: &str,
+    device_id: &str,
+    options: &ScrcpyOptions,
+) -> Result<Recording, String> {
+    let index_path = get_recordings_index_path(app_handle)?;
+    register_in_index(&index_path, session_id, device_id, options, Utc::now())
+}
+
+/// Mark a recording as stopped, recording its duration and final size
+pub fn finalize_recording<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, recording_id: &str) -> Result<Option<Recording>, String> {
+    let index_path = get_recordings_index_path(app_handle)?;
+    finalize_in_index(&index_path, recording_id, Utc::now())
+}
+
+fn read_index(index_path: &Path) -> Result<Vec<Recording>, String> {
+    if !index_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let json = fs::read_to_string(index_path)
+        .map_err(|e| format!("Failed to read recordings index: {}", e))?;
+
+    serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse recordings index: {}", e))
+}
+
+fn write_index(index_path: &Path, recordings: &[Recording]) -> Result<(), String> {
+    if let Some(parent) = index_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create recordings directory: {}", e))?;
+    }
+
+    let json = serde_json::to_string_pretty(recordings)
+        .map_err(|e| format!("Failed to serialize recordings index: {}", e))?;
+
+    fs::write(index_path, json)
+        .map_err(|e| format!("Failed to write recordings index: {}", e))
+}
+
+fn register_in_index(
+    index_path: &Path,
+    session_id: &str,
+    device_id: &str,
+    options: &ScrcpyOptions,
+    now: DateTime<Utc>,
+) -> Result<Recording, String> {
+    let _lock = store::lock();
+    let file_path = options.record_path.clone()
+        .ok_or_else(|| "Session has no recording path".to_string())?;
+
+    let mut recordings = read_index(index_path)?;
+
+    let mut id = format!("rec_{}", now.timestamp_millis());
+    let mut suffix = 1;
+    while recordings.iter().any(|r| r.id == id) {
+        id = format!("rec_{}_{}", now.timestamp_millis(), suffix);
+        suffix += 1;
+    }
+
+    let recording = Recording {
+        id,
+        session_id: session_id.to_string(),
+        device_id: device_id.to_string(),
+        file_path,
+        format: options.effective_record_format(),
+        options: options.clone(),
+        started_at: now.to_rfc3339(),
+        stopped_at: None,
+        duration_secs: None,
+        size_bytes: None,
+    };
+
+    recordings.push(recording.clone());
+    write_index(index_path, &recordings)?;
+
+    Ok(recording)
+}
+
+/// Fill in stop time, duration and size for a recording
+fn finalize_entry(recording: &mut Recording, stopped_at: DateTime<Utc>) {
+    let metadata = fs::metadata(&recording.file_path).ok();
+
+    recording.size_bytes = metadata.as_ref().map(|m| m.len());
+    recording.stopped_at = Some(stopped_at.to_rfc3339());
+    recording.duration_secs = DateTime::parse_from_rfc3339(&recording.started_at)
+        .ok()
+        .map(|started| (stopped_at - started.with_timezone(&Utc)).num_milliseconds().max(0) as f64 / 1000.0);
+}
+
+fn finalize_in_index(index_path: &Path, recording_id: &str, now: DateTime<Utc>) -> Result<Option<Recording>, String> {
+    let _lock = store::lock();
+    let mut recordings = read_index(index_path)?;
+
+    let Some(recording) = recordings.iter_mut().find(|r| r.id == recording_id) else {
+        return Ok(None);
+    };
+
+    finalize_entry(recording, now);
+    let recording = recording.clone();
+    write_index(index_path, &recordings)?;
+
+    Ok(Some(recording))
+}
+
+/// Finalize recordings whose session ended without going through `stop_mirroring`
+/// (time limit reached, window closed, app crash). The file modification time
+/// is the best estimate of when writing stopped.
+fn reconcile_index(index_path: &Path, is_running: impl Fn(&str) -> bool) -> Result<Vec<Recording>, String> {
+    let _lock = store::lock();
+    let mut recordings = read_index(index_path)?;
+    let mut changed = false;
+
+    for recording in recordings.iter_mut() {
+        if recording.stopped_at.is_some() || is_running(&recording.session_id) {
+            continue;
+        }
+
+        let stopped_at = fs::metadata(&recording.file_path)
+            .and_then(|m| m.modified())
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_else(|_| Utc::now());
+
+        finalize_entry(recording, stopped_at);
+        changed = true;
+    }
+
+    if changed {
+        write_index(index_path, &recordings)?;
+    }
+
+    Ok(recordings)
+}
+
+fn rename_in_index(index_path: &Path, recording_id: &str, new_name: &str) -> Result<Recording, String> {
+    let new_name = new_name.trim();
+    if new_name.is_empty() || new_name.contains(['/', '\\']) || new_name == "." || new_name == ".." {
+        return Err(format!("Invalid recording name: '{}'", new_name));
+    }
+
+    let _lock = store::lock();
+    let mut recordings = read_index(index_path)?;
+
+    let recording = recordings.iter_mut().find(|r| r.id == recording_id)
+        .ok_or_else(|| format!("Recording not found: {}", recording_id))?;
+
+    let old_path = PathBuf::from(&recording.file_path);
+
+    // Keep the original extension unless the new name provides one
+    let mut file_name = new_name.to_string();
+    if Path::new(new_name).extension().is_none() {
+        if let Some(extension) = old_path.extension() {
+            file_name = format!("{}.{}", new_name, extension.to_string_lossy());
+        }
+    }
+
+    let new_path = old_path.with_file_name(file_name);
+    if new_path.exists() {
+        return Err(format!("A file named {} already exists", new_path.display()));
+    }
+
+    fs::rename(&old_path, &new_path)
+        .map_err(|e| format!("Failed to rename recording: {}", e))?;
+
+    recording.file_path = new_path.to_string_lossy().to_string();
+    let recording = recording.clone();
+    write_index(index_path, &recordings)?;
+
+    Ok(recording)
+}
+
+fn delete_from_index(index_path: &Path, recording_id: &str, delete_file: bool) -> Result<bool, String> {
+    let _lock = store::lock();
+    let mut recordings = read_index(index_path)?;
+
+    let Some(pos) = recordings.iter().position(|r| r.id == recording_id) else {
+        return Ok(false);
+    };
+
+    let recording = recordings.remove(pos);
+
+    if delete_file {
+        match fs::remove_file(&recording.file_path) {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => return Err(format!("Failed to delete recording file: {}", e)),
+        }
+    }
+
+    write_index(index_path, &recordings)?;
+    Ok(true)
+}
+
+/// List all recordings in the library
+#[tauri::command]
+pub async fn list_recordings(
+    app_handle: tauri::AppHandle,
+    state: State<'_, ScrcpyState>,
+) -> Result<Vec<Recording>, String> {
+    state.cleanup_finished()?;
+
+    let index_path = get_recordings_index_path(&app_handle)?;
+    reconcile_index(&index_path, |session_id| state.is_running(session_id))
+}
+
+/// Rename a recording file, keeping its extension unless a new one is given
+#[tauri::command]
+pub async fn rename_recording(
+    app_handle: tauri::AppHandle,
+    state: State<'_, ScrcpyState>,
+    recording_id: String,
+    new_name: String,
+) -> Result<Recording, String> {
+    let index_path = get_recordings_index_path(&app_handle)?;
+    ensure_not_in_progress(&index_path, &state, &recording_id)?;
+    rename_in_index(&index_path, &recording_id, &new_name)
+}
+
+/// Remove a recording from the library, deleting the file unless told otherwise
+#[tauri::command]
+pub async fn delete_recording(
+    app_handle: tauri::AppHandle,
+    state: State<'_, ScrcpyState>,
+    recording_id: String,
+    delete_file: Option<bool>,
+) -> Result<bool, String> {
+    let index_path = get_recordings_index_path(&app_handle)?;
+    ensure_not_in_progress(&index_path, &state, &recording_id)?;
+    delete_from_index(&index_path, &recording_id, delete_file.unwrap_or(true))
+}
+
+fn ensure_not_in_progress(index_path: &Path, state: &ScrcpyState, recording_id: &str) -> Result<(), String> {
+    state.cleanup_finished()?;
+
+    let _lock = store::lock();
+    let recordings = read_index(index_path)?;
+    if let Some(recording) = recordings.iter().find(|r| r.id == recording_id) {
+        if state.is_running(&recording.session_id) {
+            return Err("Recording is still in progress. Stop the session first.".to_string());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recording_options(path: &Path) -> ScrcpyOptions {
+        ScrcpyOptions {
+            record_path: Some(path.to_string_lossy().to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_register_and_finalize() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = dir.path().join("recordings.json");
+        let file = dir.path().join("demo.mp4");
+        fs::write(&file, vec![0u8; 1024]).unwrap();
+
+        let started = Utc::now();
+        let recording = register_in_index(&index, "session_1", "device1", &recording_options(&file), started).unwrap();
+        assert_eq!(recording.format, Some(RecordFormat::Mp4));
+        assert!(recording.stopped_at.is_none());
+
+        let stopped = started + chrono::Duration::seconds(90);
+        let recording = finalize_in_index(&index, &recording.id, stopped).unwrap().unwrap();
+        assert_eq!(recording.size_bytes, Some(1024));
+        assert_eq!(recording.duration_secs, Some(90.0));
+        assert!(recording.stopped_at.is_some());
+
+        assert_eq!(read_index(&index).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_register_unique_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = dir.path().join("recordings.json");
+        let options = recording_options(&dir.path().join("a.mkv"));
+        let now = Utc::now();
+
+        let first = register_in_index(&index, "s1", "d", &options, now).unwrap();
+        let second = register_in_index(&index, "s2", "d", &options, now).unwrap();
+        assert_ne!(first.id, second.id);
+    }
+
+    #[test]
+    fn test_concurrent_registrations() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = dir.path().join("recordings.json");
+
+        let handles: Vec<_> = (0..8).map(|i| {
+            let index = index.clone();
+            let options = recording_options(&dir.path().join(format!("{}.mp4", i)));
+            std::thread::spawn(move || register_in_index(&index, &format!("s{}", i), "d", &options, Utc::now()).unwrap())
+        }).collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(read_index(&index).unwrap().len(), 8);
+    }
+
+    #[test]
+    fn test_reconcile_finished_sessions() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = dir.path().join("recordings.json");
+        let now = Utc::now();
+
+        register_in_index(&index, "running", "d", &recording_options(&dir.path().join("a.mp4")), now).unwrap();
+        register_in_index(&index, "ended", "d", &recording_options(&dir.path().join("b.mp4")), now).unwrap();
+
+        let recordings = reconcile_index(&index, |session_id| session_id == "running").unwrap();
+        let running = recordings.iter().find(|r| r.session_id == "running").unwrap();
+        let ended = recordings.iter().find(|r| r.session_id == "ended").unwrap();
+        assert!(running.stopped_at.is_none());
+        assert!(ended.stopped_at.is_some());
+    }
+
+    #[test]
+    fn test_rename_keeps_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = dir.path().join("recordings.json");
+        let file = dir.path().join("demo.mkv");
+        fs::write(&file, b"data").unwrap();
+
+        let recording = register_in_index(&index, "s", "d", &recording_options(&file), Utc::now()).unwrap();
+        let renamed = rename_in_index(&index, &recording.id, "Sprint demo").unwrap();
+
+        assert!(renamed.file_path.ends_with("Sprint demo.mkv"));
+        assert!(Path::new(&renamed.file_path).exists());
+        assert!(!file.exists());
+
+        assert!(rename_in_index(&index, &recording.id, "../escape").is_err());
+        assert!(rename_in_index(&index, "missing", "x").is_err());
+    }
+
+    #[test]
+    fn test_delete_recording() {
+        let dir = tempfile::tempdir().unwrap();
+        let index = dir.path().join("recordings.json");
+        let file = dir.path().join("demo.mp4");
+        fs::write(&file, b"data").unwrap();
+
+        let recording = register_in_index(&index, "s", "d", &recording_options(&file), Utc::now()).unwrap();
+        assert!(delete_from_index(&index, &recording.id, true).unwrap());
+        assert!(!file.exists());
+        assert!(read_index(&index).unwrap().is_empty());
+        assert!(!delete_from_index(&index, &recording.id, true).unwrap());
+    }
+}
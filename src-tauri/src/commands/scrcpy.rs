@@ -1,99 +1,881 @@
 use serde::{Deserialize, Serialize};
-use tauri::State;
-use crate::scrcpy::{self, ScrcpyOptions, ScrcpyState, ProcessInfo};
+use tauri::{Emitter, State};
+use crate::adb::Adb;
+use crate::scrcpy::{self, AdoptedProcess, AppInfo, CameraInfo, DisplayInfo, EncoderInfo, ExitReason, LogLine, NewDisplay, OptionOverrides, OrphanPolicy, ParsedScrcpyArgs, RestartPolicy, ScrcpyCapabilities, ScrcpyOptions, ScrcpyState, ProcessInfo, ProcessState, ResourceMonitor, SessionExit, SessionPolicy, SessionFailure, SessionLog, SessionLogs, SessionPoll, SessionProcess, SessionResources, SessionTarget, ShutdownMethod, ShutdownReport, StartApp, TargetClaim};
+use crate::utils;
+use super::{effective, history, orphans, recordings, settings, windows};
+use super::quality::QualityDecision;
+
+/// How often session monitors check whether scrcpy is still running
+const MONITOR_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Longest time a restarting session waits for its device to come back
+const DEVICE_WAIT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How often a restarting session checks whether its device is back
+const DEVICE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// How often the CPU and memory use of sessions is sampled and reported
+const RESOURCE_SAMPLE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MirrorSession {
     pub session_id: String,
     pub device_id: String,
     pub status: SessionStatus,
+    /// RFC3339 timestamp
     pub started_at: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SessionStatus {
     Running,
+    /// Waiting to be relaunched by its restart policy
+    Reconnecting,
     Stopped,
     Error,
 }
 
+/// Payload of the `session-quality` event, emitted when the auto quality mode
+/// picked the options of a new session
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionQualityEvent {
+    pub session_id: String,
+    pub decision: QualityDecision,
+}
+
+/// Payload of the `session-warning` event, emitted for each option dropped
+/// because the scrcpy in use does not have it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionWarningEvent {
+    pub session_id: String,
+    pub message: String,
+}
+
+/// Payload of the `session-log` event, emitted for every line of scrcpy output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionLogEvent {
+    pub session_id: String,
+    pub line: LogLine,
+    /// Set when the line reports a known fatal error
+    pub failure: Option<SessionFailure>,
+}
+
+/// Payload of the `session-exited` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionExitedEvent {
+    pub session_id: String,
+    pub device_id: String,
+    #[serde(flatten)]
+    pub exit: SessionExit,
+}
+
+/// Payload of the `session-error` event, emitted when a session ends abnormally
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionErrorEvent {
+    pub session_id: String,
+    pub device_id: String,
+    pub failure: Option<SessionFailure>,
+    /// Last error printed by scrcpy
+    pub message: String,
+}
+
+/// Payload of the `session-restarting` event, emitted before each relaunch attempt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRestartEvent {
+    pub session_id: String,
+    pub device_id: String,
+    /// 1 for the first restart after an exit
+    pub attempt: u32,
+    pub delay_ms: u64,
+    /// Why the session ended
+    pub reason: ExitReason,
+    /// Last error, from scrcpy or from the previous attempt
+    pub error: Option<String>,
+}
+
+/// Resource use of one session, as sent in the `session-resources` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionResourceUsage {
+    pub session_id: String,
+    pub device_id: String,
+    #[serde(flatten)]
+    pub resources: SessionResources,
+}
+
+/// Command line a session would run, with the options the scrcpy in use drops
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandPreview {
+    pub command: String,
+    pub warnings: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessStats {
     pub active_sessions: usize,
+    /// Runs in the session history, including failed starts and restarts
     pub total_started: usize,
 }
 
-/// Start screen mirroring for a device
+/// Start screen mirroring for a device.
+///
+/// Returns once scrcpy reports that it is up (or after `STARTUP_TIMEOUT`), and
+/// fails with the captured stderr if scrcpy exits during startup. With a
+/// `restart_policy`, sessions that exit on their own are relaunched under the
+/// same session id once the device is reachable again.
+///
+/// The options are the saved settings, then `preset` (or the default preset),
+/// then the auto quality pick, then the overrides of the saved device, then
+/// the fields given in `options`. With auto quality on, the measurement and
+/// the pick are reported in a `session-quality` event.
+///
+/// If the device already has a session on the same display or camera,
+/// `session_policy` (the saved setting by default) decides whether to fail,
+/// return the existing session id, or replace that session.
 #[tauri::command]
 pub async fn start_mirroring(
     app: tauri::AppHandle,
     state: State<'_, ScrcpyState>,
     device_id: String,
-    options: Option<ScrcpyOptions>,
+    options: Option<OptionOverrides>,
+    restart_policy: Option<RestartPolicy>,
+    session_policy: Option<SessionPolicy>,
+    preset: Option<String>,
 ) -> Result<String, String> {
-    let opts = options.unwrap_or_default();
+    let effective = effective::spawn_resolve_options(app.clone(), device_id.clone(), preset, options).await?;
+    let opts = effective.options;
+    let restart = restart_policy.unwrap_or_default();
+    let policy = match session_policy {
+        Some(policy) => policy,
+        None => settings::read_settings(&app)?.session_policy,
+    };
+    
+    // A relaunch would need a new file, and the library tracks one file per session
+    if restart != RestartPolicy::Never && opts.record_path.is_some() {
+        return Err("Automatic restart is not available for recording sessions".to_string());
+    }
+    
+    let session_id = spawn_start_session(app.clone(), &state, device_id, opts, restart, policy).await?;
+    
+    if let Some(decision) = effective.quality {
+        emit_quality(&app, &session_id, decision);
+    }
     
+    Ok(session_id)
+}
+
+/// Launch an app on a new virtual display, mirrored in its own window.
+///
+/// Each call starts a separate session, so several apps from one device can
+/// be shown side by side. Requires scrcpy 3.0 or newer. The options are
+/// layered as in `start_mirroring`, then the virtual display and the app are
+/// set on top.
+#[tauri::command]
+pub async fn launch_app_in_window(
+    app: tauri::AppHandle,
+    state: State<'_, ScrcpyState>,
+    device_id: String,
+    package: String,
+    options: Option<OptionOverrides>,
+    preset: Option<String>,
+) -> Result<String, String> {
+    let effective = effective::spawn_resolve_options(app.clone(), device_id.clone(), preset, options).await?;
+    let mut opts = effective.options;
+    opts.new_display.get_or_insert_with(NewDisplay::default);
+    opts.start_app = Some(StartApp {
+        package,
+        force_stop: false,
+        search: false,
+    });
+    
+    // Every app gets its own virtual display, so there is nothing to conflict with
+    let session_id = spawn_start_session(app.clone(), &state, device_id, opts, RestartPolicy::Never, SessionPolicy::Reject).await?;
+    
+    if let Some(decision) = effective.quality {
+        emit_quality(&app, &session_id, decision);
+    }
+    
+    Ok(session_id)
+}
+
+/// Report the auto quality pick a session was started with
+fn emit_quality(app: &tauri::AppHandle, session_id: &str, decision: QualityDecision) {
+    let event = SessionQualityEvent {
+        session_id: session_id.to_string(),
+        decision,
+    };
+    if let Err(e) = app.emit("session-quality", event) {
+        eprintln!("Failed to emit session quality: {}", e);
+    }
+}
+
+/// Run `start_session` off the async runtime, since it waits for scrcpy to start up
+async fn spawn_start_session(
+    app: tauri::AppHandle,
+    state: &ScrcpyState,
+    device_id: String,
+    opts: ScrcpyOptions,
+    restart: RestartPolicy,
+    policy: SessionPolicy,
+) -> Result<String, String> {
+    let state = state.clone();
+    tokio::task::spawn_blocking(move || start_session(&app, &state, &device_id, opts, restart, policy))
+        .await
+        .map_err(|e| format!("Start task failed: {}", e))?
+}
+
+/// Spawn scrcpy for a device and track it as a new session
+fn start_session<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    state: &ScrcpyState,
+    device_id: &str,
+    mut opts: ScrcpyOptions,
+    restart: RestartPolicy,
+    policy: SessionPolicy,
+) -> Result<String, String> {
     // Clean up any finished processes first
     state.cleanup_finished()?;
     
-    // Execute scrcpy
-    let child = scrcpy::execute_scrcpy(
-        &app,
-        Some(&device_id),
-        &opts,
-    )?;
+    // Two scrcpy instances on the same display fight over the server, so
+    // only one session per device and target is allowed
+    let target = resolve_target(app, device_id, &opts);
+    let _reservation = match target {
+        Some(ref target) => Some(loop {
+            match state.claim_target(device_id, target)? {
+                TargetClaim::Claimed(reservation) => break reservation,
+                TargetClaim::Starting => {
+                    return Err(format!("Device {} is already starting a session on {}", device_id, target));
+                }
+                TargetClaim::Taken(existing) => match policy {
+                    SessionPolicy::Reject => {
+                        return Err(format!(
+                            "Device {} already has a session on {}: {}",
+                            device_id, target, existing
+                        ));
+                    }
+                    SessionPolicy::Reuse => {
+                        println!("Reusing session {} for device {}", existing, device_id);
+                        return Ok(existing);
+                    }
+                    SessionPolicy::Replace => {
+                        println!("Replacing session {} for device {}", existing, device_id);
+                        stop_session(app, state, &existing, scrcpy::DEFAULT_SHUTDOWN_GRACE)?;
+                    }
+                },
+            }
+        }),
+        None => None,
+    };
     
-    let session_id = format!("session_{}_{}", device_id, child.id());
+    recordings::prepare_record_path(app, &mut opts)?;
+    
+    let (session_id, child, log) = launch_scrcpy(app, state, device_id, &opts, None)?;
+    
+    // Register the recording in the library so it can be listed while in progress
+    let recording_id = if opts.record_path.is_some() {
+        match recordings::register_recording(app, &session_id, device_id, &opts) {
+            Ok(recording) => Some(recording.id),
+            Err(e) => {
+                eprintln!("Failed to register recording for session {}: {}", session_id, e);
+                None
+            }
+        }
+    } else {
+        None
+    };
     
-    // Store the process
     let process_info = ProcessInfo {
-        child,
-        device_id: device_id.clone(),
+        process: child,
+        device_id: device_id.to_string(),
         started_at: std::time::SystemTime::now(),
+        target: target.clone(),
+        recording_id,
+        log,
+        resources: ResourceMonitor::new(),
     };
-    
-    state.add_process(session_id.clone(), process_info)?;
+    track_session(app, state, &session_id, &opts, restart, process_info)?;
     
     println!("Started mirroring session: {} for device: {}", session_id, device_id);
     
+    let monitor = SessionMonitor {
+        app: app.clone(),
+        state: state.clone(),
+        session_id: session_id.clone(),
+        device_id: device_id.to_string(),
+        options: opts,
+        target,
+        restart,
+    };
+    std::thread::spawn(move || monitor.run());
+    
     Ok(session_id)
 }
 
-/// Stop screen mirroring for a device
+/// Target of a session, with the camera looked up on the device so the same
+/// camera compares equal however it was asked for
+fn resolve_target<R: tauri::Runtime>(app: &tauri::AppHandle<R>, device_id: &str, opts: &ScrcpyOptions) -> Option<SessionTarget> {
+    if !opts.is_camera() || opts.camera_id.is_some() {
+        return opts.session_target();
+    }
+    
+    match scrcpy::run_list_command(app, device_id, "--list-cameras") {
+        Ok(output) => opts.resolve_session_target(&scrcpy::parse_camera_list(&output)),
+        Err(e) => {
+            eprintln!("Failed to list cameras of {}: {}", device_id, e);
+            opts.session_target()
+        }
+    }
+}
+
+/// Spawn scrcpy, stream its output and wait for it to start up.
+///
+/// The session id is derived from the process id unless an existing session
+/// is being relaunched.
+fn launch_scrcpy<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    state: &ScrcpyState,
+    device_id: &str,
+    opts: &ScrcpyOptions,
+    session_id: Option<&str>,
+) -> Result<(String, SessionProcess, SessionLog), String> {
+    let started_at = std::time::SystemTime::now();
+    
+    // Execute scrcpy
+    let (mut child, warnings) = scrcpy::execute_scrcpy(
+        app,
+        state.launcher(),
+        Some(device_id),
+        opts,
+    )?;
+    
+    let session_id = session_id
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("session_{}_{}", device_id, child.id()));
+    
+    for message in warnings {
+        eprintln!("Session {}: {}", session_id, message);
+        let event = SessionWarningEvent {
+            session_id: session_id.clone(),
+            message,
+        };
+        if let Err(e) = app.emit("session-warning", event) {
+            eprintln!("Failed to emit session warning: {}", e);
+        }
+    }
+    
+    let log = SessionLog::new();
+    let emitter = app.clone();
+    let log_session_id = session_id.clone();
+    scrcpy::capture_output(child.as_mut(), &log, move |line, failure| {
+        let event = SessionLogEvent {
+            session_id: log_session_id.clone(),
+            line: line.clone(),
+            failure,
+        };
+        if let Err(e) = emitter.emit("session-log", event) {
+            eprintln!("Failed to emit session log: {}", e);
+        }
+    });
+    
+    // Report devices that are unauthorized, encoders that fail, etc. as start errors
+    // instead of handing out a session that disappears a moment later
+    if let Err(e) = scrcpy::wait_for_startup(child.as_mut(), &log, scrcpy::STARTUP_TIMEOUT) {
+        eprintln!("Session {} failed to start: {}", session_id, e);
+        let status = match child.try_wait() {
+            Ok(ProcessState::Exited(status)) => status,
+            _ => None,
+        };
+        let _ = child.kill();
+        let _ = child.wait();
+        state.record_exit(&session_id, &log, None);
+        
+        // Failed starts count against the device's reliability too
+        let exit = SessionExit {
+            code: status.and_then(|s| s.code()),
+            signal: status.and_then(scrcpy::exit_signal),
+            duration_secs: started_at.elapsed().map(|d| d.as_secs_f64()).unwrap_or(0.0),
+            reason: ExitReason::Failed,
+            failure: log.failure(),
+        };
+        let mut record = history::SessionRecord::new(&session_id, device_id, opts, started_at);
+        record.model = device_model(app, device_id);
+        record.finish(&exit, chrono::Utc::now());
+        if let Err(e) = history::record_session_start(app, record) {
+            eprintln!("Failed to record session {} in history: {}", session_id, e);
+        }
+        
+        return Err(e);
+    }
+    
+    Ok((session_id, child, log))
+}
+
+/// Store a started process under its session id, add it to the history and announce it
+fn track_session<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    state: &ScrcpyState,
+    session_id: &str,
+    opts: &ScrcpyOptions,
+    restart: RestartPolicy,
+    process_info: ProcessInfo,
+) -> Result<(), String> {
+    let started_at = chrono::DateTime::<chrono::Utc>::from(process_info.started_at).to_rfc3339();
+    let session = MirrorSession {
+        session_id: session_id.to_string(),
+        device_id: process_info.device_id.clone(),
+        status: SessionStatus::Running,
+        started_at: started_at.clone(),
+    };
+    
+    let mut record = history::SessionRecord::new(session_id, &session.device_id, opts, process_info.started_at);
+    record.model = device_model(app, &session.device_id);
+    
+    // Lets the next launch find this process if the app dies without stopping it
+    let pid_file = utils::get_scrcpy_path(app).map(|exe_path| orphans::PidFile {
+        session_id: session_id.to_string(),
+        pid: process_info.process.id(),
+        device_id: session.device_id.clone(),
+        started_at,
+        exe_path,
+        options: opts.clone(),
+        recording_id: process_info.recording_id.clone(),
+        restart,
+    });
+    
+    state.add_process(session_id.to_string(), process_info)?;
+    
+    if let Err(e) = pid_file.and_then(|pid_file| orphans::write_pid_file(app, &pid_file)) {
+        eprintln!("Failed to write pid file for session {}: {}", session_id, e);
+    }
+    
+    if let Err(e) = history::record_session_start(app, record) {
+        eprintln!("Failed to record session {} in history: {}", session_id, e);
+    }
+    
+    emit_session_started(app, session);
+    Ok(())
+}
+
+fn emit_session_started<R: tauri::Runtime>(app: &tauri::AppHandle<R>, session: MirrorSession) {
+    if let Err(e) = app.emit("session-started", session) {
+        eprintln!("Failed to emit session start: {}", e);
+    }
+}
+
+/// Deal with scrcpy processes left running by a previous run of the app that
+/// crashed or was killed, adopting or terminating them per the orphan policy
+pub fn recover_orphaned_sessions<R: tauri::Runtime>(app: &tauri::AppHandle<R>, state: &ScrcpyState) -> Result<(), String> {
+    let policy = settings::read_settings(app)?.orphan_policy;
+    
+    for orphan in orphans::find_orphans(app)? {
+        let started_at = chrono::DateTime::parse_from_rfc3339(&orphan.started_at)
+            .map(std::time::SystemTime::from)
+            .unwrap_or_else(|_| std::time::SystemTime::now());
+        
+        // The output pipes died with the previous app, so there is no log to read
+        let target = resolve_target(app, &orphan.device_id, &orphan.options);
+        let mut info = ProcessInfo {
+            process: Box::new(AdoptedProcess(orphan.pid)),
+            device_id: orphan.device_id.clone(),
+            started_at,
+            target: target.clone(),
+            recording_id: orphan.recording_id.clone(),
+            log: SessionLog::new(),
+            resources: ResourceMonitor::new(),
+        };
+        
+        match policy {
+            OrphanPolicy::Adopt => {
+                eprintln!("Adopting orphaned session {} (pid {})", orphan.session_id, orphan.pid);
+                
+                state.add_process(orphan.session_id.clone(), info)?;
+                emit_session_started(app, MirrorSession {
+                    session_id: orphan.session_id.clone(),
+                    device_id: orphan.device_id.clone(),
+                    status: SessionStatus::Running,
+                    started_at: orphan.started_at.clone(),
+                });
+                
+                let monitor = SessionMonitor {
+                    app: app.clone(),
+                    state: state.clone(),
+                    session_id: orphan.session_id,
+                    device_id: orphan.device_id,
+                    options: orphan.options,
+                    target,
+                    restart: orphan.restart,
+                };
+                std::thread::spawn(move || monitor.run());
+            }
+            OrphanPolicy::Terminate => {
+                eprintln!("Stopping orphaned session {} (pid {})", orphan.session_id, orphan.pid);
+                
+                let grace = scrcpy::stop_grace(&info, scrcpy::DEFAULT_SHUTDOWN_GRACE);
+                match scrcpy::terminate(info.process.as_mut(), grace) {
+                    Ok(report) => {
+                        if let Some(ref recording_id) = info.recording_id {
+                            if let Err(e) = recordings::finalize_recording(app, recording_id) {
+                                eprintln!("Failed to finalize recording {}: {}", recording_id, e);
+                            }
+                        }
+                        record_session_end(app, &orphan.session_id, &SessionExit::stopped(&report, &info));
+                    }
+                    Err(e) => eprintln!("Failed to stop orphaned session {}: {}", orphan.session_id, e),
+                }
+            }
+        }
+    }
+    
+    Ok(())
+}
+
+/// Model name of a device, if adb can read it
+fn device_model<R: tauri::Runtime>(app: &tauri::AppHandle<R>, device_id: &str) -> Option<String> {
+    let adb = Adb::new(utils::get_adb_path(app).ok()?);
+    adb.get_model(Some(device_id)).ok().filter(|model| !model.is_empty())
+}
+
+/// Record the end of a session's current run in the history and drop its pid file
+fn record_session_end<R: tauri::Runtime>(app: &tauri::AppHandle<R>, session_id: &str, exit: &SessionExit) {
+    if let Err(e) = history::record_session_end(app, session_id, exit) {
+        eprintln!("Failed to record end of session {} in history: {}", session_id, e);
+    }
+    if let Err(e) = orphans::remove_pid_file(app, session_id) {
+        eprintln!("Failed to remove pid file of session {}: {}", session_id, e);
+    }
+}
+
+/// Outcome of trying to relaunch a session
+enum RestartOutcome {
+    Restarted,
+    /// The session was stopped from the app while waiting
+    Cancelled,
+    /// The restart policy gave up; holds the last error
+    GaveUp(String),
+}
+
+/// Watches a session until it exits, relaunching it per its restart policy
+struct SessionMonitor<R: tauri::Runtime> {
+    app: tauri::AppHandle<R>,
+    state: ScrcpyState,
+    session_id: String,
+    device_id: String,
+    options: ScrcpyOptions,
+    /// What the session mirrors, resolved when it started
+    target: Option<SessionTarget>,
+    restart: RestartPolicy,
+}
+
+impl<R: tauri::Runtime> SessionMonitor<R> {
+    fn run(self) {
+        // Consecutive restarts, reset once a relaunched session runs stably
+        let mut attempts = 0;
+        
+        loop {
+            std::thread::sleep(MONITOR_INTERVAL);
+            
+            let restart = |exit: &SessionExit| {
+                if exit.duration_secs >= scrcpy::STABLE_RUN.as_secs_f64() {
+                    attempts = 0;
+                }
+                self.restart.should_restart(exit.reason, attempts)
+            };
+            let (exit, log, recording_id) = match self.state.poll_session(&self.session_id, restart) {
+                Ok(SessionPoll::Running) => continue,
+                Ok(SessionPoll::Exited(info, exit)) => (exit, info.log, info.recording_id),
+                Ok(SessionPoll::NotTracked) => {
+                    // Reaped by cleanup_finished before we saw it; stop_mirroring reports its own exits
+                    match self.state.session_exit(&self.session_id) {
+                        Some(exit) if exit.reason != ExitReason::Stopped => {
+                            if exit.duration_secs >= scrcpy::STABLE_RUN.as_secs_f64() {
+                                attempts = 0;
+                            }
+                            if self.restart.should_restart(exit.reason, attempts) {
+                                self.state.begin_restart(&self.session_id, &self.device_id, self.target.clone());
+                            }
+                            let log = self.state.finished_log(&self.session_id).unwrap_or_default();
+                            (exit, log, self.state.finished_recording(&self.session_id))
+                        }
+                        _ => break,
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Failed to poll session {}: {}", self.session_id, e);
+                    break;
+                }
+            };
+            
+            println!("Session {} exited ({:?}, code {:?})", self.session_id, exit.reason, exit.code);
+            record_session_end(&self.app, &self.session_id, &exit);
+            
+            if let Some(ref recording_id) = recording_id {
+                if let Err(e) = recordings::finalize_recording(&self.app, recording_id) {
+                    eprintln!("Failed to finalize recording {}: {}", recording_id, e);
+                }
+            }
+            
+            // Marked by the poll when the policy asked for it, unless stopped since
+            if self.state.is_restarting(&self.session_id) {
+                match self.relaunch(&exit, &mut attempts) {
+                    RestartOutcome::Restarted => continue,
+                    RestartOutcome::Cancelled => {
+                        let exit = SessionExit { reason: ExitReason::Stopped, ..exit };
+                        self.state.record_exit(&self.session_id, &log, Some(exit.clone()));
+                        emit_session_exit(&self.app, &self.session_id, &self.device_id, exit, &log, None);
+                    }
+                    RestartOutcome::GaveUp(error) => {
+                        emit_session_exit(&self.app, &self.session_id, &self.device_id, exit, &log, Some(error));
+                    }
+                }
+                break;
+            }
+            
+            emit_session_exit(&self.app, &self.session_id, &self.device_id, exit, &log, None);
+            break;
+        }
+    }
+
+    /// Wait for the device and relaunch scrcpy, retrying until the policy gives up
+    fn relaunch(&self, exit: &SessionExit, attempts: &mut u32) -> RestartOutcome {
+        let mut last_error = self.state.finished_log(&self.session_id)
+            .and_then(|log| log.last_error());
+        
+        loop {
+            let delay = self.restart.delay(*attempts);
+            *attempts += 1;
+            
+            let event = SessionRestartEvent {
+                session_id: self.session_id.clone(),
+                device_id: self.device_id.clone(),
+                attempt: *attempts,
+                delay_ms: delay.as_millis() as u64,
+                reason: exit.reason,
+                error: last_error.clone(),
+            };
+            if let Err(e) = self.app.emit("session-restarting", event) {
+                eprintln!("Failed to emit session restart: {}", e);
+            }
+            println!("Restarting session {} in {:?} (attempt {})", self.session_id, delay, attempts);
+            
+            if !self.sleep_unless_cancelled(delay) {
+                return RestartOutcome::Cancelled;
+            }
+            
+            let result = match self.wait_for_device() {
+                Ok(true) => launch_scrcpy(&self.app, &self.state, &self.device_id, &self.options, Some(&self.session_id)),
+                Ok(false) => return RestartOutcome::Cancelled,
+                Err(e) => Err(e),
+            };
+            
+            match result {
+                Ok((_, mut process, log)) => {
+                    if !self.state.end_restart(&self.session_id) {
+                        // Stopped while scrcpy was starting up
+                        let _ = scrcpy::terminate(process.as_mut(), scrcpy::DEFAULT_SHUTDOWN_GRACE);
+                        return RestartOutcome::Cancelled;
+                    }
+                    
+                    let process_info = ProcessInfo {
+                        process,
+                        device_id: self.device_id.clone(),
+                        started_at: std::time::SystemTime::now(),
+                        target: self.target.clone(),
+                        recording_id: None,
+                        log,
+                        resources: ResourceMonitor::new(),
+                    };
+                    return match track_session(&self.app, &self.state, &self.session_id, &self.options, self.restart, process_info) {
+                        Ok(()) => {
+                            println!("Restarted session {} (attempt {})", self.session_id, attempts);
+                            RestartOutcome::Restarted
+                        }
+                        Err(e) => RestartOutcome::GaveUp(e),
+                    };
+                }
+                Err(e) => {
+                    eprintln!("Failed to restart session {}: {}", self.session_id, e);
+                    if !self.restart.should_restart(ExitReason::Failed, *attempts) {
+                        self.state.end_restart(&self.session_id);
+                        return RestartOutcome::GaveUp(e);
+                    }
+                    last_error = Some(e);
+                }
+            }
+        }
+    }
+
+    /// Sleep for `duration`, returning false early if the restart is cancelled
+    fn sleep_unless_cancelled(&self, duration: std::time::Duration) -> bool {
+        let deadline = std::time::Instant::now() + duration;
+        while std::time::Instant::now() < deadline {
+            if !self.state.is_restarting(&self.session_id) {
+                return false;
+            }
+            std::thread::sleep(MONITOR_INTERVAL.min(deadline - std::time::Instant::now()));
+        }
+        self.state.is_restarting(&self.session_id)
+    }
+
+    /// Wait until adb lists the device as ready, reconnecting wireless devices.
+    ///
+    /// Returns Ok(false) if the restart is cancelled while waiting.
+    fn wait_for_device(&self) -> Result<bool, String> {
+        let adb = Adb::new(self.state.adb_path(&self.app)?);
+        let deadline = std::time::Instant::now() + DEVICE_WAIT_TIMEOUT;
+        
+        // Wireless serials are "<ip>:<port>"
+        let address = self.device_id.rsplit_once(':')
+            .and_then(|(ip, port)| port.parse::<u16>().ok().map(|port| (ip, port)));
+        
+        loop {
+            if !self.state.is_restarting(&self.session_id) {
+                return Ok(false);
+            }
+            
+            let ready = adb.devices()
+                .map(|devices| devices.iter().any(|d| d.serial == self.device_id && d.state == "device"))
+                .unwrap_or(false);
+            if ready {
+                return Ok(true);
+            }
+            
+            if let Some((ip, port)) = address {
+                if let Err(e) = adb.connect(ip, port) {
+                    eprintln!("Failed to reconnect to {}: {}", self.device_id, e);
+                }
+            }
+            
+            if std::time::Instant::now() >= deadline {
+                return Err(format!(
+                    "Device {} did not come back within {} seconds",
+                    self.device_id, DEVICE_WAIT_TIMEOUT.as_secs()
+                ));
+            }
+            
+            if !self.sleep_unless_cancelled(DEVICE_POLL_INTERVAL) {
+                return Ok(false);
+            }
+        }
+    }
+}
+
+/// Emit `session-exited`, and `session-error` if the session ended abnormally.
+///
+/// `message` overrides the error shown to the user, which defaults to the
+/// last error printed by scrcpy.
+fn emit_session_exit<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    session_id: &str,
+    device_id: &str,
+    exit: SessionExit,
+    log: &SessionLog,
+    message: Option<String>,
+) {
+    if exit.reason == ExitReason::Failed {
+        let message = message.or_else(|| log.last_error()).unwrap_or_else(|| match (exit.code, exit.signal) {
+            (_, Some(signal)) => format!("scrcpy was terminated by signal {}", signal),
+            (Some(code), _) => format!("scrcpy exited with code {}", code),
+            _ => "scrcpy exited unexpectedly".to_string(),
+        });
+        
+        let event = SessionErrorEvent {
+            session_id: session_id.to_string(),
+            device_id: device_id.to_string(),
+            failure: exit.failure,
+            message,
+        };
+        if let Err(e) = app.emit("session-error", event) {
+            eprintln!("Failed to emit session error: {}", e);
+        }
+    }
+    
+    let event = SessionExitedEvent {
+        session_id: session_id.to_string(),
+        device_id: device_id.to_string(),
+        exit,
+    };
+    if let Err(e) = app.emit("session-exited", event) {
+        eprintln!("Failed to emit session exit: {}", e);
+    }
+}
+
+/// Stop screen mirroring for a device.
+///
+/// scrcpy is first asked to exit so it can finalize recordings and restore
+/// the device state, and is only killed if it is still running after the
+/// grace period (`grace_period_ms`, 5 seconds by default).
 #[tauri::command]
 pub async fn stop_mirroring(
+    app: tauri::AppHandle,
     state: State<'_, ScrcpyState>,
     session_id: String,
-) -> Result<bool, String> {
+    grace_period_ms: Option<u64>,
+) -> Result<ShutdownReport, String> {
+    let grace = grace_period_ms
+        .map(std::time::Duration::from_millis)
+        .unwrap_or(scrcpy::DEFAULT_SHUTDOWN_GRACE);
+    
+    // Waiting out the grace period blocks, so keep it off the async runtime
+    let state = state.inner().clone();
+    tokio::task::spawn_blocking(move || stop_session(&app, &state, &session_id, grace))
+        .await
+        .map_err(|e| format!("Stop task failed: {}", e))?
+}
+
+/// Stop a session, or cancel its pending restart, and report how it ended
+fn stop_session<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    state: &ScrcpyState,
+    session_id: &str,
+    grace: std::time::Duration,
+) -> Result<ShutdownReport, String> {
     // Implement via helper to avoid strict lock scope issues in main command files
     // and to use the shared remove_process logic.
-    let process_info = state.remove_process(&session_id)?;
+    let process_info = state.remove_process(session_id)?;
 
     if let Some(mut info) = process_info {
         println!("Stopping mirroring session: {}", session_id);
-        match info.child.kill() {
-            Ok(_) => {
-                println!("Successfully stopped session: {}", session_id);
-                Ok(true)
+        
+        if info.target == Some(SessionTarget::Display(0)) {
+            windows::remember_window(app, &info.device_id, info.process.id());
+        }
+        
+        let grace = scrcpy::stop_grace(&info, grace);
+        match scrcpy::terminate(info.process.as_mut(), grace) {
+            Ok(report) => {
+                println!("Successfully stopped session: {} ({:?})", session_id, report.method);
+                
+                if let Some(ref recording_id) = info.recording_id {
+                    if let Err(e) = recordings::finalize_recording(app, recording_id) {
+                        eprintln!("Failed to finalize recording {}: {}", recording_id, e);
+                    }
+                }
+                
+                let exit = SessionExit::stopped(&report, &info);
+                state.record_exit(session_id, &info.log, Some(exit.clone()));
+                record_session_end(app, session_id, &exit);
+                emit_session_exit(app, session_id, &info.device_id, exit, &info.log, None);
+                
+                Ok(report)
             }
             Err(e) => {
-                // If killing fails, check if the process is already dead.
-                // An `InvalidInput` error means the process has already exited.
-                if e.kind() == std::io::ErrorKind::InvalidInput {
-                    println!("Session {} was already stopped.", session_id);
-                    Ok(true) // Consider it a success
-                } else {
-                    // For any other error, the kill command failed unexpectedly.
-                    // The process might still be running. We must put the info back to avoid orphaning it.
-                    eprintln!("Failed to kill process for session {}, re-inserting into map. Error: {}", session_id, e);
-                    
-                    // Put it back using the public API
-                    state.add_process(session_id.clone(), info)?;
-                    
-                    Err(format!("Failed to stop session {}: {}", session_id, e))
-                }
+                // The process might still be running. We must put the info back to avoid orphaning it.
+                eprintln!("Failed to stop process for session {}, re-inserting into map. Error: {}", session_id, e);
+                
+                // Put it back using the public API
+                state.add_process(session_id.to_string(), info)?;
+                
+                Err(format!("Failed to stop session {}: {}", session_id, e))
             }
         }
+    } else if state.end_restart(session_id) {
+        // Waiting to be relaunched: cancelling is enough, the monitor reports the exit
+        println!("Cancelled restart of session: {}", session_id);
+        Ok(ShutdownReport {
+            method: ShutdownMethod::AlreadyExited,
+            exit_code: None,
+            signal: None,
+        })
     } else {
         // If the session is not in the map, it might have already finished.
         // Run cleanup to be sure and then give a clearer message.
@@ -105,12 +887,39 @@ pub async fn stop_mirroring(
 /// Stop all active mirroring sessions
 #[tauri::command]
 pub async fn stop_all_mirroring(
+    app: tauri::AppHandle,
     state: State<'_, ScrcpyState>,
+    grace_period_ms: Option<u64>,
 ) -> Result<usize, String> {
-    let count = state.active_count();
-    state.stop_all()?;
-    println!("Stopped all {} mirroring session(s)", count);
-    Ok(count)
+    let grace = grace_period_ms
+        .map(std::time::Duration::from_millis)
+        .unwrap_or(scrcpy::DEFAULT_SHUTDOWN_GRACE);
+    
+    let state = state.inner().clone();
+    let stopped = tokio::task::spawn_blocking(move || stop_all_sessions(&app, &state, grace))
+        .await
+        .map_err(|e| format!("Stop task failed: {}", e))??;
+    
+    println!("Stopped all {} mirroring session(s)", stopped);
+    Ok(stopped)
+}
+
+/// Stop every session and record how each one ended in the history
+pub fn stop_all_sessions<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    state: &ScrcpyState,
+    grace: std::time::Duration,
+) -> Result<usize, String> {
+    windows::remember_windows(app, state);
+    let reports = state.stop_all(grace)?;
+    
+    for (session_id, _) in &reports {
+        if let Some(exit) = state.session_exit(session_id) {
+            record_session_end(app, session_id, &exit);
+        }
+    }
+    
+    Ok(reports.len())
 }
 
 /// Get mirroring status for a specific session
@@ -122,9 +931,16 @@ pub async fn get_mirroring_status(
     state.cleanup_finished()?;
     
     if state.is_running(&session_id) {
-        Ok(SessionStatus::Running)
-    } else {
-        Ok(SessionStatus::Stopped)
+        return Ok(SessionStatus::Running);
+    }
+    
+    if state.is_restarting(&session_id) {
+        return Ok(SessionStatus::Reconnecting);
+    }
+    
+    match state.session_exit(&session_id) {
+        Some(exit) if exit.reason == ExitReason::Failed => Ok(SessionStatus::Error),
+        _ => Ok(SessionStatus::Stopped),
     }
 }
 
@@ -144,7 +960,7 @@ pub async fn get_active_sessions(
                 session_id: id,
                 device_id,
                 status: SessionStatus::Running,
-                started_at: format!("{:?}", started_at),
+                started_at: chrono::DateTime::<chrono::Utc>::from(started_at).to_rfc3339(),
             })
         } else {
             None
@@ -157,15 +973,14 @@ pub async fn get_active_sessions(
 /// Get process statistics
 #[tauri::command]
 pub async fn get_process_stats(
+    app: tauri::AppHandle,
     state: State<'_, ScrcpyState>,
 ) -> Result<ProcessStats, String> {
     state.cleanup_finished()?;
     
-    let active_sessions = state.active_count();
-    
     Ok(ProcessStats {
-        active_sessions,
-        total_started: active_sessions, // Could track this separately if needed
+        active_sessions: state.active_count(),
+        total_started: history::count_sessions(&app)?,
     })
 }
 
@@ -180,3 +995,421 @@ pub async fn check_scrcpy_available(app: tauri::AppHandle) -> Result<bool, Strin
 pub async fn get_scrcpy_version(app: tauri::AppHandle) -> Result<String, String> {
     scrcpy::get_version(&app)
 }
+
+/// Features the scrcpy in use supports, by the version it reports
+#[tauri::command]
+pub async fn get_scrcpy_capabilities(app: tauri::AppHandle) -> Result<ScrcpyCapabilities, String> {
+    Ok(ScrcpyCapabilities::for_version(scrcpy::detect_version(&app)?))
+}
+
+/// Command line `start_mirroring` would run for the same arguments, quoted
+/// for pasting into a terminal, with the warnings the session would report
+#[tauri::command]
+pub async fn preview_scrcpy_command(
+    app: tauri::AppHandle,
+    device_id: String,
+    options: Option<OptionOverrides>,
+    preset: Option<String>,
+) -> Result<CommandPreview, String> {
+    let effective = effective::spawn_resolve_options(app.clone(), device_id.clone(), preset, options).await?;
+    if let Some(error) = effective.error {
+        return Err(error);
+    }
+    
+    // Show the options the scrcpy in use would actually get
+    let mut opts = effective.options;
+    let mut warnings = Vec::new();
+    if let Ok(version) = scrcpy::detect_version(&app) {
+        (opts, warnings) = opts.adapt_to_version(&version)?;
+    }
+    let scrcpy_path = utils::get_scrcpy_path(&app)?;
+    let scrcpy_dir = utils::get_scrcpy_dir(&app)?;
+    
+    let cmd = scrcpy::build_scrcpy_command(&scrcpy_path, &scrcpy_dir, None, Some(&device_id), &opts);
+    Ok(CommandPreview {
+        command: scrcpy::format_command_line(&cmd),
+        warnings,
+    })
+}
+
+/// Turn a scrcpy command line into options, listing the flags that have no equivalent
+#[tauri::command]
+pub async fn parse_scrcpy_args(command_line: String) -> Result<ParsedScrcpyArgs, String> {
+    scrcpy::parse_scrcpy_args(&command_line)
+}
+
+/// Get the captured output of a running or recently ended session
+#[tauri::command]
+pub async fn get_session_logs(
+    state: State<'_, ScrcpyState>,
+    session_id: String,
+) -> Result<SessionLogs, String> {
+    state.session_logs(&session_id)?
+        .ok_or_else(|| format!("No logs for session: {}", session_id))
+}
+
+/// Get the latest CPU, memory and uptime sample of a running session.
+///
+/// Samples are taken in the background every couple of seconds; a session
+/// that was not sampled yet is read on the spot.
+#[tauri::command]
+pub async fn get_session_resources(
+    state: State<'_, ScrcpyState>,
+    session_id: String,
+) -> Result<SessionResources, String> {
+    if !state.is_running(&session_id) {
+        return Err(format!("Session not found or already terminated: {}", session_id));
+    }
+    
+    if let Some(resources) = state.session_resources(&session_id)? {
+        return Ok(resources);
+    }
+    
+    let state = state.inner().clone();
+    tokio::task::spawn_blocking(move || {
+        state.sample_resources()?
+            .into_iter()
+            .find(|(id, _, _)| *id == session_id)
+            .map(|(_, _, resources)| resources)
+            .ok_or_else(|| format!("Session not found or already terminated: {}", session_id))
+    }).await.map_err(|e| format!("Failed to sample session resources: {}", e))?
+}
+
+/// Sample the resource use of all sessions periodically, emitting `session-resources`
+pub fn spawn_resource_monitor(app: tauri::AppHandle, state: ScrcpyState) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(RESOURCE_SAMPLE_INTERVAL);
+        
+        if state.active_count() == 0 {
+            continue;
+        }
+        
+        let usage: Vec<SessionResourceUsage> = match state.sample_resources() {
+            Ok(samples) => samples.into_iter()
+                .map(|(session_id, device_id, resources)| SessionResourceUsage { session_id, device_id, resources })
+                .collect(),
+            Err(e) => {
+                eprintln!("Failed to sample session resources: {}", e);
+                continue;
+            }
+        };
+        
+        for session in usage.iter().filter(|s| s.resources.frozen) {
+            eprintln!("Session {} has used no CPU for {:.0}s", session.session_id, session.resources.idle_secs);
+        }
+        
+        if let Err(e) = app.emit("session-resources", usage) {
+            eprintln!("Failed to emit session resources: {}", e);
+        }
+    });
+}
+
+/// Run a scrcpy `--list-*` query for a device off the async runtime
+async fn run_list_command(app: tauri::AppHandle, device_id: String, flag: &'static str) -> Result<String, String> {
+    tokio::task::spawn_blocking(move || {
+        scrcpy::run_list_command(&app, &device_id, flag)
+    }).await.map_err(|e| format!("Failed to run scrcpy {}: {}", flag, e))?
+}
+
+/// List the cameras of a device, with their capture sizes unless `include_sizes` is false
+#[tauri::command]
+pub async fn list_cameras(
+    app: tauri::AppHandle,
+    device_id: String,
+    include_sizes: Option<bool>,
+) -> Result<Vec<CameraInfo>, String> {
+    let flag = if include_sizes.unwrap_or(true) {
+        "--list-camera-sizes"
+    } else {
+        "--list-cameras"
+    };
+    
+    let output = run_list_command(app, device_id, flag).await?;
+    Ok(scrcpy::parse_camera_list(&output))
+}
+
+/// List the video and audio encoders of a device
+#[tauri::command]
+pub async fn list_encoders(
+    app: tauri::AppHandle,
+    device_id: String,
+) -> Result<Vec<EncoderInfo>, String> {
+    let output = run_list_command(app, device_id, "--list-encoders").await?;
+    Ok(scrcpy::parse_encoder_list(&output))
+}
+
+/// List the displays of a device
+#[tauri::command]
+pub async fn list_displays(
+    app: tauri::AppHandle,
+    device_id: String,
+) -> Result<Vec<DisplayInfo>, String> {
+    let output = run_list_command(app, device_id, "--list-displays").await?;
+    Ok(scrcpy::parse_display_list(&output))
+}
+
+/// List the apps installed on a device
+#[tauri::command]
+pub async fn list_apps(
+    app: tauri::AppHandle,
+    device_id: String,
+) -> Result<Vec<AppInfo>, String> {
+    let output = run_list_command(app, device_id, "--list-apps").await?;
+    Ok(scrcpy::parse_app_list(&output))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adb::fake::FakeAdb;
+    use crate::scrcpy::fake::{FakeLauncher, FakeScript};
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+    use tauri::test::{mock_builder, mock_context, noop_assets, MockRuntime};
+    use tauri::Listener;
+
+    /// A mock app with its data in a directory of its own, and a state running fake scrcpy
+    struct TestApp {
+        app: tauri::App<MockRuntime>,
+        state: ScrcpyState,
+        launcher: Arc<FakeLauncher>,
+        dir: tempfile::TempDir,
+    }
+
+    impl TestApp {
+        fn new(name: &str) -> Self {
+            let dir = tempfile::Builder::new().prefix(&format!("mirin_app_{}", name)).tempdir().unwrap();
+
+            // The app data directory is the data directory joined with the identifier
+            let mut context = mock_context(noop_assets());
+            context.config_mut().identifier = dir.path().to_string_lossy().to_string();
+            let app = mock_builder().build(context).unwrap();
+            
+            let launcher = FakeLauncher::new();
+            let state = ScrcpyState::with_launcher(launcher.clone());
+            Self { app, state, launcher, dir }
+        }
+
+        /// Reach devices through `adb` instead of the app's own
+        fn with_adb(mut self, adb: &FakeAdb) -> Self {
+            self.state = self.state.with_adb_path(adb.path());
+            self
+        }
+
+        fn handle(&self) -> &tauri::AppHandle<MockRuntime> {
+            self.app.handle()
+        }
+
+        fn start(&self, device_id: &str, restart: RestartPolicy, policy: SessionPolicy) -> Result<String, String> {
+            start_session(self.handle(), &self.state, device_id, ScrcpyOptions::default(), restart, policy)
+        }
+
+        fn stop(&self, session_id: &str) -> Result<ShutdownReport, String> {
+            stop_session(self.handle(), &self.state, session_id, Duration::from_secs(1))
+        }
+
+        /// Payloads of an event, as they are emitted
+        fn events(&self, event: &str) -> Arc<Mutex<Vec<serde_json::Value>>> {
+            let events = Arc::new(Mutex::new(Vec::new()));
+            let received = events.clone();
+            self.handle().listen(event, move |event| {
+                received.lock().unwrap().push(serde_json::from_str(event.payload()).unwrap());
+            });
+            events
+        }
+
+        fn has_pid_file(&self, session_id: &str) -> bool {
+            self.dir.path().join("sessions").join(format!("{}.json", session_id)).exists()
+        }
+    }
+
+    fn wait_until(what: &str, condition: impl Fn() -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !condition() {
+            assert!(Instant::now() < deadline, "timed out waiting until {}", what);
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    #[test]
+    fn test_start_and_stop_session() {
+        let test = TestApp::new("start_stop");
+        let started = test.events("session-started");
+        let exited = test.events("session-exited");
+        
+        let session_id = test.start("device1", RestartPolicy::Never, SessionPolicy::Reject).unwrap();
+        let handle = test.launcher.last();
+        assert_eq!(session_id, format!("session_device1_{}", handle.pid));
+        assert_eq!(handle.device_id.as_deref(), Some("device1"));
+        assert!(test.state.is_running(&session_id));
+        assert!(test.has_pid_file(&session_id));
+        assert_eq!(history::count_sessions(test.handle()).unwrap(), 1);
+        assert_eq!(started.lock().unwrap()[0]["session_id"], session_id.as_str());
+        
+        let report = test.stop(&session_id).unwrap();
+        assert_eq!(report.method, ShutdownMethod::Graceful);
+        assert!(handle.exit_requested() && !handle.killed());
+        assert!(!test.state.is_running(&session_id));
+        assert!(!test.has_pid_file(&session_id));
+        assert_eq!(exited.lock().unwrap()[0]["reason"], "Stopped");
+        
+        let err = test.stop(&session_id).unwrap_err();
+        assert!(err.starts_with("Session not found"), "{}", err);
+    }
+
+    #[test]
+    fn test_start_failures() {
+        let test = TestApp::new("start_failures");
+        test.launcher.push_script(FakeScript {
+            stdout: Vec::new(),
+            stderr: vec!["ERROR: Could not find any ADB device".to_string()],
+            exit_on_start: Some(1),
+            ..Default::default()
+        });
+        test.launcher.push_script(FakeScript {
+            spawn_error: Some("No such file or directory".to_string()),
+            ..Default::default()
+        });
+        
+        let err = test.start("device1", RestartPolicy::Never, SessionPolicy::Reject).unwrap_err();
+        assert!(err.contains("exit code 1"), "{}", err);
+        assert!(err.contains("Could not find any ADB device"), "{}", err);
+        
+        let err = test.start("device1", RestartPolicy::Never, SessionPolicy::Reject).unwrap_err();
+        assert!(err.starts_with("Failed to start scrcpy"), "{}", err);
+        assert_eq!(test.state.active_count(), 0);
+        
+        // Only the start that got as far as running scrcpy is in the history
+        assert_eq!(history::count_sessions(test.handle()).unwrap(), 1);
+        
+        // Neither failure kept the display reserved
+        assert!(test.start("device1", RestartPolicy::Never, SessionPolicy::Reject).is_ok());
+    }
+
+    #[test]
+    fn test_session_policies() {
+        let test = TestApp::new("session_policies");
+        let first = test.start("device1", RestartPolicy::Never, SessionPolicy::Reject).unwrap();
+        
+        let err = test.start("device1", RestartPolicy::Never, SessionPolicy::Reject).unwrap_err();
+        assert!(err.contains(&first), "{}", err);
+        assert_eq!(test.start("device1", RestartPolicy::Never, SessionPolicy::Reuse).unwrap(), first);
+        assert_eq!(test.launcher.spawned().len(), 1);
+        
+        // Another device is free to start on the same display
+        test.start("device2", RestartPolicy::Never, SessionPolicy::Reject).unwrap();
+        
+        let second = test.start("device1", RestartPolicy::Never, SessionPolicy::Replace).unwrap();
+        assert_ne!(second, first);
+        assert!(!test.state.is_running(&first));
+        let replaced = &test.launcher.spawned()[0];
+        assert!(replaced.exit_requested() && replaced.has_exited());
+        assert_eq!(test.state.active_count(), 2);
+        
+        assert_eq!(stop_all_sessions(test.handle(), &test.state, Duration::from_secs(1)).unwrap(), 2);
+        assert_eq!(test.state.active_count(), 0);
+    }
+
+    #[test]
+    fn test_stop_all_kills_stubborn_sessions() {
+        let test = TestApp::new("stop_all");
+        test.start("device1", RestartPolicy::Never, SessionPolicy::Reject).unwrap();
+        test.launcher.push_script(FakeScript {
+            ignore_exit_request: true,
+            ..Default::default()
+        });
+        let stubborn = test.start("device2", RestartPolicy::Never, SessionPolicy::Reject).unwrap();
+        
+        assert_eq!(stop_all_sessions(test.handle(), &test.state, Duration::from_millis(200)).unwrap(), 2);
+        
+        let handles = test.launcher.spawned();
+        assert!(!handles[0].killed());
+        assert!(handles[1].exit_requested() && handles[1].killed());
+        assert_eq!(test.state.session_exit(&stubborn).unwrap().reason, ExitReason::Stopped);
+        assert!(!test.has_pid_file(&stubborn));
+    }
+
+    #[test]
+    fn test_monitor_reports_exits() {
+        let test = TestApp::new("monitor_exits");
+        let exited = test.events("session-exited");
+        let errors = test.events("session-error");
+        
+        let crashed = test.start("device1", RestartPolicy::Never, SessionPolicy::Reject).unwrap();
+        let closed = test.start("device2", RestartPolicy::Never, SessionPolicy::Reject).unwrap();
+        test.launcher.spawned()[0].exit(1);
+        test.launcher.spawned()[1].exit(0);
+        
+        wait_until("both exits are reported", || exited.lock().unwrap().len() == 2);
+        let exits = exited.lock().unwrap().clone();
+        let reason = |session_id: &str| exits.iter()
+            .find(|exit| exit["session_id"] == session_id)
+            .map(|exit| exit["reason"].clone())
+            .unwrap();
+        assert_eq!(reason(&crashed), "Failed");
+        assert_eq!(reason(&closed), "Closed");
+        
+        // Only the crash is an error
+        let errors = errors.lock().unwrap().clone();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0]["session_id"], crashed.as_str());
+        assert_eq!(errors[0]["device_id"], "device1");
+        
+        assert_eq!(test.state.active_count(), 0);
+        assert!(!test.has_pid_file(&crashed) && !test.has_pid_file(&closed));
+    }
+
+    #[test]
+    fn test_monitor_finalizes_reaped_recording() {
+        let test = TestApp::new("monitor_recording");
+        let exited = test.events("session-exited");
+        let record_path = test.dir.path().join("demo.mp4");
+        let options = ScrcpyOptions {
+            record_path: Some(record_path.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        
+        let session_id = start_session(test.handle(), &test.state, "device1", options, RestartPolicy::Never, SessionPolicy::Reject).unwrap();
+        std::fs::write(&record_path, vec![0u8; 1024]).unwrap();
+        test.launcher.last().exit(0);
+        // A status query reaps the session before the monitor polls it
+        test.state.cleanup_finished().unwrap();
+        
+        wait_until("the exit is reported", || exited.lock().unwrap().len() == 1);
+        let index = std::fs::read_to_string(test.dir.path().join("recordings.json")).unwrap();
+        let index: serde_json::Value = serde_json::from_str(&index).unwrap();
+        assert_eq!(index[0]["session_id"], session_id.as_str());
+        assert!(!index[0]["stopped_at"].is_null());
+        assert_eq!(index[0]["size_bytes"], 1024);
+    }
+
+    #[test]
+    fn test_monitor_restarts_crashed_session() {
+        // Restarts wait for adb to list the device again
+        let adb = FakeAdb::new("monitor_restart")
+            .on("devices -l", "List of devices attached\ndevice1\tdevice\n");
+        let test = TestApp::new("monitor_restart").with_adb(&adb);
+        let restarting = test.events("session-restarting");
+        let started = test.events("session-started");
+        
+        let restart = RestartPolicy::OnFailure { max_retries: 2, backoff_ms: 10 };
+        let session_id = test.start("device1", restart, SessionPolicy::Reject).unwrap();
+        test.launcher.last().exit(1);
+        
+        wait_until("the session is relaunched", || started.lock().unwrap().len() == 2);
+        assert_eq!(restarting.lock().unwrap()[0]["attempt"], 1);
+        assert_eq!(started.lock().unwrap()[1]["session_id"], session_id.as_str());
+        assert_eq!(test.launcher.spawned().len(), 2);
+        assert!(test.state.is_running(&session_id));
+        assert!(test.has_pid_file(&session_id));
+        assert_eq!(history::count_sessions(test.handle()).unwrap(), 2);
+        
+        // Closing the window ends the session for good
+        let exited = test.events("session-exited");
+        test.launcher.last().exit(0);
+        wait_until("the exit is reported", || !exited.lock().unwrap().is_empty());
+        assert_eq!(exited.lock().unwrap()[0]["reason"], "Closed");
+        assert_eq!(test.launcher.spawned().len(), 2);
+        assert!(!test.state.is_running(&session_id));
+    }
+}
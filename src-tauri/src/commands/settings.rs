@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::PathBuf;
 use tauri::Manager;
+use crate::scrcpy::{OptionOverrides, OrphanPolicy, ScrcpyOptions, SessionPolicy};
+use super::quality::QualityMode;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Settings {
@@ -15,6 +17,23 @@ pub struct Settings {
     pub stay_awake: bool,
     #[serde(rename = "turnScreenOff")]
     pub turn_screen_off: bool,
+    /// Reopen each device's window where it was last, titled with its saved name
+    #[serde(rename = "rememberWindows", default = "default_true")]
+    pub remember_windows: bool,
+    /// Whether resolution, bitrate and FPS are picked per session from a
+    /// measurement of the link instead of the values above
+    #[serde(default)]
+    pub quality: QualityMode,
+    /// What starting a second session for the same device and display does
+    #[serde(rename = "sessionPolicy", default)]
+    pub session_policy: SessionPolicy,
+    /// What happens on startup to scrcpy windows left open by a crash
+    #[serde(rename = "orphanPolicy", default)]
+    pub orphan_policy: OrphanPolicy,
+}
+
+fn default_true() -> bool {
+    true
 }
 
 impl Default for Settings {
@@ -26,11 +45,70 @@ impl Default for Settings {
             always_on_top: false,
             stay_awake: true,
             turn_screen_off: false,
+            remember_windows: true,
+            quality: QualityMode::default(),
+            session_policy: SessionPolicy::default(),
+            orphan_policy: OrphanPolicy::default(),
         }
     }
 }
 
-fn get_settings_path(app_handle: tauri::AppHandle) -> Result<PathBuf, String> {
+impl Settings {
+    /// Mirroring options set by the saved settings, the lowest layer under
+    /// presets, device overrides and explicit options
+    pub fn option_overrides(&self) -> Result<OptionOverrides, String> {
+        if self.bitrate == 0 {
+            return Err("Invalid bitrate: must be greater than 0".to_string());
+        }
+        if self.max_fps == 0 {
+            return Err("Invalid max FPS: must be greater than 0".to_string());
+        }
+
+        let mut overrides = OptionOverrides::new();
+        overrides.insert("max_size".to_string(), parse_resolution(&self.resolution)?.into());
+        overrides.insert("bit_rate".to_string(), self.bitrate.into());
+        overrides.insert("max_fps".to_string(), self.max_fps.into());
+        overrides.insert("always_on_top".to_string(), self.always_on_top.into());
+        overrides.insert("stay_awake".to_string(), self.stay_awake.into());
+        overrides.insert("turn_screen_off".to_string(), self.turn_screen_off.into());
+        Ok(overrides)
+    }
+
+    /// Mirroring options for a session started with nothing but these settings
+    pub fn to_options(&self) -> Result<ScrcpyOptions, String> {
+        let options = ScrcpyOptions::default().with_overrides(&self.option_overrides()?)?;
+        options.validate()?;
+        Ok(options)
+    }
+}
+
+/// Longest side of the mirrored screen for a `resolution` setting: "default"
+/// keeps the device's native size, otherwise a number of pixels such as
+/// "1280" or a size such as "1920x1080"
+pub fn parse_resolution(resolution: &str) -> Result<Option<u32>, String> {
+    let resolution = resolution.trim();
+    if resolution.is_empty() || resolution.eq_ignore_ascii_case("default") {
+        return Ok(None);
+    }
+
+    let invalid = || format!("Invalid resolution '{}': expected \"default\", a size in pixels such as 1280, or WIDTHxHEIGHT", resolution);
+    let sides: Vec<&str> = resolution.split(['x', 'X']).collect();
+    if sides.len() > 2 {
+        return Err(invalid());
+    }
+
+    let mut longest = 0;
+    for side in sides {
+        let side: u32 = side.trim().parse().map_err(|_| invalid())?;
+        if side == 0 {
+            return Err(invalid());
+        }
+        longest = longest.max(side);
+    }
+    Ok(Some(longest))
+}
+
+fn get_settings_path<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
     app_handle
         .path()
         .app_data_dir()
@@ -46,7 +124,10 @@ pub async fn save_settings(
     settings: Settings,
     app_handle: tauri::AppHandle,
 ) -> Result<bool, String> {
-    let settings_path = get_settings_path(app_handle)?;
+    // Refuse settings that could not start a session
+    settings.to_options()?;
+
+    let settings_path = get_settings_path(&app_handle)?;
 
     // Create parent directory if it doesn't exist
     if let Some(parent) = settings_path.parent() {
@@ -66,6 +147,11 @@ pub async fn save_settings(
 
 #[tauri::command]
 pub async fn load_settings(app_handle: tauri::AppHandle) -> Result<Settings, String> {
+    read_settings(&app_handle)
+}
+
+/// Read the saved settings, or the defaults if none were saved
+pub fn read_settings<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<Settings, String> {
     let settings_path = get_settings_path(app_handle)?;
 
     // If settings file doesn't exist, return default settings
@@ -82,3 +168,57 @@ pub async fn load_settings(app_handle: tauri::AppHandle) -> Result<Settings, Str
 
     Ok(settings)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_resolution() {
+        assert_eq!(parse_resolution("default"), Ok(None));
+        assert_eq!(parse_resolution(""), Ok(None));
+        assert_eq!(parse_resolution("1280"), Ok(Some(1280)));
+        assert_eq!(parse_resolution(" 800 "), Ok(Some(800)));
+        assert_eq!(parse_resolution("1080x2400"), Ok(Some(2400)));
+        assert_eq!(parse_resolution("1920X1080"), Ok(Some(1920)));
+
+        for invalid in ["0", "720p", "-1280", "1920x", "1920x1080x3", "1280.5"] {
+            assert!(parse_resolution(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_to_options() {
+        let options = Settings::default().to_options().unwrap();
+        // "default" is the device's native size, not the 1920 scrcpy options default to
+        assert_eq!(options.max_size, None);
+        assert_eq!(options.bit_rate, Some(8_000_000));
+        assert_eq!(options.max_fps, Some(60));
+        assert!(options.stay_awake);
+
+        let settings = Settings {
+            resolution: "1280".to_string(),
+            bitrate: 2_000_000,
+            max_fps: 30,
+            turn_screen_off: true,
+            ..Default::default()
+        };
+        let options = settings.to_options().unwrap();
+        assert_eq!(options.max_size, Some(1280));
+        assert_eq!(options.bit_rate, Some(2_000_000));
+        assert_eq!(options.max_fps, Some(30));
+        assert!(options.turn_screen_off);
+
+        let settings = Settings {
+            resolution: "huge".to_string(),
+            ..Default::default()
+        };
+        assert!(settings.to_options().unwrap_err().starts_with("Invalid resolution 'huge'"));
+
+        let settings = Settings {
+            max_fps: 0,
+            ..Default::default()
+        };
+        assert!(settings.to_options().is_err());
+    }
+}
@@ -0,0 +1,14 @@
+use std::sync::{Mutex, MutexGuard};
+
+/// Serializes updates of the JSON files in the app data directory
+static STORE_LOCK: Mutex<()> = Mutex::new(());
+
+/// Hold while reading and rewriting one of the JSON files, so session
+/// monitors and commands updating it at the same time keep each other's
+/// changes.
+///
+/// Take it once per update: it is not reentrant.
+pub(crate) fn lock() -> MutexGuard<'static, ()> {
+    // Nothing is guarded but the files, so a panic while holding it leaves nothing to repair
+    STORE_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+use crate::scrcpy::{self, OptionOverrides, ScrcpyState, WindowGeometry};
+use super::{device, settings, store};
+
+/// How often the place of the session windows is captured
+const WINDOW_CAPTURE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Last window geometry of each device, by serial
+pub type WindowMemory = BTreeMap<String, WindowGeometry>;
+
+fn get_window_memory_path<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+    app_handle
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {}", e))
+        .map(|path| path.join("window_geometry.json"))
+}
+
+fn read_memory_in(path: &Path) -> Result<WindowMemory, String> {
+    if !path.exists() {
+        return Ok(WindowMemory::new());
+    }
+
+    let json = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read window geometry file: {}", e))?;
+
+    serde_json::from_str(&json)
+        .map_err(|e| format!("Failed to parse window geometry: {}", e))
+}
+
+fn write_memory_in(path: &Path, memory: &WindowMemory) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create window geometry directory: {}", e))?;
+    }
+
+    let json = serde_json::to_string_pretty(memory)
+        .map_err(|e| format!("Failed to serialize window geometry: {}", e))?;
+
+    fs::write(path, json)
+        .map_err(|e| format!("Failed to write window geometry file: {}", e))
+}
+
+/// Store the geometry of a device's window, returning whether it changed
+fn remember_in(path: &Path, device_id: &str, geometry: WindowGeometry) -> Result<bool, String> {
+    let _lock = store::lock();
+    let mut memory = read_memory_in(path)?;
+    if memory.get(device_id) == Some(&geometry) {
+        return Ok(false);
+    }
+
+    memory.insert(device_id.to_string(), geometry);
+    write_memory_in(path, &memory)?;
+    Ok(true)
+}
+
+fn forget_in(path: &Path, device_id: &str) -> Result<bool, String> {
+    let _lock = store::lock();
+    let mut memory = read_memory_in(path)?;
+    if memory.remove(device_id).is_none() {
+        return Ok(false);
+    }
+
+    write_memory_in(path, &memory)?;
+    Ok(true)
+}
+
+fn remembers_windows<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>) -> bool {
+    settings::read_settings(app_handle)
+        .map(|settings| settings.remember_windows)
+        .unwrap_or(false)
+}
+
+/// Record where the window of a device's session is, if window memory is on
+pub fn remember_window<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, device_id: &str, pid: u32) {
+    if !remembers_windows(app_handle) {
+        return;
+    }
+
+    let Some(geometry) = scrcpy::read_window_geometry(pid) else {
+        return;
+    };
+
+    if let Err(e) = get_window_memory_path(app_handle).and_then(|path| remember_in(&path, device_id, geometry)) {
+        eprintln!("Failed to remember window of {}: {}", device_id, e);
+    }
+}
+
+/// Record where the windows of all sessions mirroring a main display are
+pub fn remember_windows<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, state: &ScrcpyState) {
+    if !remembers_windows(app_handle) {
+        return;
+    }
+
+    match state.main_display_sessions() {
+        Ok(sessions) => {
+            for (device_id, pid) in sessions {
+                remember_window(app_handle, &device_id, pid);
+            }
+        }
+        Err(e) => eprintln!("Failed to list session windows: {}", e),
+    }
+}
+
+/// Capture where the session windows are periodically, since a window may be
+/// closed by hand before its session is stopped
+pub fn spawn_window_tracker(app_handle: tauri::AppHandle, state: ScrcpyState) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(WINDOW_CAPTURE_INTERVAL);
+
+        if state.active_count() > 0 {
+            remember_windows(&app_handle, &state);
+        }
+    });
+}
+
+/// Option layer for the window of a device: titled with its saved name and
+/// placed where it was last
+pub fn window_layer<R: tauri::Runtime>(app_handle: &tauri::AppHandle<R>, device_id: &str) -> Result<Option<OptionOverrides>, String> {
+    let geometry = read_memory_in(&get_window_memory_path(app_handle)?)?
+        .get(device_id)
+        .copied();
+    let title = device::find_saved_device(device_id)?.map(|device| device.name);
+
+    Ok(window_overrides(geometry, title.as_deref()))
+}
+
+fn window_overrides(geometry: Option<WindowGeometry>, title: Option<&str>) -> Option<OptionOverrides> {
+    let mut overrides = geometry.and_then(WindowGeometry::to_overrides).unwrap_or_default();
+
+    if let Some(title) = title.map(str::trim).filter(|title| !title.is_empty()) {
+        overrides.insert("window_title".to_string(), title.into());
+    }
+
+    (!overrides.is_empty()).then_some(overrides)
+}
+
+/// Get the remembered window geometry of every device
+#[tauri::command]
+pub async fn get_window_geometries(app_handle: tauri::AppHandle) -> Result<WindowMemory, String> {
+    read_memory_in(&get_window_memory_path(&app_handle)?)
+}
+
+/// Forget where a device's window was, so it opens centered again
+#[tauri::command]
+pub async fn forget_window_geometry(app_handle: tauri::AppHandle, device_id: String) -> Result<bool, String> {
+    forget_in(&get_window_memory_path(&app_handle)?, &device_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remember_and_forget() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("window_geometry.json");
+        let left = WindowGeometry { x: 0, y: 40, width: 540, height: 1170 };
+        let right = WindowGeometry { x: 1380, y: 40, width: 540, height: 1170 };
+
+        assert!(remember_in(&path, "R5CT12345", left).unwrap());
+        assert!(remember_in(&path, "192.168.1.42:5555", right).unwrap());
+        // Unchanged geometry is not written again
+        assert!(!remember_in(&path, "R5CT12345", left).unwrap());
+
+        let memory = read_memory_in(&path).unwrap();
+        assert_eq!(memory.len(), 2);
+        assert_eq!(memory["192.168.1.42:5555"], right);
+
+        assert!(forget_in(&path, "R5CT12345").unwrap());
+        assert!(!forget_in(&path, "R5CT12345").unwrap());
+        assert_eq!(read_memory_in(&path).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_window_overrides() {
+        let geometry = WindowGeometry { x: 1380, y: 40, width: 540, height: 1170 };
+
+        let overrides = window_overrides(Some(geometry), Some("Lab tablet 2")).unwrap();
+        assert_eq!(overrides["window_x"], 1380);
+        assert_eq!(overrides["window_title"], "Lab tablet 2");
+        assert!(crate::scrcpy::ScrcpyOptions::default().with_overrides(&overrides).is_ok());
+
+        let overrides = window_overrides(None, Some("Lab tablet 2")).unwrap();
+        assert_eq!(overrides.len(), 1);
+
+        assert!(window_overrides(None, Some("  ")).is_none());
+        assert!(window_overrides(None, None).is_none());
+    }
+}
@@ -59,6 +59,9 @@ pub fn run() {
             commands::save_device,
             commands::get_saved_devices,
             commands::remove_saved_device,
+            commands::set_device_overrides,
+            commands::get_effective_options,
+            commands::measure_quality,
             // Scrcpy commands
             commands::start_mirroring,
             commands::stop_mirroring,
@@ -68,16 +71,56 @@ pub fn run() {
             commands::get_process_stats,
             commands::check_scrcpy_available,
             commands::get_scrcpy_version,
+            commands::get_scrcpy_capabilities,
+            commands::preview_scrcpy_command,
+            commands::parse_scrcpy_args,
+            commands::list_cameras,
+            commands::list_encoders,
+            commands::list_displays,
+            commands::list_apps,
+            commands::launch_app_in_window,
+            commands::get_session_logs,
+            commands::get_session_resources,
+            // Window commands
+            commands::get_window_geometries,
+            commands::forget_window_geometry,
+            // Recording commands
+            commands::list_recordings,
+            commands::rename_recording,
+            commands::delete_recording,
+            // History commands
+            commands::get_session_history,
+            // Preset commands
+            commands::list_presets,
+            commands::save_preset,
+            commands::rename_preset,
+            commands::delete_preset,
+            commands::set_default_preset,
+            commands::export_presets,
+            commands::import_presets,
             // Settings commands
             commands::save_settings,
             commands::load_settings,
         ])
+        .setup(|app| {
+            // Look for scrcpy processes a crashed previous run left behind
+            let handle = app.handle().clone();
+            commands::spawn_resource_monitor(handle.clone(), app.state::<scrcpy::ScrcpyState>().inner().clone());
+            commands::spawn_window_tracker(handle.clone(), app.state::<scrcpy::ScrcpyState>().inner().clone());
+            std::thread::spawn(move || {
+                let state = handle.state::<scrcpy::ScrcpyState>().inner().clone();
+                if let Err(e) = commands::recover_orphaned_sessions(&handle, &state) {
+                    eprintln!("Failed to recover orphaned sessions: {}", e);
+                }
+            });
+            Ok(())
+        })
         .on_window_event(|window, event| {
             if let tauri::WindowEvent::Destroyed = event {
                 // Clean up all scrcpy processes when window is closed
                 if let Some(state) = window.try_state::<scrcpy::ScrcpyState>() {
                     println!("Window destroyed, cleaning up scrcpy processes...");
-                    let _ = state.stop_all();
+                    let _ = commands::stop_all_sessions(window.app_handle(), &state, scrcpy::DEFAULT_SHUTDOWN_GRACE);
                 }
             }
         })
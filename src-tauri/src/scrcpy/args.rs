@@ -0,0 +1,835 @@
+use std::process::Command;
+use serde::de::DeserializeOwned;
+use serde::{Serialize, Deserialize};
+use super::{Crop, NewDisplay, ScrcpyOptions, ScrcpyVersion, Size, StartApp};
+
+/// A scrcpy command line turned back into options
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParsedScrcpyArgs {
+    pub options: ScrcpyOptions,
+    /// Device selected with `-s`/`--serial`
+    pub device_id: Option<String>,
+    /// Arguments with no equivalent in `ScrcpyOptions`, as written
+    pub unsupported: Vec<String>,
+}
+
+/// Program and arguments of a command as one line that can be pasted into the platform shell
+pub fn format_command_line(cmd: &Command) -> String {
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|arg| quote_arg(&arg.to_string_lossy()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Quote an argument for a POSIX shell, or for cmd and PowerShell on Windows
+pub fn quote_arg(arg: &str) -> String {
+    if cfg!(target_os = "windows") {
+        quote_windows(arg)
+    } else {
+        quote_posix(arg)
+    }
+}
+
+fn quote_posix(arg: &str) -> String {
+    let is_plain = |c: char| c.is_ascii_alphanumeric() || "_-./:=@%+,".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_plain) {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+fn quote_windows(arg: &str) -> String {
+    // '@' opens a splat or array at the start of a PowerShell argument
+    let is_plain = |c: char| c.is_ascii_alphanumeric() || r"_-./:=+,\".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_plain) {
+        return arg.to_string();
+    }
+
+    // Backslashes are literal unless they precede a quote, see CommandLineToArgvW
+    let mut quoted = String::from("\"");
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                quoted.push_str(&"\\".repeat(backslashes + 1));
+                backslashes = 0;
+            }
+            _ => backslashes = 0,
+        }
+        quoted.push(c);
+    }
+    quoted.push_str(&"\\".repeat(backslashes));
+    quoted.push('"');
+    quoted
+}
+
+/// Split a command line into arguments.
+///
+/// Accepts both POSIX and Windows habits: single and double quotes, and line
+/// continuations with `\`, `^` or a backtick. Backslashes only escape
+/// whitespace, quotes and other backslashes, so Windows paths survive.
+pub fn split_command_line(line: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current: Option<String> = None;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' | '^' | '`' if matches!(chars.peek(), Some('\n') | Some('\r')) => {
+                while matches!(chars.peek(), Some('\n') | Some('\r')) {
+                    chars.next();
+                }
+                if let Some(arg) = current.take() {
+                    args.push(arg);
+                }
+            }
+            c if c.is_whitespace() => {
+                if let Some(arg) = current.take() {
+                    args.push(arg);
+                }
+            }
+            '\'' => {
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => return Err("Unterminated single quote in command line".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let arg = current.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') if matches!(chars.peek(), Some('"') | Some('\\')) => {
+                            arg.push(chars.next().unwrap_or('\\'));
+                        }
+                        Some(c) => arg.push(c),
+                        None => return Err("Unterminated double quote in command line".to_string()),
+                    }
+                }
+            }
+            '\\' if chars.peek().is_some_and(|next| next.is_whitespace() || "'\"\\".contains(*next)) => {
+                let escaped = chars.next().unwrap_or('\\');
+                current.get_or_insert_with(String::new).push(escaped);
+            }
+            c => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if let Some(arg) = current {
+        args.push(arg);
+    }
+    Ok(args)
+}
+
+/// Options as scrcpy itself defaults them, before any argument is applied
+fn bare_options() -> ScrcpyOptions {
+    ScrcpyOptions {
+        max_size: None,
+        bit_rate: None,
+        max_fps: None,
+        stay_awake: false,
+        ..Default::default()
+    }
+}
+
+/// Parse a scrcpy command line, as copied from a terminal or a script.
+///
+/// The program name may be included or left out. Options scrcpy would
+/// reject are an error; flags the app does not support are returned in
+/// `unsupported` rather than failing the whole command line.
+pub fn parse_scrcpy_args(line: &str) -> Result<ParsedScrcpyArgs, String> {
+    let mut args = split_command_line(line)?.into_iter().peekable();
+
+    // Skip the program itself ("scrcpy", "scrcpy.exe", or a full path)
+    if args.peek().is_some_and(|arg| !arg.starts_with('-')) {
+        args.next();
+    }
+
+    let mut parsed = ParsedScrcpyArgs {
+        options: bare_options(),
+        device_id: None,
+        unsupported: Vec::new(),
+    };
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            parsed.unsupported.extend(args.by_ref());
+            break;
+        }
+
+        if let Some(long) = arg.strip_prefix("--") {
+            let (name, inline_value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (long, None),
+            };
+
+            // The value of --new-display is optional, so it is only taken when attached
+            if name == "new-display" {
+                parsed.options.new_display = Some(parse_new_display(inline_value.as_deref().unwrap_or(""))?);
+                continue;
+            }
+
+            if apply_flag(&mut parsed.options, name) {
+                if inline_value.is_some() {
+                    return Err(format!("Option --{} does not take a value", name));
+                }
+                continue;
+            }
+
+            if VALUE_OPTIONS.contains(&name) {
+                // Like getopt, take the next argument even if it starts with '-' (as in `--angle -90`)
+                let value = inline_value.or_else(|| args.next())
+                    .ok_or_else(|| format!("Option --{} requires a value", name))?;
+                apply_value(&mut parsed, name, &value)?;
+                continue;
+            }
+
+            // Unknown options: scrcpy takes no positional arguments, so a
+            // following non-option argument must be the value
+            match args.next_if(|next| inline_value.is_none() && !next.starts_with('-')) {
+                Some(value) => parsed.unsupported.push(format!("{} {}", arg, quote_arg(&value))),
+                None => parsed.unsupported.push(arg),
+            }
+        } else if let Some(short) = arg.strip_prefix('-').filter(|short| !short.is_empty()) {
+            // getopt style: "-Sw" is "-S -w", "-m1024" is "-m 1024"
+            let mut chars = short.chars();
+            while let Some(flag) = chars.next() {
+                match short_option(flag) {
+                    Some((name, false)) => {
+                        apply_flag(&mut parsed.options, name);
+                    }
+                    Some((name, true)) => {
+                        let attached: String = chars.by_ref().collect();
+                        let value = if attached.is_empty() { args.next() } else { Some(attached) };
+                        let value = value.ok_or_else(|| format!("Option -{} requires a value", flag))?;
+                        apply_value(&mut parsed, name, &value)?;
+                    }
+                    None => {
+                        // Keep the value of an unmodeled option with it, so "-p 27183" is one entry
+                        let takes_value = KNOWN_SHORT_FLAGS.iter()
+                            .find(|(known, _, _)| *known == flag)
+                            .map(|(_, _, value)| *value == FlagValue::Required);
+                        let attached = chars.as_str();
+                        if takes_value == Some(false) {
+                            parsed.unsupported.push(format!("-{}", flag));
+                        } else if !attached.is_empty() {
+                            parsed.unsupported.push(format!("-{}{}", flag, attached));
+                            break;
+                        } else {
+                            // Unknown options only get a following argument that is not an option
+                            let value = match takes_value {
+                                Some(_) => args.next(),
+                                None => args.next_if(|next| !next.starts_with('-')),
+                            };
+                            match value {
+                                Some(value) => parsed.unsupported.push(format!("-{} {}", flag, quote_arg(&value))),
+                                None => parsed.unsupported.push(format!("-{}", flag)),
+                            }
+                        }
+                    }
+                }
+            }
+        } else {
+            // scrcpy takes no positional arguments
+            parsed.unsupported.push(arg);
+        }
+    }
+
+    parsed.options.validate()?;
+    Ok(parsed)
+}
+
+/// Long option behind a short one, and whether it takes a value
+fn short_option(flag: char) -> Option<(&'static str, bool)> {
+    match flag {
+        'm' => Some(("max-size", true)),
+        'b' => Some(("video-bit-rate", true)),
+        'r' => Some(("record", true)),
+        's' => Some(("serial", true)),
+        'S' => Some(("turn-screen-off", false)),
+        'w' => Some(("stay-awake", false)),
+        'N' => Some(("no-playback", false)),
+        'f' => Some(("fullscreen", false)),
+        _ => None,
+    }
+}
+
+/// Supported long options that take a value
+const VALUE_OPTIONS: &[&str] = &[
+    "serial", "max-size", "video-bit-rate", "bit-rate", "max-fps", "video-codec",
+    "video-encoder", "video-codec-options", "display-id", "crop", "orientation",
+    "capture-orientation", "angle", "audio-codec", "audio-bit-rate", "audio-source",
+    "audio-buffer", "record", "record-format", "record-orientation", "time-limit",
+    "video-source", "camera-id", "camera-facing", "camera-size", "camera-ar",
+    "camera-fps", "start-app", "window-title", "window-x", "window-y", "window-width",
+    "window-height",
+];
+
+/// Apply a supported option without a value, returning false for any other option
+fn apply_flag(options: &mut ScrcpyOptions, name: &str) -> bool {
+    match name {
+        "always-on-top" => options.always_on_top = true,
+        "stay-awake" => options.stay_awake = true,
+        "turn-screen-off" => options.turn_screen_off = true,
+        "no-audio" => options.audio = false,
+        "audio-dup" => options.audio_dup = true,
+        "no-playback" => options.no_playback = true,
+        "camera-high-speed" => options.camera_high_speed = true,
+        "no-vd-destroy-content" => options.no_vd_destroy_content = true,
+        "no-vd-system-decorations" => options.no_vd_system_decorations = true,
+        "window-borderless" => options.window_borderless = true,
+        "fullscreen" => options.fullscreen = true,
+        "disable-screensaver" => options.disable_screensaver = true,
+        _ => return false,
+    }
+    true
+}
+
+/// Apply one of `VALUE_OPTIONS` with its value
+fn apply_value(parsed: &mut ParsedScrcpyArgs, name: &str, value: &str) -> Result<(), String> {
+    let options = &mut parsed.options;
+    match name {
+        "serial" => parsed.device_id = Some(value.to_string()),
+        "max-size" => options.max_size = Some(parse_number(name, value)?),
+        "video-bit-rate" | "bit-rate" => options.bit_rate = Some(parse_bit_rate(name, value)?),
+        "max-fps" => options.max_fps = Some(parse_number(name, value)?),
+        "video-codec" => options.video_codec = Some(parse_enum(name, value)?),
+        "video-encoder" => options.video_encoder = Some(value.to_string()),
+        "video-codec-options" => options.video_codec_options = Some(value.to_string()),
+        "display-id" => options.display_id = Some(parse_number(name, value)?),
+        "crop" => options.crop = Some(parse_crop(value)?),
+        "orientation" => options.orientation = Some(parse_enum(name, value)?),
+        "capture-orientation" => {
+            let (locked, orientation) = match value.strip_prefix('@') {
+                Some(rest) => (true, rest),
+                None => (false, value),
+            };
+            options.capture_orientation_locked = locked;
+            options.capture_orientation = match orientation {
+                "" if locked => None,
+                orientation => Some(parse_enum(name, orientation)?),
+            };
+        }
+        "angle" => options.angle = Some(parse_number(name, value)?),
+        "audio-codec" => options.audio_codec = Some(parse_enum(name, value)?),
+        "audio-bit-rate" => options.audio_bit_rate = Some(parse_bit_rate(name, value)?),
+        "audio-source" => options.audio_source = Some(parse_enum(name, value)?),
+        "audio-buffer" => options.audio_buffer = Some(parse_number(name, value)?),
+        "record" => options.record_path = Some(value.to_string()),
+        "record-format" => options.record_format = Some(parse_enum(name, value)?),
+        "record-orientation" => options.record_orientation = Some(parse_enum(name, value)?),
+        "time-limit" => options.time_limit = Some(parse_number(name, value)?),
+        "video-source" => options.video_source = Some(parse_enum(name, value)?),
+        "camera-id" => options.camera_id = Some(value.to_string()),
+        "camera-facing" => options.camera_facing = Some(parse_enum(name, value)?),
+        "camera-size" => {
+            options.camera_size = Some(Size::parse(value)
+                .ok_or_else(|| format!("Invalid value for --camera-size: '{}'", value))?);
+        }
+        "camera-ar" => options.camera_ar = Some(value.to_string()),
+        "camera-fps" => options.camera_fps = Some(parse_number(name, value)?),
+        "start-app" => options.start_app = Some(parse_start_app(value)),
+        "window-title" => options.window_title = Some(value.to_string()),
+        "window-x" => options.window_x = Some(parse_number(name, value)?),
+        "window-y" => options.window_y = Some(parse_number(name, value)?),
+        "window-width" => options.window_width = Some(parse_number(name, value)?),
+        "window-height" => options.window_height = Some(parse_number(name, value)?),
+        _ => return Err(format!("Unsupported option --{}", name)),
+    }
+    Ok(())
+}
+
+/// Whether an option is built from `ScrcpyOptions`, so must not be passed in `extra_args`
+fn is_modeled(name: &str) -> bool {
+    name == "new-display" || VALUE_OPTIONS.contains(&name) || apply_flag(&mut bare_options(), name)
+}
+
+/// Options that pick the device, which the app does for every session
+const DEVICE_SELECTION: &[&str] = &["serial", "select-usb", "select-tcpip", "tcpip"];
+const DEVICE_SELECTION_SHORT: &[char] = &['s', 'd', 'e'];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FlagValue {
+    None,
+    Required,
+    /// Only attached with '=', like `--pause-on-exit=if-error`
+    Optional,
+}
+
+/// A scrcpy option not modeled in `ScrcpyOptions`, allowed in `extra_args`
+struct KnownFlag {
+    name: &'static str,
+    value: FlagValue,
+    since: ScrcpyVersion,
+}
+
+const fn flag(name: &'static str, value: FlagValue, major: u32, minor: u32) -> KnownFlag {
+    KnownFlag { name, value, since: ScrcpyVersion::new(major, minor, 0) }
+}
+
+/// Minimum versions as listed in the scrcpy release notes
+const KNOWN_FLAGS: &[KnownFlag] = &[
+    flag("show-touches", FlagValue::None, 1, 0),
+    flag("port", FlagValue::Required, 1, 0),
+    flag("no-control", FlagValue::None, 1, 5),
+    flag("prefer-text", FlagValue::None, 1, 9),
+    flag("push-target", FlagValue::Required, 1, 10),
+    flag("force-adb-forward", FlagValue::None, 1, 10),
+    flag("verbosity", FlagValue::Required, 1, 11),
+    flag("no-mipmaps", FlagValue::None, 1, 11),
+    flag("render-driver", FlagValue::Required, 1, 13),
+    flag("shortcut-mod", FlagValue::Required, 1, 15),
+    flag("no-key-repeat", FlagValue::None, 1, 16),
+    flag("legacy-paste", FlagValue::None, 1, 16),
+    flag("v4l2-sink", FlagValue::Required, 1, 17),
+    flag("raw-key-events", FlagValue::None, 1, 17),
+    flag("display-buffer", FlagValue::Required, 1, 18),
+    flag("v4l2-buffer", FlagValue::Required, 1, 18),
+    flag("power-off-on-close", FlagValue::None, 1, 18),
+    flag("no-clipboard-autosync", FlagValue::None, 1, 20),
+    flag("no-downsize-on-error", FlagValue::None, 1, 20),
+    flag("otg", FlagValue::None, 1, 22),
+    flag("tunnel-host", FlagValue::Required, 1, 23),
+    flag("tunnel-port", FlagValue::Required, 1, 23),
+    flag("no-cleanup", FlagValue::None, 1, 23),
+    flag("no-power-on", FlagValue::None, 1, 23),
+    flag("print-fps", FlagValue::None, 2, 0),
+    flag("require-audio", FlagValue::None, 2, 0),
+    flag("audio-output-buffer", FlagValue::Required, 2, 1),
+    flag("no-audio-playback", FlagValue::None, 2, 1),
+    flag("kill-adb-on-close", FlagValue::None, 2, 2),
+    flag("pause-on-exit", FlagValue::Optional, 2, 3),
+    flag("keyboard", FlagValue::Required, 2, 4),
+    flag("mouse", FlagValue::Required, 2, 4),
+    flag("screen-off-timeout", FlagValue::Required, 2, 5),
+    flag("no-mouse-hover", FlagValue::None, 2, 6),
+    flag("gamepad", FlagValue::Required, 2, 7),
+    flag("display-ime-policy", FlagValue::Required, 3, 2),
+];
+
+/// Unmodeled options that change what a modeled one controls, with that option
+const OVERLAPPING_FLAGS: &[(&str, &str)] = &[
+    ("display-orientation", "orientation"),
+    ("no-video-playback", "no playback"),
+    ("no-window", "no playback"),
+    ("no-video", "no playback"),
+];
+
+/// Short forms of `KNOWN_FLAGS`, and whether they take a value
+/// (`-K` is `--keyboard=uhid`, for example)
+const KNOWN_SHORT_FLAGS: &[(char, &str, FlagValue)] = &[
+    ('t', "show-touches", FlagValue::None),
+    ('n', "no-control", FlagValue::None),
+    ('p', "port", FlagValue::Required),
+    ('V', "verbosity", FlagValue::Required),
+    ('K', "keyboard", FlagValue::None),
+    ('M', "mouse", FlagValue::None),
+    ('G', "gamepad", FlagValue::None),
+];
+
+/// Check raw arguments for `extra_args`.
+///
+/// Each must be a scrcpy option the app knows but does not model: modeled
+/// options, options overlapping them and device selection would conflict with
+/// the arguments built from `ScrcpyOptions`. Given the scrcpy version, options
+/// newer than it are rejected as well.
+pub fn validate_extra_args(args: &[String], version: Option<&ScrcpyVersion>) -> Result<(), String> {
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        if let Some(long) = arg.strip_prefix("--") {
+            let (name, inline_value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (long, None),
+            };
+
+            if DEVICE_SELECTION.contains(&name) {
+                return Err(selects_device(arg));
+            }
+            if is_modeled(name) {
+                return Err(conflicts_with_options(arg));
+            }
+
+            let known = known_flag(arg, name, version)?;
+            match known.value {
+                FlagValue::None if inline_value.is_some() => {
+                    return Err(format!("Option '{}' does not take a value", arg));
+                }
+                FlagValue::Required if inline_value.is_none() => {
+                    args.next().ok_or_else(|| format!("Option '{}' requires a value", arg))?;
+                }
+                _ => {}
+            }
+        } else if let Some(group) = arg.strip_prefix('-').filter(|group| !group.is_empty()) {
+            // getopt style, as in `parse_scrcpy_args`: "-tK" is "-t -K", "-p27183" is "-p 27183"
+            let mut chars = group.chars();
+            while let Some(short) = chars.next() {
+                let flag = format!("-{}", short);
+                if DEVICE_SELECTION_SHORT.contains(&short) {
+                    return Err(selects_device(&flag));
+                }
+                if short_option(short).is_some() {
+                    return Err(conflicts_with_options(&flag));
+                }
+
+                let (_, name, value) = KNOWN_SHORT_FLAGS.iter()
+                    .find(|(known, _, _)| *known == short)
+                    .ok_or_else(|| unknown_option(&flag))?;
+                known_flag(&flag, name, version)?;
+
+                if *value == FlagValue::Required {
+                    // The rest of the group is the value, or else the next argument
+                    if chars.as_str().is_empty() {
+                        args.next().ok_or_else(|| format!("Option '{}' requires a value", flag))?;
+                    }
+                    break;
+                }
+            }
+        } else {
+            return Err(format!("Unexpected extra argument '{}': only scrcpy options can be added", arg));
+        }
+    }
+
+    Ok(())
+}
+
+/// The entry of `KNOWN_FLAGS` for `name`, if the scrcpy `version` has it
+fn known_flag(arg: &str, name: &str, version: Option<&ScrcpyVersion>) -> Result<&'static KnownFlag, String> {
+    if let Some((_, option)) = OVERLAPPING_FLAGS.iter().find(|(flag, _)| *flag == name) {
+        return Err(format!("Extra argument '{}' conflicts with the {} option; set it there instead", arg, option));
+    }
+
+    let known = KNOWN_FLAGS.iter()
+        .find(|flag| flag.name == name)
+        .ok_or_else(|| unknown_option(arg))?;
+
+    if let Some(version) = version {
+        if *version < known.since {
+            return Err(format!(
+                "Option --{} requires scrcpy {} or newer (found {})",
+                known.name, known.since, version
+            ));
+        }
+    }
+
+    Ok(known)
+}
+
+fn selects_device(arg: &str) -> String {
+    format!("Extra argument '{}' selects the device, which the app does for each session", arg)
+}
+
+fn conflicts_with_options(arg: &str) -> String {
+    format!("Extra argument '{}' conflicts with the mirroring options; set it there instead", arg)
+}
+
+fn unknown_option(arg: &str) -> String {
+    format!("Unknown scrcpy option in extra arguments: '{}'", arg)
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("Invalid value for --{}: '{}'", name, value))
+}
+
+/// Enum values are spelled the same on the command line and in their serde form
+fn parse_enum<T: DeserializeOwned>(name: &str, value: &str) -> Result<T, String> {
+    serde_json::from_value(serde_json::Value::String(value.to_string()))
+        .map_err(|_| format!("Invalid value for --{}: '{}'", name, value))
+}
+
+/// Bit rate in bits per second, with an optional K or M suffix ("8M")
+fn parse_bit_rate(name: &str, value: &str) -> Result<u32, String> {
+    let (digits, multiplier) = match value.char_indices().last() {
+        Some((i, 'K' | 'k')) => (&value[..i], 1_000),
+        Some((i, 'M' | 'm')) => (&value[..i], 1_000_000),
+        _ => (value, 1),
+    };
+
+    digits.parse::<u32>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| format!("Invalid value for --{}: '{}'", name, value))
+}
+
+fn parse_crop(value: &str) -> Result<Crop, String> {
+    let parts: Vec<u32> = value.split(':')
+        .map(|part| part.parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("Invalid value for --crop: '{}' (expected width:height:x:y)", value))?;
+
+    match parts[..] {
+        [width, height, x, y] => Ok(Crop { width, height, x, y }),
+        _ => Err(format!("Invalid value for --crop: '{}' (expected width:height:x:y)", value)),
+    }
+}
+
+/// `[<width>x<height>][/<dpi>]`
+fn parse_new_display(value: &str) -> Result<NewDisplay, String> {
+    let invalid = || format!("Invalid value for --new-display: '{}'", value);
+    let (size, dpi) = match value.split_once('/') {
+        Some((size, dpi)) => (size, Some(dpi.parse().map_err(|_| invalid())?)),
+        None => (value, None),
+    };
+
+    let size = match size {
+        "" => None,
+        size => Some(Size::parse(size).ok_or_else(invalid)?),
+    };
+
+    Ok(NewDisplay { size, dpi })
+}
+
+/// `[+][?]<package or name>`
+fn parse_start_app(value: &str) -> StartApp {
+    let force_stop = value.starts_with('+');
+    let value = value.strip_prefix('+').unwrap_or(value);
+    let search = value.starts_with('?');
+    let value = value.strip_prefix('?').unwrap_or(value);
+
+    StartApp {
+        package: value.to_string(),
+        force_stop,
+        search,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{build_scrcpy_command, AudioSource, Orientation, VideoCodec, VideoSource};
+    use std::path::Path;
+
+    /// Arguments `build_scrcpy_command` passes to scrcpy for these options
+    fn scrcpy_args(device_id: Option<&str>, options: &ScrcpyOptions) -> Vec<String> {
+        let cmd = build_scrcpy_command(Path::new("scrcpy"), Path::new("."), None, device_id, options);
+        cmd.get_args().map(|arg| arg.to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn test_parse_round_trip() {
+        let options = ScrcpyOptions {
+            max_size: Some(1024),
+            video_codec: Some(VideoCodec::H265),
+            crop: Some(Crop { width: 1080, height: 1920, x: 0, y: 100 }),
+            capture_orientation: Some(Orientation::Deg90),
+            capture_orientation_locked: true,
+            angle: Some(-12.5),
+            audio_source: Some(AudioSource::Playback),
+            audio_dup: true,
+            record_path: Some("C:\\Users\\me\\My Videos\\demo.mkv".to_string()),
+            new_display: Some(NewDisplay { size: None, dpi: Some(240) }),
+            start_app: Some(StartApp { package: "org.mozilla.firefox".to_string(), force_stop: true, search: false }),
+            ..Default::default()
+        };
+
+        let args = scrcpy_args(Some("192.168.1.20:5555"), &options);
+        let line = std::iter::once("scrcpy".to_string())
+            .chain(args.iter().map(|arg| quote_arg(arg)))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let parsed = parse_scrcpy_args(&line).unwrap();
+        assert!(parsed.unsupported.is_empty(), "{:?}", parsed.unsupported);
+        assert_eq!(parsed.device_id.as_deref(), Some("192.168.1.20:5555"));
+        assert_eq!(scrcpy_args(parsed.device_id.as_deref(), &parsed.options), args);
+    }
+
+    #[test]
+    fn test_parse_starts_from_scrcpy_defaults() {
+        let parsed = parse_scrcpy_args("scrcpy").unwrap();
+        assert!(scrcpy_args(None, &parsed.options).is_empty());
+        assert!(parsed.options.audio);
+    }
+
+    #[test]
+    fn test_parse_short_and_attached_values() {
+        let parsed = parse_scrcpy_args("scrcpy.exe -Sw -m1024 -b 8M --max-fps=30 -s R5CT12345").unwrap();
+        let options = &parsed.options;
+        assert!(options.turn_screen_off && options.stay_awake);
+        assert_eq!(options.max_size, Some(1024));
+        assert_eq!(options.bit_rate, Some(8_000_000));
+        assert_eq!(options.max_fps, Some(30));
+        assert_eq!(parsed.device_id.as_deref(), Some("R5CT12345"));
+
+        let parsed = parse_scrcpy_args("--video-source=camera --camera-facing front --camera-size 1920x1080").unwrap();
+        assert_eq!(parsed.options.video_source, Some(VideoSource::Camera));
+        assert_eq!(parsed.options.camera_size, Some(Size { width: 1920, height: 1080 }));
+    }
+
+    #[test]
+    fn test_parse_reports_unsupported_flags() {
+        let parsed = parse_scrcpy_args(
+            "scrcpy --show-touches -t --push-target '/sdcard/My files/' --max-size 800 --keyboard=uhid -p 27183 -tKw -V1 -Z -w"
+        ).unwrap();
+
+        assert_eq!(parsed.options.max_size, Some(800));
+        assert!(parsed.options.stay_awake);
+        assert_eq!(parsed.unsupported, vec![
+            "--show-touches".to_string(),
+            "-t".to_string(),
+            format!("--push-target {}", quote_arg("/sdcard/My files/")),
+            "--keyboard=uhid".to_string(),
+            "-p 27183".to_string(),
+            "-t".to_string(),
+            "-K".to_string(),
+            "-V1".to_string(),
+            "-Z".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn test_parse_window_options() {
+        let parsed = parse_scrcpy_args(
+            "scrcpy -f --window-title 'My phone' --window-x -1080 --window-y=0 --window-width 540 --window-height 1170 --window-borderless --disable-screensaver"
+        ).unwrap();
+
+        assert!(parsed.unsupported.is_empty(), "{:?}", parsed.unsupported);
+        let options = &parsed.options;
+        assert!(options.fullscreen);
+        assert_eq!(options.window_title.as_deref(), Some("My phone"));
+        assert_eq!((options.window_x, options.window_y), (Some(-1080), Some(0)));
+        assert_eq!((options.window_width, options.window_height), (Some(540), Some(1170)));
+        assert!(options.window_borderless);
+        assert!(options.disable_screensaver);
+
+        let args = scrcpy_args(None, options);
+        assert!(args.windows(2).any(|pair| pair == ["--window-x", "-1080"]), "{:?}", args);
+        assert!(args.contains(&"--fullscreen".to_string()));
+
+        assert!(parse_scrcpy_args("scrcpy --window-x 40000").is_err());
+    }
+
+    #[test]
+    fn test_validate_extra_args() {
+        let args = |line: &str| split_command_line(line).unwrap();
+
+        assert!(validate_extra_args(&args("--show-touches -K --push-target '/sdcard/My files/' --pause-on-exit=if-error"), None).is_ok());
+        assert!(validate_extra_args(&args("-p 27199 --verbosity=debug -Vinfo"), None).is_ok());
+        // Grouped short flags, the last one possibly taking a value
+        assert!(validate_extra_args(&args("-tK -tp 27199 -tVinfo"), None).is_ok());
+        assert_eq!(
+            validate_extra_args(&args("-tS"), None).unwrap_err(),
+            "Extra argument '-S' conflicts with the mirroring options; set it there instead"
+        );
+        assert!(validate_extra_args(&args("-tx"), None).is_err());
+        assert!(validate_extra_args(&args("-tp"), None).is_err());
+
+        assert_eq!(
+            validate_extra_args(&args("--max-size 800"), None).unwrap_err(),
+            "Extra argument '--max-size' conflicts with the mirroring options; set it there instead"
+        );
+        assert!(validate_extra_args(&args("-m1024"), None).is_err());
+        assert!(validate_extra_args(&args("--no-audio"), None).is_err());
+        assert!(validate_extra_args(&args("--new-display=1920x1080"), None).is_err());
+        assert_eq!(
+            validate_extra_args(&args("-s R5CT12345"), None).unwrap_err(),
+            "Extra argument '-s' selects the device, which the app does for each session"
+        );
+        assert!(validate_extra_args(&args("--select-tcpip"), None).is_err());
+        assert!(validate_extra_args(&args("-te"), None).is_err());
+
+        assert_eq!(
+            validate_extra_args(&args("--display-orientation=90"), None).unwrap_err(),
+            "Extra argument '--display-orientation=90' conflicts with the orientation option; set it there instead"
+        );
+        for arg in ["--no-video-playback", "--no-window", "--no-video"] {
+            assert!(validate_extra_args(&args(arg), None).unwrap_err().contains("no playback"), "{}", arg);
+        }
+
+        assert_eq!(
+            validate_extra_args(&args("--frobnicate"), None).unwrap_err(),
+            "Unknown scrcpy option in extra arguments: '--frobnicate'"
+        );
+        assert!(validate_extra_args(&args("-x"), None).is_err());
+        assert!(validate_extra_args(&args("show-touches"), None).is_err());
+        assert!(validate_extra_args(&args("--push-target"), None).is_err());
+        assert!(validate_extra_args(&args("--window-title Pixel"), None).is_err());
+        assert!(validate_extra_args(&args("--show-touches=yes"), None).is_err());
+
+        let gamepad = args("--gamepad=uhid");
+        assert_eq!(
+            validate_extra_args(&gamepad, Some(&ScrcpyVersion::new(2, 6, 0))).unwrap_err(),
+            "Option --gamepad requires scrcpy 2.7.0 or newer (found 2.6.0)"
+        );
+        assert!(validate_extra_args(&gamepad, Some(&ScrcpyVersion::new(3, 3, 3))).is_ok());
+        assert!(validate_extra_args(&args("-G"), Some(&ScrcpyVersion::new(2, 4, 0))).is_err());
+        assert_eq!(
+            validate_extra_args(&args("--otg"), Some(&ScrcpyVersion::new(1, 21, 0))).unwrap_err(),
+            "Option --otg requires scrcpy 1.22.0 or newer (found 1.21.0)"
+        );
+    }
+
+    #[test]
+    fn test_extra_args_appended() {
+        let options = ScrcpyOptions {
+            extra_args: vec!["--push-target".to_string(), "/sdcard/My files/".to_string(), "-t".to_string()],
+            ..Default::default()
+        };
+        assert!(options.validate().is_ok());
+
+        let args = scrcpy_args(Some("R5CT12345"), &options);
+        assert_eq!(&args[args.len() - 3..], ["--push-target", "/sdcard/My files/", "-t"]);
+
+        let conflicting = ScrcpyOptions {
+            extra_args: vec!["--max-fps".to_string(), "30".to_string()],
+            ..Default::default()
+        };
+        assert!(conflicting.validate().is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_values() {
+        let err = parse_scrcpy_args("scrcpy --video-codec vp9").unwrap_err();
+        assert_eq!(err, "Invalid value for --video-codec: 'vp9'");
+
+        assert!(parse_scrcpy_args("scrcpy --max-size").is_err());
+        assert!(parse_scrcpy_args("scrcpy --crop 100:200").is_err());
+        assert!(parse_scrcpy_args("scrcpy --always-on-top=yes").is_err());
+        // Parsed fine, but scrcpy would refuse the combination
+        assert!(parse_scrcpy_args("scrcpy --no-playback").is_err());
+    }
+
+    #[test]
+    fn test_split_command_line() {
+        assert_eq!(
+            split_command_line(r#"scrcpy --record "C:\Users\me\My Videos\a.mp4" --window-title 'it''s' \
+                --crop=1:2:3:4"#).unwrap(),
+            vec!["scrcpy", "--record", r"C:\Users\me\My Videos\a.mp4", "--window-title", "its", "--crop=1:2:3:4"]
+        );
+        assert_eq!(
+            split_command_line("C:\\scrcpy\\scrcpy.exe -m 1024 ^\r\n  --no-audio").unwrap(),
+            vec![r"C:\scrcpy\scrcpy.exe", "-m", "1024", "--no-audio"]
+        );
+        assert_eq!(split_command_line(r#"a "say \"hi\"" b\ c"#).unwrap(), vec!["a", "say \"hi\"", "b c"]);
+        assert!(split_command_line("scrcpy --record 'unterminated").is_err());
+    }
+
+    #[test]
+    fn test_quote_posix() {
+        assert_eq!(quote_posix("--max-size"), "--max-size");
+        assert_eq!(quote_posix("192.168.1.20:5555"), "192.168.1.20:5555");
+        assert_eq!(quote_posix("My Videos/a.mp4"), "'My Videos/a.mp4'");
+        assert_eq!(quote_posix("it's"), r"'it'\''s'");
+        assert_eq!(quote_posix(""), "''");
+
+        let args = ["/tmp/My Videos/a.mp4", "it's", "$HOME", ""];
+        let line = args.iter().map(|arg| quote_posix(arg)).collect::<Vec<_>>().join(" ");
+        assert_eq!(split_command_line(&line).unwrap(), args);
+    }
+
+    #[test]
+    fn test_quote_windows() {
+        assert_eq!(quote_windows(r"C:\scrcpy\scrcpy.exe"), r"C:\scrcpy\scrcpy.exe");
+        assert_eq!(quote_windows(r"C:\My Videos\a.mp4"), r#""C:\My Videos\a.mp4""#);
+        assert_eq!(quote_windows("@90"), r#""@90""#);
+        assert_eq!(quote_windows(r#"say "hi""#), r#""say \"hi\"""#);
+        // A trailing backslash must not escape the closing quote
+        assert_eq!(quote_windows(r"C:\My Videos\"), r#""C:\My Videos\\""#);
+    }
+}
@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use super::ScrcpyVersion;
+
+/// A scrcpy feature that only some releases have
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Capability {
+    /// Audio forwarding and the `--audio-*` options
+    Audio,
+    /// `--video-codec`, `--video-encoder` and `--video-codec-options`
+    VideoCodec,
+    /// Recording without a window (`--no-playback`)
+    NoPlayback,
+    /// Display and record orientation (`--orientation`)
+    Orientation,
+    /// Rotating the recording only (`--record-orientation`)
+    RecordOrientation,
+    /// Stopping the session after a while (`--time-limit`)
+    TimeLimit,
+    /// Mirroring a camera (`--video-source=camera` and `--camera-*`)
+    Camera,
+    /// The FLAC audio codec
+    FlacAudio,
+    /// Keyboard simulated through the device's UHID driver (`--keyboard=uhid`)
+    UhidKeyboard,
+    /// Mouse simulated through the device's UHID driver (`--mouse=uhid`)
+    UhidMouse,
+    /// Capturing app playback while it keeps playing on the device (`--audio-dup`)
+    AudioDup,
+    /// Gamepads forwarded to the device (`--gamepad=uhid`)
+    Gamepad,
+    /// Mirroring a new virtual display (`--new-display`)
+    NewDisplay,
+    /// Starting an app on launch (`--start-app`)
+    StartApp,
+    /// Rotating the capture on the device (`--capture-orientation`)
+    CaptureOrientation,
+    /// Rotating the video by any angle (`--angle`)
+    Angle,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 16] = [
+        Capability::Audio,
+        Capability::VideoCodec,
+        Capability::NoPlayback,
+        Capability::Orientation,
+        Capability::RecordOrientation,
+        Capability::TimeLimit,
+        Capability::Camera,
+        Capability::FlacAudio,
+        Capability::UhidKeyboard,
+        Capability::UhidMouse,
+        Capability::AudioDup,
+        Capability::Gamepad,
+        Capability::NewDisplay,
+        Capability::StartApp,
+        Capability::CaptureOrientation,
+        Capability::Angle,
+    ];
+
+    /// First scrcpy release with the feature
+    pub fn min_version(&self) -> ScrcpyVersion {
+        match self {
+            Capability::Audio | Capability::VideoCodec => ScrcpyVersion::new(2, 0, 0),
+            Capability::NoPlayback
+            | Capability::Orientation
+            | Capability::RecordOrientation
+            | Capability::TimeLimit => ScrcpyVersion::new(2, 1, 0),
+            Capability::Camera => ScrcpyVersion::new(2, 2, 0),
+            Capability::FlacAudio => ScrcpyVersion::new(2, 3, 0),
+            Capability::UhidKeyboard | Capability::UhidMouse => ScrcpyVersion::new(2, 4, 0),
+            Capability::AudioDup => ScrcpyVersion::new(2, 6, 0),
+            Capability::Gamepad => ScrcpyVersion::new(2, 7, 0),
+            Capability::NewDisplay
+            | Capability::StartApp
+            | Capability::CaptureOrientation
+            | Capability::Angle => ScrcpyVersion::new(3, 0, 0),
+        }
+    }
+
+    pub fn is_supported_by(&self, version: &ScrcpyVersion) -> bool {
+        *version >= self.min_version()
+    }
+}
+
+/// Whether one feature is available in the scrcpy in use
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CapabilityInfo {
+    pub capability: Capability,
+    pub min_version: ScrcpyVersion,
+    pub supported: bool,
+}
+
+/// What the scrcpy in use can do, for greying out unsupported settings
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScrcpyCapabilities {
+    pub version: ScrcpyVersion,
+    pub capabilities: Vec<CapabilityInfo>,
+}
+
+impl ScrcpyCapabilities {
+    pub fn for_version(version: ScrcpyVersion) -> Self {
+        let capabilities = Capability::ALL
+            .iter()
+            .map(|capability| CapabilityInfo {
+                capability: *capability,
+                min_version: capability.min_version(),
+                supported: capability.is_supported_by(&version),
+            })
+            .collect();
+
+        Self { version, capabilities }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn supports(capabilities: &ScrcpyCapabilities, capability: Capability) -> bool {
+        capabilities.capabilities.iter().any(|info| info.capability == capability && info.supported)
+    }
+
+    #[test]
+    fn test_capabilities_for_version() {
+        let v1 = ScrcpyCapabilities::for_version(ScrcpyVersion::new(1, 25, 0));
+        assert!(Capability::ALL.iter().all(|capability| !supports(&v1, *capability)));
+
+        let v2 = ScrcpyCapabilities::for_version(ScrcpyVersion::new(2, 4, 0));
+        assert!(supports(&v2, Capability::Audio));
+        assert!(supports(&v2, Capability::Camera));
+        assert!(supports(&v2, Capability::UhidKeyboard));
+        assert!(!supports(&v2, Capability::AudioDup));
+        assert!(!supports(&v2, Capability::NewDisplay));
+
+        let v3 = ScrcpyCapabilities::for_version(ScrcpyVersion::new(3, 3, 3));
+        assert!(v3.capabilities.iter().all(|info| info.supported));
+        assert_eq!(v3.capabilities.len(), Capability::ALL.len());
+        assert_eq!(v3.capabilities[0].min_version, ScrcpyVersion::new(2, 0, 0));
+    }
+}
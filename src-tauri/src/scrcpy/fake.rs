@@ -0,0 +1,212 @@
+//! In-memory stand-in for scrcpy, for testing session flows without binaries
+
+use std::collections::VecDeque;
+use std::io::{Cursor, Read};
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use super::{LaunchRequest, Launcher, ProcessState, ScrcpyProcess, SessionProcess};
+
+/// How a fake scrcpy process behaves
+#[derive(Debug, Clone)]
+pub struct FakeScript {
+    /// Lines printed on stdout right away
+    pub stdout: Vec<String>,
+    /// Lines printed on stderr right away
+    pub stderr: Vec<String>,
+    /// Exit immediately with this code, as when the device is missing
+    pub exit_on_start: Option<i32>,
+    /// Keep running after a termination request, so only a kill stops it
+    pub ignore_exit_request: bool,
+    /// Fail to spawn at all, as when the binary is missing
+    pub spawn_error: Option<String>,
+}
+
+impl Default for FakeScript {
+    /// A session that starts up and runs until asked to exit
+    fn default() -> Self {
+        Self {
+            stdout: vec!["INFO: Renderer: opengl".to_string()],
+            stderr: Vec::new(),
+            exit_on_start: None,
+            ignore_exit_request: false,
+            spawn_error: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct FakeStatus {
+    exit: Option<ExitStatus>,
+    exit_requested: bool,
+    killed: bool,
+}
+
+/// Test-side control of a process started by `FakeLauncher`
+#[derive(Debug, Clone)]
+pub struct FakeHandle {
+    pub pid: u32,
+    pub device_id: Option<String>,
+    status: Arc<Mutex<FakeStatus>>,
+}
+
+impl FakeHandle {
+    /// Make the process exit on its own, as on a crash or a closed window
+    pub fn exit(&self, code: i32) {
+        self.status.lock().unwrap().exit.get_or_insert(exit_status(code));
+    }
+
+    pub fn exit_requested(&self) -> bool {
+        self.status.lock().unwrap().exit_requested
+    }
+
+    pub fn killed(&self) -> bool {
+        self.status.lock().unwrap().killed
+    }
+
+    pub fn has_exited(&self) -> bool {
+        self.status.lock().unwrap().exit.is_some()
+    }
+}
+
+#[derive(Debug)]
+pub struct FakeProcess {
+    pid: u32,
+    status: Arc<Mutex<FakeStatus>>,
+    stdout: Option<Vec<u8>>,
+    stderr: Option<Vec<u8>>,
+    ignore_exit_request: bool,
+}
+
+impl ScrcpyProcess for FakeProcess {
+    fn id(&self) -> u32 {
+        self.pid
+    }
+
+    fn try_wait(&mut self) -> std::io::Result<ProcessState> {
+        Ok(match self.status.lock().unwrap().exit {
+            Some(status) => ProcessState::Exited(Some(status)),
+            None => ProcessState::Running,
+        })
+    }
+
+    fn request_exit(&mut self) -> Result<(), String> {
+        let mut status = self.status.lock().unwrap();
+        status.exit_requested = true;
+        if !self.ignore_exit_request {
+            status.exit.get_or_insert(exit_status(0));
+        }
+        Ok(())
+    }
+
+    fn kill(&mut self) -> std::io::Result<()> {
+        let mut status = self.status.lock().unwrap();
+        status.killed = true;
+        status.exit.get_or_insert(killed_status());
+        Ok(())
+    }
+
+    fn wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+        loop {
+            if let ProcessState::Exited(status) = self.try_wait()? {
+                return Ok(status);
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.stdout.take().map(|bytes| Box::new(Cursor::new(bytes)) as Box<dyn Read + Send>)
+    }
+
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.stderr.take().map(|bytes| Box::new(Cursor::new(bytes)) as Box<dyn Read + Send>)
+    }
+}
+
+/// Launcher that starts `FakeProcess`es following queued scripts
+#[derive(Debug, Default)]
+pub struct FakeLauncher {
+    scripts: Mutex<VecDeque<FakeScript>>,
+    spawned: Mutex<Vec<FakeHandle>>,
+    next_pid: AtomicU32,
+}
+
+impl FakeLauncher {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            next_pid: AtomicU32::new(10_000),
+            ..Default::default()
+        })
+    }
+
+    /// Script for the next spawn; spawns without a queued script use the default one
+    pub fn push_script(&self, script: FakeScript) {
+        self.scripts.lock().unwrap().push_back(script);
+    }
+
+    /// Processes started so far, oldest first
+    pub fn spawned(&self) -> Vec<FakeHandle> {
+        self.spawned.lock().unwrap().clone()
+    }
+
+    pub fn last(&self) -> FakeHandle {
+        self.spawned().pop().expect("no fake process was spawned")
+    }
+}
+
+impl Launcher for FakeLauncher {
+    fn spawn(&self, request: &LaunchRequest) -> Result<SessionProcess, String> {
+        let script = self.scripts.lock().unwrap().pop_front().unwrap_or_default();
+        if let Some(error) = script.spawn_error {
+            return Err(format!("Failed to start scrcpy: {}", error));
+        }
+
+        let status = Arc::new(Mutex::new(FakeStatus {
+            exit: script.exit_on_start.map(exit_status),
+            ..Default::default()
+        }));
+        let handle = FakeHandle {
+            pid: self.next_pid.fetch_add(1, Ordering::SeqCst),
+            device_id: request.device_id.map(str::to_string),
+            status: status.clone(),
+        };
+        self.spawned.lock().unwrap().push(handle.clone());
+
+        Ok(Box::new(FakeProcess {
+            pid: handle.pid,
+            status,
+            stdout: Some(lines(&script.stdout)),
+            stderr: Some(lines(&script.stderr)),
+            ignore_exit_request: script.ignore_exit_request,
+        }))
+    }
+}
+
+fn lines(lines: &[String]) -> Vec<u8> {
+    lines.iter().map(|line| format!("{}\n", line)).collect::<String>().into_bytes()
+}
+
+#[cfg(unix)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    ExitStatus::from_raw(code << 8)
+}
+
+#[cfg(unix)]
+fn killed_status() -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    ExitStatus::from_raw(9) // SIGKILL
+}
+
+#[cfg(windows)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+    ExitStatus::from_raw(code as u32)
+}
+
+#[cfg(windows)]
+fn killed_status() -> ExitStatus {
+    exit_status(1)
+}
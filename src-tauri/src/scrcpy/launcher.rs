@@ -0,0 +1,43 @@
+use std::path::Path;
+use std::process::Stdio;
+use super::{build_scrcpy_command, ScrcpyOptions, SessionProcess};
+
+/// Everything needed to start scrcpy for a session
+#[derive(Debug, Clone, Copy)]
+pub struct LaunchRequest<'a> {
+    pub scrcpy_path: &'a Path,
+    pub scrcpy_dir: &'a Path,
+    pub adb_dir: Option<&'a Path>,
+    pub device_id: Option<&'a str>,
+    pub options: &'a ScrcpyOptions,
+}
+
+/// Starts scrcpy processes for sessions.
+///
+/// `ScrcpyState` holds the launcher, so tests can swap in a fake scrcpy.
+pub trait Launcher: Send + Sync {
+    fn spawn(&self, request: &LaunchRequest) -> Result<SessionProcess, String>;
+}
+
+/// Launches the bundled scrcpy binary with its output piped
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemLauncher;
+
+impl Launcher for SystemLauncher {
+    fn spawn(&self, request: &LaunchRequest) -> Result<SessionProcess, String> {
+        let mut cmd = build_scrcpy_command(
+            request.scrcpy_path,
+            request.scrcpy_dir,
+            request.adb_dir,
+            request.device_id,
+            request.options,
+        );
+
+        // Output is captured into the session log, see `capture_output`
+        cmd.stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map(SessionProcess::from)
+            .map_err(|e| format!("Failed to start scrcpy: {}", e))
+    }
+}
@@ -0,0 +1,523 @@
+use serde::{Serialize, Deserialize};
+use crate::utils;
+use super::{base_scrcpy_command, CameraFacing, Size};
+
+/// A device camera, as listed by `scrcpy --list-cameras`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CameraInfo {
+    pub id: String,
+    pub facing: Option<CameraFacing>,
+    /// Active sensor array size
+    pub sensor_size: Option<Size>,
+    /// Frame rates available in normal capture mode
+    pub fps: Vec<u32>,
+    /// Capture sizes (only with `--list-camera-sizes`)
+    pub sizes: Vec<Size>,
+    /// Sizes available with `--camera-high-speed` (only with `--list-camera-sizes`)
+    pub high_speed_sizes: Vec<HighSpeedSize>,
+}
+
+/// A high-speed capture size and its frame rates
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HighSpeedSize {
+    pub size: Size,
+    pub fps: Vec<u32>,
+}
+
+/// Whether an encoder handles video or audio
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EncoderKind {
+    Video,
+    Audio,
+}
+
+/// Hardware or software implementation, as reported on Android 10+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EncoderType {
+    Hw,
+    Sw,
+    Hybrid,
+}
+
+/// A MediaCodec encoder, as listed by `scrcpy --list-encoders`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EncoderInfo {
+    pub kind: EncoderKind,
+    pub codec: String,
+    pub name: String,
+    pub encoder_type: Option<EncoderType>,
+    pub vendor: bool,
+    /// Canonical encoder name when this one is an alias
+    pub alias_for: Option<String>,
+}
+
+/// A device display, as listed by `scrcpy --list-displays`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DisplayInfo {
+    pub id: u32,
+    /// None when the server could not query the size
+    pub size: Option<Size>,
+}
+
+/// An installed app, as listed by `scrcpy --list-apps`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppInfo {
+    pub package: String,
+    pub label: String,
+    pub system: bool,
+}
+
+/// Run scrcpy with a `--list-*` flag for a device and return its combined output
+pub fn run_list_command<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    device_id: &str,
+    flag: &str,
+) -> Result<String, String> {
+    let scrcpy_path = utils::get_scrcpy_path(app)?;
+    let scrcpy_dir = utils::get_scrcpy_dir(app)?;
+    let adb_dir = utils::get_adb_dir(app).ok();
+
+    let mut cmd = base_scrcpy_command(&scrcpy_path, &scrcpy_dir, adb_dir.as_deref(), Some(device_id));
+    cmd.arg(flag);
+
+    let output = cmd.output()
+        .map_err(|e| format!("Failed to execute scrcpy: {}", e))?;
+
+    // The server prints the lists on stdout, client errors go to stderr
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    if output.status.success() {
+        Ok(format!("{}\n{}", stdout, stderr))
+    } else {
+        let error = if stderr.trim().is_empty() { stdout.trim() } else { stderr.trim() };
+        Err(format!("scrcpy {} failed: {}", flag, error))
+    }
+}
+
+/// Value of a `--flag=value` token in a line, without surrounding quotes
+fn flag_value<'a>(line: &'a str, flag: &str) -> Option<&'a str> {
+    let start = line.find(flag)? + flag.len();
+    let rest = &line[start..];
+    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+    Some(rest[..end].trim_matches('\''))
+}
+
+/// Parse the output of `scrcpy --list-encoders`
+///
+/// ```text
+/// [server] INFO: List of video encoders:
+///     --video-codec=h264 --video-encoder=c2.qti.avc.encoder (hw) [vendor]
+///     --video-codec=h264 --video-encoder=OMX.qcom.video.encoder.avc (hw) [vendor] (alias for c2.qti.avc.encoder)
+/// [server] INFO: List of audio encoders:
+///     --audio-codec=opus --audio-encoder=c2.android.opus.encoder (sw)
+/// ```
+///
+/// scrcpy 2.0 quotes the encoder names and omits the type information.
+pub fn parse_encoder_list(output: &str) -> Vec<EncoderInfo> {
+    let mut encoders = Vec::new();
+
+    for line in output.lines() {
+        let (kind, codec_flag, encoder_flag) = if line.contains("--video-encoder=") {
+            (EncoderKind::Video, "--video-codec=", "--video-encoder=")
+        } else if line.contains("--audio-encoder=") {
+            (EncoderKind::Audio, "--audio-codec=", "--audio-encoder=")
+        } else {
+            continue;
+        };
+
+        let (Some(codec), Some(name)) = (flag_value(line, codec_flag), flag_value(line, encoder_flag)) else {
+            continue;
+        };
+
+        let encoder_type = if line.contains("(hw)") {
+            Some(EncoderType::Hw)
+        } else if line.contains("(sw)") {
+            Some(EncoderType::Sw)
+        } else if line.contains("(hybrid)") {
+            Some(EncoderType::Hybrid)
+        } else {
+            None
+        };
+
+        let alias_for = line.find("(alias for ").map(|pos| {
+            line[pos + "(alias for ".len()..].trim_end().trim_end_matches(')').to_string()
+        });
+
+        encoders.push(EncoderInfo {
+            kind,
+            codec: codec.to_string(),
+            name: name.to_string(),
+            encoder_type,
+            vendor: line.contains("[vendor]"),
+            alias_for,
+        });
+    }
+
+    encoders
+}
+
+/// Parse the output of `scrcpy --list-displays`
+///
+/// ```text
+/// [server] INFO: List of displays:
+///     --display-id=0    (1080x2340)
+///     --display-id=2    (size unknown)
+/// ```
+///
+/// scrcpy 1.x printed `--display=` instead of `--display-id=`.
+pub fn parse_display_list(output: &str) -> Vec<DisplayInfo> {
+    let mut displays = Vec::new();
+
+    for line in output.lines() {
+        let Some(id) = flag_value(line, "--display-id=").or_else(|| flag_value(line, "--display=")) else {
+            continue;
+        };
+        let Ok(id) = id.parse::<u32>() else {
+            continue;
+        };
+
+        let size = match (line.find('('), line.rfind(')')) {
+            (Some(open), Some(close)) if open < close => {
+                Size::parse(&line[open + 1..close])
+            }
+            _ => None,
+        };
+
+        displays.push(DisplayInfo { id, size });
+    }
+
+    displays
+}
+
+/// Parse the output of `scrcpy --list-apps` (scrcpy 3.0+)
+///
+/// ```text
+/// [server] INFO: List of apps:
+///  * Settings                com.android.settings
+///  - Firefox Nightly         org.mozilla.fenix
+/// ```
+///
+/// `*` marks system apps and `-` user apps. Labels are padded and may
+/// contain spaces, so the package is the last column.
+pub fn parse_app_list(output: &str) -> Vec<AppInfo> {
+    let mut apps = Vec::new();
+    let mut in_list = false;
+
+    for line in output.lines() {
+        if line.contains("List of apps:") {
+            in_list = true;
+            continue;
+        }
+        if !in_list {
+            continue;
+        }
+
+        let trimmed = line.trim_start();
+        let (system, rest) = if let Some(rest) = trimmed.strip_prefix("* ") {
+            (true, rest)
+        } else if let Some(rest) = trimmed.strip_prefix("- ") {
+            (false, rest)
+        } else {
+            continue;
+        };
+
+        let rest = rest.trim_end();
+        let Some(split) = rest.rfind(char::is_whitespace) else {
+            continue;
+        };
+        let package = &rest[split + 1..];
+        if !package.contains('.') {
+            continue;
+        }
+
+        apps.push(AppInfo {
+            package: package.to_string(),
+            label: rest[..split].trim().to_string(),
+            system,
+        });
+    }
+
+    apps
+}
+
+/// Parse a Java set such as `[15, 24, 30]`
+fn parse_fps_list(value: &str) -> Vec<u32> {
+    value
+        .trim()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .split(',')
+        .filter_map(|v| v.trim().parse().ok())
+        .collect()
+}
+
+/// Parse the output of `scrcpy --list-cameras` or `scrcpy --list-camera-sizes`
+///
+/// ```text
+/// [server] INFO: List of cameras:
+///     --camera-id=0    (back, 4000x3000, fps=[15, 24, 30])
+///         - 4000x3000
+///         - 1920x1080
+///       High speed capture (--camera-high-speed):
+///         - 1920x1080 (fps=[120, 240])
+/// ```
+pub fn parse_camera_list(output: &str) -> Vec<CameraInfo> {
+    let mut cameras: Vec<CameraInfo> = Vec::new();
+    let mut in_high_speed = false;
+
+    for line in output.lines() {
+        let line = line.trim();
+
+        if let Some(pos) = line.find("--camera-id=") {
+            let rest = &line[pos + "--camera-id=".len()..];
+            let id_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            let id = rest[..id_end].to_string();
+
+            let mut camera = CameraInfo {
+                id,
+                facing: None,
+                sensor_size: None,
+                fps: Vec::new(),
+                sizes: Vec::new(),
+                high_speed_sizes: Vec::new(),
+            };
+
+            // Details: "(back, 4000x3000, fps=[15, 24, 30])"
+            if let (Some(open), Some(close)) = (rest.find('('), rest.rfind(')')) {
+                let details = &rest[open + 1..close];
+                let (main, fps) = match details.split_once("fps=") {
+                    Some((main, fps)) => (main, Some(fps)),
+                    None => (details, None),
+                };
+
+                let mut fields = main.split(',').map(str::trim).filter(|f| !f.is_empty());
+                camera.facing = fields.next().and_then(CameraFacing::from_name);
+                camera.sensor_size = fields.next().and_then(Size::parse);
+                camera.fps = fps.map(parse_fps_list).unwrap_or_default();
+            }
+
+            cameras.push(camera);
+            in_high_speed = false;
+            continue;
+        }
+
+        if line.starts_with("High speed capture") {
+            in_high_speed = true;
+            continue;
+        }
+
+        let Some(entry) = line.strip_prefix("- ") else {
+            continue;
+        };
+        let Some(camera) = cameras.last_mut() else {
+            continue;
+        };
+
+        if in_high_speed {
+            // "1920x1080 (fps=[120, 240])"
+            let (size, fps) = match entry.split_once('(') {
+                Some((size, rest)) => (size, rest.trim_end_matches(')').trim_start_matches("fps=")),
+                None => (entry, ""),
+            };
+            if let Some(size) = Size::parse(size) {
+                camera.high_speed_sizes.push(HighSpeedSize { size, fps: parse_fps_list(fps) });
+            }
+        } else if let Some(size) = Size::parse(entry) {
+            camera.sizes.push(size);
+        }
+    }
+
+    cameras
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST_CAMERAS: &str = "\
+scrcpy 3.3.3 <https://github.com/Genymobile/scrcpy>
+INFO: ADB device found:
+INFO:     -->   (usb)  R58M123ABC                      device  SM_G973F
+/usr/local/share/scrcpy/scrcpy-server: 1 file pushed, 0 skipped.
+[server] INFO: Device: [samsung] samsung SM-G973F (Android 12)
+[server] INFO: List of cameras:
+    --camera-id=0    (back, 4032x3024, fps=[15, 24, 30])
+    --camera-id=1    (front, 3264x2448, fps=[15, 30])
+    --camera-id=2    (back, 4032x3024, fps=[15, 24, 30, 60])
+";
+
+    const LIST_CAMERA_SIZES: &str = "\
+[server] INFO: List of cameras:
+    --camera-id=0    (back, 4032x3024, fps=[15, 24, 30])
+        - 4032x3024
+        - 3840x2160
+        - 1920x1080
+      High speed capture (--camera-high-speed):
+        - 1920x1080 (fps=[120, 240])
+        - 1280x720 (fps=[120, 240, 480])
+    --camera-id=1    (front, 3264x2448, fps=[15, 30])
+        - 3264x2448
+        - 1280x720
+    --camera-id=10    (external, 1280x720, fps=[30])
+";
+
+    #[test]
+    fn test_parse_camera_list() {
+        let cameras = parse_camera_list(LIST_CAMERAS);
+        assert_eq!(cameras.len(), 3);
+        assert_eq!(cameras[0].id, "0");
+        assert_eq!(cameras[0].facing, Some(CameraFacing::Back));
+        assert_eq!(cameras[0].sensor_size, Some(Size { width: 4032, height: 3024 }));
+        assert_eq!(cameras[0].fps, vec![15, 24, 30]);
+        assert_eq!(cameras[1].facing, Some(CameraFacing::Front));
+        assert_eq!(cameras[2].fps, vec![15, 24, 30, 60]);
+        assert!(cameras.iter().all(|c| c.sizes.is_empty()));
+    }
+
+    #[test]
+    fn test_parse_camera_sizes() {
+        let cameras = parse_camera_list(LIST_CAMERA_SIZES);
+        assert_eq!(cameras.len(), 3);
+
+        assert_eq!(cameras[0].sizes.len(), 3);
+        assert_eq!(cameras[0].sizes[2], Size { width: 1920, height: 1080 });
+        assert_eq!(cameras[0].high_speed_sizes.len(), 2);
+        assert_eq!(cameras[0].high_speed_sizes[1].size, Size { width: 1280, height: 720 });
+        assert_eq!(cameras[0].high_speed_sizes[1].fps, vec![120, 240, 480]);
+
+        // High speed section ends with the next camera
+        assert_eq!(cameras[1].sizes.len(), 2);
+        assert!(cameras[1].high_speed_sizes.is_empty());
+
+        assert_eq!(cameras[2].id, "10");
+        assert_eq!(cameras[2].facing, Some(CameraFacing::External));
+    }
+
+    #[test]
+    fn test_parse_camera_list_without_fps() {
+        // Older servers could fail to query the frame rates
+        let output = "[server] INFO: List of cameras:\n    --camera-id=0    (back, 4000x3000)\n";
+        let cameras = parse_camera_list(output);
+        assert_eq!(cameras.len(), 1);
+        assert_eq!(cameras[0].sensor_size, Some(Size { width: 4000, height: 3000 }));
+        assert!(cameras[0].fps.is_empty());
+    }
+
+    const LIST_ENCODERS_V3: &str = "\
+[server] INFO: Device: [Google] google Pixel 7 (Android 14)
+[server] INFO: List of video encoders:
+    --video-codec=h264 --video-encoder=c2.exynos.h264.encoder (hw) [vendor]
+    --video-codec=h264 --video-encoder=c2.android.avc.encoder (sw)
+    --video-codec=h265 --video-encoder=c2.exynos.hevc.encoder (hw) [vendor]
+    --video-codec=h265 --video-encoder=OMX.Exynos.HEVC.Encoder (hw) [vendor] (alias for c2.exynos.hevc.encoder)
+    --video-codec=av1 --video-encoder=c2.android.av1.encoder (sw)
+[server] INFO: List of audio encoders:
+    --audio-codec=opus --audio-encoder=c2.android.opus.encoder (sw)
+    --audio-codec=aac --audio-encoder=c2.android.aac.encoder (sw)
+    --audio-codec=flac --audio-encoder=c2.android.flac.encoder (sw)
+";
+
+    const LIST_ENCODERS_V2_0: &str = "\
+[server] INFO: List of video encoders:
+    --video-codec=h264 --video-encoder='OMX.qcom.video.encoder.avc'
+    --video-codec=h264 --video-encoder='c2.android.avc.encoder'
+[server] INFO: List of audio encoders:
+    --audio-codec=opus --audio-encoder='c2.android.opus.encoder'
+";
+
+    #[test]
+    fn test_parse_encoder_list() {
+        let encoders = parse_encoder_list(LIST_ENCODERS_V3);
+        assert_eq!(encoders.len(), 8);
+
+        assert_eq!(encoders[0].kind, EncoderKind::Video);
+        assert_eq!(encoders[0].codec, "h264");
+        assert_eq!(encoders[0].name, "c2.exynos.h264.encoder");
+        assert_eq!(encoders[0].encoder_type, Some(EncoderType::Hw));
+        assert!(encoders[0].vendor);
+        assert!(encoders[0].alias_for.is_none());
+
+        assert_eq!(encoders[1].encoder_type, Some(EncoderType::Sw));
+        assert!(!encoders[1].vendor);
+
+        assert_eq!(encoders[3].name, "OMX.Exynos.HEVC.Encoder");
+        assert_eq!(encoders[3].alias_for.as_deref(), Some("c2.exynos.hevc.encoder"));
+
+        let audio: Vec<_> = encoders.iter().filter(|e| e.kind == EncoderKind::Audio).collect();
+        assert_eq!(audio.len(), 3);
+        assert_eq!(audio[2].codec, "flac");
+    }
+
+    #[test]
+    fn test_parse_encoder_list_v2_0() {
+        let encoders = parse_encoder_list(LIST_ENCODERS_V2_0);
+        assert_eq!(encoders.len(), 3);
+        assert_eq!(encoders[0].name, "OMX.qcom.video.encoder.avc");
+        assert_eq!(encoders[0].encoder_type, None);
+        assert_eq!(encoders[2].kind, EncoderKind::Audio);
+        assert_eq!(encoders[2].name, "c2.android.opus.encoder");
+    }
+
+    #[test]
+    fn test_parse_display_list() {
+        let output = "\
+[server] INFO: List of displays:
+    --display-id=0    (1080x2340)
+    --display-id=4    (1920x1080)
+    --display-id=7    (size unknown)
+";
+        let displays = parse_display_list(output);
+        assert_eq!(displays.len(), 3);
+        assert_eq!(displays[0], DisplayInfo { id: 0, size: Some(Size { width: 1080, height: 2340 }) });
+        assert_eq!(displays[1].id, 4);
+        assert_eq!(displays[2].size, None);
+    }
+
+    #[test]
+    fn test_parse_display_list_v1() {
+        let output = "[server] INFO: List of displays:\n    --display=0    (1440x3120)\n";
+        let displays = parse_display_list(output);
+        assert_eq!(displays.len(), 1);
+        assert_eq!(displays[0].size, Some(Size { width: 1440, height: 3120 }));
+    }
+
+    #[test]
+    fn test_parse_app_list() {
+        let output = "\
+INFO: ADB device found:
+INFO:     -->   (usb)  R58M123ABC                      device  SM_G973F
+[server] INFO: List of apps:
+ * Camera                  com.sec.android.app.camera
+ * Settings                com.android.settings
+ - Firefox Nightly         org.mozilla.fenix
+ - My App (debug)          com.example.app.debug
+";
+        let apps = parse_app_list(output);
+        assert_eq!(apps.len(), 4);
+        assert_eq!(apps[0], AppInfo {
+            package: "com.sec.android.app.camera".to_string(),
+            label: "Camera".to_string(),
+            system: true,
+        });
+        assert_eq!(apps[2].label, "Firefox Nightly");
+        assert!(!apps[2].system);
+        assert_eq!(apps[3].label, "My App (debug)");
+        assert_eq!(apps[3].package, "com.example.app.debug");
+    }
+
+    #[test]
+    fn test_parse_app_list_ignores_logs_before_header() {
+        // Client log lines also start with "-" in some releases
+        let output = " - not.an.app\n[server] INFO: List of apps:\n";
+        assert!(parse_app_list(output).is_empty());
+    }
+
+    #[test]
+    fn test_parse_no_cameras() {
+        let output = "[server] INFO: List of cameras:\n    (none)\n";
+        assert!(parse_camera_list(output).is_empty());
+        assert!(parse_camera_list("").is_empty());
+    }
+}
@@ -0,0 +1,326 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::{Arc, Mutex};
+use chrono::Utc;
+use serde::{Serialize, Deserialize};
+use super::ScrcpyProcess;
+
+/// Number of output lines kept per session
+pub const MAX_LOG_LINES: usize = 1000;
+
+/// Output stream a log line was read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogStream {
+    Stdout,
+    Stderr,
+}
+
+/// A single line of scrcpy output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogLine {
+    pub stream: LogStream,
+    pub text: String,
+    /// RFC 3339 time the line was read
+    pub timestamp: String,
+}
+
+/// Known fatal errors reported by scrcpy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SessionFailure {
+    /// adb does not see the device (unplugged, unauthorized or wrong serial)
+    NoAdbDevice,
+    /// The requested video or audio encoder does not exist on the device
+    EncoderNotFound,
+    /// The client could not connect to the scrcpy server on the device
+    ServerConnectionFailed,
+    /// The device went away while the session was running
+    DeviceDisconnected,
+}
+
+impl SessionFailure {
+    /// Recognize a fatal error in a line of scrcpy output
+    pub fn classify(line: &str) -> Option<Self> {
+        if line.contains("Could not find any ADB device") {
+            Some(SessionFailure::NoAdbDevice)
+        } else if line.contains("Encoder") && line.contains("not found") {
+            // "Encoder not found" or "Encoder '<name>' for <codec> not found"
+            Some(SessionFailure::EncoderNotFound)
+        } else if line.contains("Server connection failed") {
+            Some(SessionFailure::ServerConnectionFailed)
+        } else if line.contains("Device disconnected") {
+            Some(SessionFailure::DeviceDisconnected)
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether a line shows that scrcpy finished starting up.
+///
+/// The renderer and texture lines are printed once the window is shown;
+/// recordings without playback only report the recording start.
+pub fn is_ready_line(line: &str) -> bool {
+    line.contains("Renderer:") || line.contains("Texture:") || line.contains("Recording started to")
+}
+
+/// Logs of a session, as returned to the UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionLogs {
+    pub lines: Vec<LogLine>,
+    /// Lines discarded because the buffer was full
+    pub dropped: usize,
+    /// First fatal error recognized in the output
+    pub failure: Option<SessionFailure>,
+}
+
+#[derive(Debug, Default)]
+struct LogBuffer {
+    lines: VecDeque<LogLine>,
+    dropped: usize,
+    failure: Option<SessionFailure>,
+    ready: bool,
+    /// Output streams still being read
+    open_streams: usize,
+}
+
+/// Bounded, shareable buffer of the output of a session
+#[derive(Debug, Clone, Default)]
+pub struct SessionLog {
+    inner: Arc<Mutex<LogBuffer>>,
+}
+
+impl SessionLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a line, evicting the oldest one when the buffer is full
+    pub fn push(&self, stream: LogStream, text: &str) -> (LogLine, Option<SessionFailure>) {
+        let line = LogLine {
+            stream,
+            text: text.to_string(),
+            timestamp: Utc::now().to_rfc3339(),
+        };
+        let failure = SessionFailure::classify(text);
+
+        if let Ok(mut buffer) = self.inner.lock() {
+            if buffer.lines.len() == MAX_LOG_LINES {
+                buffer.lines.pop_front();
+                buffer.dropped += 1;
+            }
+            buffer.lines.push_back(line.clone());
+            if buffer.failure.is_none() {
+                buffer.failure = failure;
+            }
+            if is_ready_line(text) {
+                buffer.ready = true;
+            }
+        }
+
+        (line, failure)
+    }
+
+    /// Copy of the buffered lines and the detected failure
+    pub fn snapshot(&self) -> SessionLogs {
+        match self.inner.lock() {
+            Ok(buffer) => SessionLogs {
+                lines: buffer.lines.iter().cloned().collect(),
+                dropped: buffer.dropped,
+                failure: buffer.failure,
+            },
+            Err(_) => SessionLogs { lines: Vec::new(), dropped: 0, failure: None },
+        }
+    }
+
+    /// First fatal error recognized in the output, if any
+    pub fn failure(&self) -> Option<SessionFailure> {
+        self.inner.lock().ok().and_then(|buffer| buffer.failure)
+    }
+
+    /// Whether scrcpy reported that it finished starting up
+    pub fn is_ready(&self) -> bool {
+        self.inner.lock().map(|buffer| buffer.ready).unwrap_or(false)
+    }
+
+    /// Whether all captured output streams reached their end
+    pub fn is_closed(&self) -> bool {
+        self.inner.lock().map(|buffer| buffer.open_streams == 0).unwrap_or(true)
+    }
+
+    /// Text of the last `count` lines printed on a stream
+    pub fn tail(&self, stream: LogStream, count: usize) -> Vec<String> {
+        let Ok(buffer) = self.inner.lock() else {
+            return Vec::new();
+        };
+        let mut lines: Vec<String> = buffer.lines.iter().rev()
+            .filter(|l| l.stream == stream)
+            .take(count)
+            .map(|l| l.text.clone())
+            .collect();
+        lines.reverse();
+        lines
+    }
+
+    fn set_stream_open(&self, open: bool) {
+        if let Ok(mut buffer) = self.inner.lock() {
+            if open {
+                buffer.open_streams += 1;
+            } else {
+                buffer.open_streams = buffer.open_streams.saturating_sub(1);
+            }
+        }
+    }
+
+    /// Most relevant line to show when the session failed: the last error, or the last line
+    pub fn last_error(&self) -> Option<String> {
+        let buffer = self.inner.lock().ok()?;
+        buffer.lines.iter().rev()
+            .find(|l| l.text.contains("ERROR"))
+            .or_else(|| buffer.lines.back())
+            .map(|l| l.text.clone())
+    }
+}
+
+/// Read the piped stdout and stderr of a child into a log on background threads.
+///
+/// `on_line` is called for every line, along with the failure it indicates.
+/// The threads end when scrcpy closes its output.
+pub fn capture_output<F>(child: &mut dyn ScrcpyProcess, log: &SessionLog, on_line: F)
+where
+    F: Fn(&LogLine, Option<SessionFailure>) + Send + Sync + 'static,
+{
+    let on_line = Arc::new(on_line);
+
+    if let Some(stdout) = child.take_stdout() {
+        spawn_reader(stdout, LogStream::Stdout, log.clone(), on_line.clone());
+    }
+    if let Some(stderr) = child.take_stderr() {
+        spawn_reader(stderr, LogStream::Stderr, log.clone(), on_line);
+    }
+}
+
+fn spawn_reader<R, F>(source: R, stream: LogStream, log: SessionLog, on_line: Arc<F>)
+where
+    R: Read + Send + 'static,
+    F: Fn(&LogLine, Option<SessionFailure>) + Send + Sync + 'static,
+{
+    log.set_stream_open(true);
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(source);
+        let mut buf = Vec::new();
+
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) | Err(_) => {
+                    log.set_stream_open(false);
+                    break;
+                }
+                Ok(_) => {
+                    // scrcpy output is not guaranteed to be UTF-8 (device names, Windows code pages)
+                    let text = String::from_utf8_lossy(&buf);
+                    let text = text.trim_end_matches(['\r', '\n']);
+                    let (line, failure) = log.push(stream, text);
+                    on_line(&line, failure);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::{Command, Stdio};
+    use std::time::Duration;
+
+    #[test]
+    fn test_classify_failures() {
+        assert_eq!(
+            SessionFailure::classify("ERROR: Could not find any ADB device"),
+            Some(SessionFailure::NoAdbDevice)
+        );
+        assert_eq!(
+            SessionFailure::classify("[server] ERROR: Encoder 'c2.foo.encoder' for h264 not found"),
+            Some(SessionFailure::EncoderNotFound)
+        );
+        assert_eq!(
+            SessionFailure::classify("ERROR: Server connection failed"),
+            Some(SessionFailure::ServerConnectionFailed)
+        );
+        assert_eq!(
+            SessionFailure::classify("WARN: Device disconnected"),
+            Some(SessionFailure::DeviceDisconnected)
+        );
+        assert_eq!(SessionFailure::classify("INFO: Renderer: opengl"), None);
+    }
+
+    #[test]
+    fn test_ready_lines() {
+        assert!(is_ready_line("INFO: Renderer: opengl"));
+        assert!(is_ready_line("INFO: Texture: 1080x2400"));
+        assert!(is_ready_line("INFO: Recording started to mp4 file: /tmp/a.mp4"));
+        assert!(!is_ready_line("INFO: Device: [Google] google Pixel 7 (Android 14)"));
+
+        let log = SessionLog::new();
+        log.push(LogStream::Stdout, "scrcpy 3.3.3 <https://github.com/Genymobile/scrcpy>");
+        assert!(!log.is_ready());
+        log.push(LogStream::Stdout, "INFO: Renderer: opengl");
+        assert!(log.is_ready());
+    }
+
+    #[test]
+    fn test_log_is_bounded() {
+        let log = SessionLog::new();
+        for i in 0..MAX_LOG_LINES + 5 {
+            log.push(LogStream::Stdout, &format!("line {}", i));
+        }
+
+        let logs = log.snapshot();
+        assert_eq!(logs.lines.len(), MAX_LOG_LINES);
+        assert_eq!(logs.dropped, 5);
+        assert_eq!(logs.lines[0].text, "line 5");
+    }
+
+    #[test]
+    fn test_first_failure_is_kept() {
+        let log = SessionLog::new();
+        log.push(LogStream::Stderr, "ERROR: Server connection failed");
+        log.push(LogStream::Stderr, "WARN: Device disconnected");
+        assert_eq!(log.failure(), Some(SessionFailure::ServerConnectionFailed));
+        assert_eq!(log.last_error().as_deref(), Some("ERROR: Server connection failed"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_capture_output() {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg("echo 'INFO: scrcpy 3.3.3'; echo 'ERROR: Could not find any ADB device' >&2")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let log = SessionLog::new();
+        capture_output(&mut child, &log, |_, _| {});
+        child.wait().unwrap();
+
+        // The reader threads finish shortly after the pipes close
+        for _ in 0..100 {
+            if log.is_closed() {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(log.is_closed());
+        assert_eq!(log.tail(LogStream::Stderr, 5), vec!["ERROR: Could not find any ADB device".to_string()]);
+
+        let logs = log.snapshot();
+        assert_eq!(logs.lines.len(), 2);
+        assert!(logs.lines.iter().any(|l| l.stream == LogStream::Stdout && l.text == "INFO: scrcpy 3.3.3"));
+        assert!(logs.lines.iter().any(|l| l.stream == LogStream::Stderr));
+        assert_eq!(logs.failure, Some(SessionFailure::NoAdbDevice));
+    }
+}
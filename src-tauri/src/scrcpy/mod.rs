@@ -1,33 +1,476 @@
-use std::process::{Command, Child, Stdio};
-use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::{Command, ExitStatus};
+use std::time::{Duration, Instant};
+use std::collections::{HashMap, VecDeque};
 use std::sync::{Arc, Mutex};
 use serde::{Serialize, Deserialize};
 use crate::utils;
 
+mod args;
+mod capabilities;
+#[cfg(test)]
+pub mod fake;
+mod launcher;
+mod list;
+mod logs;
+mod options;
+mod process;
+mod resources;
+mod restart;
+mod version;
+mod window;
+
+pub use args::*;
+pub use capabilities::*;
+pub use launcher::*;
+pub use list::*;
+pub use logs::*;
+pub use options::*;
+pub use process::*;
+pub use resources::*;
+pub use restart::*;
+pub use version::*;
+pub use window::*;
+
+/// Default time a session gets to exit after a polite termination request
+pub const DEFAULT_SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// Shortest time a recording session gets to exit, since scrcpy finalizes
+/// the file on its way out and a killed recording may not play
+pub const RECORDING_STOP_GRACE: Duration = Duration::from_secs(10);
+
+/// Grace period for stopping a session, extended for recordings
+pub fn stop_grace(info: &ProcessInfo, grace: Duration) -> Duration {
+    match info.recording_id {
+        Some(_) => grace.max(RECORDING_STOP_GRACE),
+        None => grace,
+    }
+}
+
+/// Longest time a session may take to report that it started
+pub const STARTUP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Number of ended sessions whose logs and exits are kept for diagnosis
+const MAX_FINISHED_SESSIONS: usize = 20;
+
+/// How a scrcpy process ended when it was stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShutdownMethod {
+    /// The process had already exited before it was asked to stop
+    AlreadyExited,
+    /// The process exited on its own after the termination request
+    Graceful,
+    /// The process outlived the grace period and was killed
+    Killed,
+}
+
+/// Outcome of stopping a scrcpy process
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShutdownReport {
+    pub method: ShutdownMethod,
+    pub exit_code: Option<i32>,
+    /// Terminating signal (Unix only)
+    pub signal: Option<i32>,
+}
+
+impl ShutdownReport {
+    /// `status` is None when it cannot be read (adopted processes)
+    fn new(method: ShutdownMethod, status: Option<ExitStatus>) -> Self {
+        Self {
+            method,
+            exit_code: status.and_then(|s| s.code()),
+            signal: status.and_then(exit_signal),
+        }
+    }
+}
+
+/// Signal that terminated a process (Unix only)
+pub fn exit_signal(status: ExitStatus) -> Option<i32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        status.signal()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = status;
+        None
+    }
+}
+
+/// Why a session ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExitReason {
+    /// scrcpy exited normally, usually because its window was closed
+    Closed,
+    /// The session was stopped from the app
+    Stopped,
+    /// scrcpy exited with an error or was killed by a signal
+    Failed,
+}
+
+/// How and when a session ended
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionExit {
+    pub code: Option<i32>,
+    /// Terminating signal (Unix only)
+    pub signal: Option<i32>,
+    /// Time the session ran, in seconds
+    pub duration_secs: f64,
+    pub reason: ExitReason,
+    /// Fatal error recognized in the output, if any
+    pub failure: Option<SessionFailure>,
+}
+
+impl SessionExit {
+    /// Exit of a session that ended on its own (`status` is None if it could not be read)
+    fn from_status(status: Option<ExitStatus>, info: &ProcessInfo) -> Self {
+        let failure = info.log.failure();
+        // Adopted processes never have a status, so only their output could tell a failure
+        let success = status.map(|s| s.success()).unwrap_or(info.process.is_adopted());
+        
+        Self {
+            code: status.and_then(|s| s.code()),
+            signal: status.and_then(exit_signal),
+            duration_secs: session_duration(info),
+            reason: if success && failure.is_none() { ExitReason::Closed } else { ExitReason::Failed },
+            failure,
+        }
+    }
+
+    /// Exit of a session stopped from the app
+    pub fn stopped(report: &ShutdownReport, info: &ProcessInfo) -> Self {
+        Self {
+            code: report.exit_code,
+            signal: report.signal,
+            duration_secs: session_duration(info),
+            reason: ExitReason::Stopped,
+            failure: info.log.failure(),
+        }
+    }
+}
+
+fn session_duration(info: &ProcessInfo) -> f64 {
+    info.started_at.elapsed().map(|d| d.as_secs_f64()).unwrap_or(0.0)
+}
+
+/// Result of checking on a tracked session
+#[derive(Debug)]
+pub enum SessionPoll {
+    Running,
+    /// The process exited and is no longer tracked
+    Exited(Box<ProcessInfo>, SessionExit),
+    /// The session was removed by someone else (stopped or cleaned up)
+    NotTracked,
+}
+
+/// What to do when a device already has a session mirroring the same target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SessionPolicy {
+    /// Refuse to start a second session
+    #[default]
+    Reject,
+    /// Return the existing session instead of starting a new one
+    Reuse,
+    /// Stop the existing session, then start the new one
+    Replace,
+}
+
+/// A session waiting to be relaunched by its restart policy
+struct PendingRestart {
+    device_id: String,
+    target: Option<SessionTarget>,
+}
+
+/// Result of claiming the target of a device for a new session
+pub enum TargetClaim {
+    /// The target is free; it stays reserved until the guard is dropped
+    Claimed(TargetReservation),
+    /// A session already mirrors the target, running or waiting to be relaunched
+    Taken(String),
+    /// Another session for the target is still starting up
+    Starting,
+}
+
+/// Keeps the target of a device reserved while its session starts up
+pub struct TargetReservation {
+    starting: Arc<Mutex<Vec<(String, SessionTarget)>>>,
+    device_id: String,
+    target: SessionTarget,
+}
+
+impl Drop for TargetReservation {
+    fn drop(&mut self) {
+        let mut starting = self.starting.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(index) = starting.iter().position(|(device_id, target)| *device_id == self.device_id && *target == self.target) {
+            starting.remove(index);
+        }
+    }
+}
+
+/// A session that is no longer tracked, kept for diagnosis
+struct FinishedSession {
+    session_id: String,
+    log: SessionLog,
+    exit: Option<SessionExit>,
+    /// Recording library entry the session was writing, if any
+    recording_id: Option<String>,
+}
+
 /// Global state to track active scrcpy processes
 #[derive(Clone)]
 pub struct ScrcpyState {
     pub processes: Arc<Mutex<HashMap<String, ProcessInfo>>>,
+    /// Sessions that are no longer tracked, oldest first
+    finished: Arc<Mutex<VecDeque<FinishedSession>>>,
+    /// Sessions waiting to be relaunched, by session id
+    restarting: Arc<Mutex<HashMap<String, PendingRestart>>>,
+    /// Device and target of sessions still starting up
+    starting: Arc<Mutex<Vec<(String, SessionTarget)>>>,
+    /// Starts the scrcpy processes of sessions
+    launcher: Arc<dyn Launcher>,
+    /// adb used in place of the app's own, if set
+    adb_path: Option<PathBuf>,
 }
 
 #[derive(Debug)]
 pub struct ProcessInfo {
-    pub child: Child,
+    pub process: SessionProcess,
     pub device_id: String,
     pub started_at: std::time::SystemTime,
+    /// What the session mirrors, None for a new virtual display
+    pub target: Option<SessionTarget>,
+    /// Recording library entry written by this session, if any
+    pub recording_id: Option<String>,
+    /// Output captured from scrcpy
+    pub log: SessionLog,
+    /// CPU and memory samples of the process
+    pub resources: ResourceMonitor,
 }
 
 impl ScrcpyState {
     pub fn new() -> Self {
+        Self::with_launcher(Arc::new(SystemLauncher))
+    }
+
+    /// State whose sessions are started by `launcher` instead of the scrcpy binary
+    pub fn with_launcher(launcher: Arc<dyn Launcher>) -> Self {
         Self {
             processes: Arc::new(Mutex::new(HashMap::new())),
+            finished: Arc::new(Mutex::new(VecDeque::new())),
+            restarting: Arc::new(Mutex::new(HashMap::new())),
+            starting: Arc::new(Mutex::new(Vec::new())),
+            launcher,
+            adb_path: None,
+        }
+    }
+
+    /// State whose sessions reach devices through the adb at `adb_path`
+    #[cfg(test)]
+    pub fn with_adb_path(mut self, adb_path: PathBuf) -> Self {
+        self.adb_path = Some(adb_path);
+        self
+    }
+
+    pub fn launcher(&self) -> &dyn Launcher {
+        self.launcher.as_ref()
+    }
+
+    /// The adb sessions reach their devices through
+    pub fn adb_path<R: tauri::Runtime>(&self, app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+        match &self.adb_path {
+            Some(adb_path) => Ok(adb_path.clone()),
+            None => utils::get_adb_path(app),
+        }
+    }
+
+    /// Mark a session as waiting to be relaunched
+    pub fn begin_restart(&self, session_id: &str, device_id: &str, target: Option<SessionTarget>) {
+        if let Ok(mut restarting) = self.restarting.lock() {
+            restarting.insert(session_id.to_string(), PendingRestart {
+                device_id: device_id.to_string(),
+                target,
+            });
+        }
+    }
+
+    /// Check if a session is waiting to be relaunched
+    pub fn is_restarting(&self, session_id: &str) -> bool {
+        self.restarting.lock().map(|r| r.contains_key(session_id)).unwrap_or(false)
+    }
+
+    /// Clear the restart mark of a session.
+    ///
+    /// Returns false if the restart was already cancelled (or never began).
+    pub fn end_restart(&self, session_id: &str) -> bool {
+        self.restarting.lock().map(|mut r| r.remove(session_id).is_some()).unwrap_or(false)
+    }
+
+    /// Sessions of a device, running or waiting to be relaunched
+    pub fn sessions_for_device(&self, device_id: &str) -> Result<Vec<(String, Option<SessionTarget>)>, String> {
+        let mut sessions: Vec<(String, Option<SessionTarget>)> = {
+            let processes = self.processes.lock()
+                .map_err(|e| format!("Failed to lock processes: {}", e))?;
+            processes.iter()
+                .filter(|(_, info)| info.device_id == device_id)
+                .map(|(id, info)| (id.clone(), info.target.clone()))
+                .collect()
+        };
+        
+        let restarting = self.restarting.lock()
+            .map_err(|e| format!("Failed to lock restarting sessions: {}", e))?;
+        sessions.extend(restarting.iter()
+            .filter(|(_, pending)| pending.device_id == device_id)
+            .map(|(id, pending)| (id.clone(), pending.target.clone())));
+        
+        Ok(sessions)
+    }
+
+    /// Session of a device that mirrors the given target, if any
+    pub fn find_session(&self, device_id: &str, target: &SessionTarget) -> Result<Option<String>, String> {
+        Ok(self.sessions_for_device(device_id)?
+            .into_iter()
+            .find(|(_, t)| t.as_ref() == Some(target))
+            .map(|(id, _)| id))
+    }
+
+    /// Reserve the target of a device for a session about to start.
+    ///
+    /// Checking for a conflicting session and reserving happen under one
+    /// lock, so two sessions started at the same time cannot both get it.
+    pub fn claim_target(&self, device_id: &str, target: &SessionTarget) -> Result<TargetClaim, String> {
+        let mut starting = self.starting.lock()
+            .map_err(|e| format!("Failed to lock starting sessions: {}", e))?;
+        
+        if starting.iter().any(|(d, t)| d == device_id && t == target) {
+            return Ok(TargetClaim::Starting);
+        }
+        if let Some(existing) = self.find_session(device_id, target)? {
+            return Ok(TargetClaim::Taken(existing));
+        }
+        
+        starting.push((device_id.to_string(), target.clone()));
+        Ok(TargetClaim::Claimed(TargetReservation {
+            starting: self.starting.clone(),
+            device_id: device_id.to_string(),
+            target: target.clone(),
+        }))
+    }
+
+    /// Keep the log and exit of a session that is no longer tracked.
+    ///
+    /// A known exit is never replaced by an unknown one.
+    pub fn record_exit(&self, session_id: &str, log: &SessionLog, exit: Option<SessionExit>) {
+        self.record_finished(session_id, log, exit, None);
+    }
+
+    /// Keep the log, exit and recording of a process that is no longer tracked
+    fn record_process_exit(&self, session_id: &str, info: &ProcessInfo, exit: Option<SessionExit>) {
+        self.record_finished(session_id, &info.log, exit, info.recording_id.clone());
+    }
+
+    fn record_finished(&self, session_id: &str, log: &SessionLog, exit: Option<SessionExit>, recording_id: Option<String>) {
+        if let Ok(mut finished) = self.finished.lock() {
+            let previous = finished.iter()
+                .position(|f| f.session_id == session_id)
+                .and_then(|index| finished.remove(index));
+            let (previous_exit, previous_recording) = match previous {
+                Some(f) => (f.exit, f.recording_id),
+                None => (None, None),
+            };
+            
+            if finished.len() == MAX_FINISHED_SESSIONS {
+                finished.pop_front();
+            }
+            finished.push_back(FinishedSession {
+                session_id: session_id.to_string(),
+                log: log.clone(),
+                exit: exit.or(previous_exit),
+                recording_id: recording_id.or(previous_recording),
+            });
+        }
+    }
+
+    /// How a recently ended session exited, if known
+    pub fn session_exit(&self, session_id: &str) -> Option<SessionExit> {
+        let finished = self.finished.lock().ok()?;
+        finished.iter()
+            .find(|f| f.session_id == session_id)
+            .and_then(|f| f.exit.clone())
+    }
+
+    /// Log of a recently ended session
+    pub fn finished_log(&self, session_id: &str) -> Option<SessionLog> {
+        let finished = self.finished.lock().ok()?;
+        finished.iter()
+            .find(|f| f.session_id == session_id)
+            .map(|f| f.log.clone())
+    }
+
+    /// Recording a recently ended session was writing, if any
+    pub fn finished_recording(&self, session_id: &str) -> Option<String> {
+        let finished = self.finished.lock().ok()?;
+        finished.iter()
+            .find(|f| f.session_id == session_id)
+            .and_then(|f| f.recording_id.clone())
+    }
+
+    /// Check whether a session has exited, and stop tracking it if it has.
+    ///
+    /// If `restart` says the exit should be relaunched, the session is marked
+    /// as restarting before it stops being tracked, so stopping it from the
+    /// app in between cancels the restart instead of missing the session.
+    pub fn poll_session(&self, session_id: &str, restart: impl FnOnce(&SessionExit) -> bool) -> Result<SessionPoll, String> {
+        let mut processes = self.processes.lock()
+            .map_err(|e| format!("Failed to lock processes: {}", e))?;
+        
+        let status = match processes.get_mut(session_id) {
+            Some(info) => match info.process.try_wait() {
+                Ok(ProcessState::Running) => return Ok(SessionPoll::Running),
+                Ok(ProcessState::Exited(status)) => status,
+                Err(_) => None,
+            },
+            None => return Ok(SessionPoll::NotTracked),
+        };
+        
+        match processes.remove(session_id) {
+            Some(info) => {
+                let exit = SessionExit::from_status(status, &info);
+                if restart(&exit) {
+                    self.begin_restart(session_id, &info.device_id, info.target.clone());
+                }
+                self.record_process_exit(session_id, &info, Some(exit.clone()));
+                Ok(SessionPoll::Exited(Box::new(info), exit))
+            }
+            None => Ok(SessionPoll::NotTracked),
+        }
+    }
+
+    /// Logs of a running session, or of a recently ended one
+    pub fn session_logs(&self, session_id: &str) -> Result<Option<SessionLogs>, String> {
+        {
+            let processes = self.processes.lock()
+                .map_err(|e| format!("Failed to lock processes: {}", e))?;
+            if let Some(info) = processes.get(session_id) {
+                return Ok(Some(info.log.snapshot()));
+            }
         }
+        
+        let finished = self.finished.lock()
+            .map_err(|e| format!("Failed to lock finished sessions: {}", e))?;
+        Ok(finished.iter().find(|f| f.session_id == session_id).map(|f| f.log.snapshot()))
     }
 
     /// Add a process to tracking
     pub fn add_process(&self, session_id: String, process_info: ProcessInfo) -> Result<(), String> {
         let mut processes = self.processes.lock()
             .map_err(|e| format!("Failed to lock processes: {}", e))?;
+        
+        // A relaunched session starts over without the exit of its previous process
+        if let Ok(mut finished) = self.finished.lock() {
+            finished.retain(|f| f.session_id != session_id);
+        }
+        
         processes.insert(session_id, process_info);
         Ok(())
     }
@@ -36,7 +479,11 @@ impl ScrcpyState {
     pub fn remove_process(&self, session_id: &str) -> Result<Option<ProcessInfo>, String> {
         let mut processes = self.processes.lock()
             .map_err(|e| format!("Failed to lock processes: {}", e))?;
-        Ok(processes.remove(session_id))
+        let info = processes.remove(session_id);
+        if let Some(ref info) = info {
+            self.record_process_exit(session_id, info, None);
+        }
+        Ok(info)
     }
 
     /// Check if a session is running
@@ -68,71 +515,134 @@ impl ScrcpyState {
         let mut processes = self.processes.lock()
             .map_err(|e| format!("Failed to lock processes: {}", e))?;
         
-        processes.retain(|_, info| {
+        processes.retain(|session_id, info| {
             // Check if process is still running
-            match info.child.try_wait() {
-                Ok(Some(_)) => false, // Process finished, remove it
-                Ok(None) => true,     // Still running, keep it
-                Err(_) => false,      // Error checking, assume dead
-            }
+            let status = match info.process.try_wait() {
+                Ok(ProcessState::Exited(status)) => status, // Process finished, remove it
+                Ok(ProcessState::Running) => return true,   // Still running, keep it
+                Err(_) => None,                             // Error checking, assume dead
+            };
+            self.record_process_exit(session_id, info, Some(SessionExit::from_status(status, info)));
+            false
         });
         
         Ok(())
     }
 
-    /// Stop all processes (for cleanup on app exit)
-    pub fn stop_all(&self) -> Result<(), String> {
-        let mut processes = self.processes.lock()
-            .map_err(|e| format!("Failed to lock processes: {}", e))?;
+    /// Stop all processes (for cleanup on app exit).
+    ///
+    /// Every session is asked to exit first, then they share a single grace
+    /// period before the remaining ones are killed. Recording sessions get at
+    /// least `RECORDING_STOP_GRACE`.
+    pub fn stop_all(&self, grace: Duration) -> Result<Vec<(String, ShutdownReport)>, String> {
+        let sessions: Vec<(String, ProcessInfo)> = {
+            let mut processes = self.processes.lock()
+                .map_err(|e| format!("Failed to lock processes: {}", e))?;
+            processes.drain().collect()
+        };
+        
+        if let Ok(mut restarting) = self.restarting.lock() {
+            restarting.clear();
+        }
+        
+        println!("Stopping {} scrcpy process(es)...", sessions.len());
         
-        println!("Stopping {} scrcpy process(es)...", processes.len());
+        let mut reports = Vec::new();
+        let mut pending = Vec::new();
         
-        for (session_id, mut info) in processes.drain() {
-            match info.child.kill() {
-                Ok(_) => println!("Stopped session: {}", session_id),
-                Err(e) => eprintln!("Failed to stop session {}: {}", session_id, e),
+        for (session_id, mut info) in sessions {
+            match info.process.try_wait() {
+                Ok(ProcessState::Exited(status)) => {
+                    self.record_process_exit(&session_id, &info, Some(SessionExit::from_status(status, &info)));
+                    reports.push((session_id, ShutdownReport::new(ShutdownMethod::AlreadyExited, status)));
+                }
+                _ => {
+                    if let Err(e) = info.process.request_exit() {
+                        eprintln!("Failed to ask session {} to exit: {}", session_id, e);
+                    }
+                    pending.push((session_id, info));
+                }
             }
         }
         
-        Ok(())
+        let asked_at = Instant::now();
+        for (session_id, mut info) in pending {
+            let deadline = asked_at + stop_grace(&info, grace);
+            match wait_or_kill(info.process.as_mut(), deadline) {
+                Ok(report) => {
+                    println!("Stopped session: {} ({:?})", session_id, report.method);
+                    self.record_process_exit(&session_id, &info, Some(SessionExit::stopped(&report, &info)));
+                    reports.push((session_id, report));
+                }
+                Err(e) => {
+                    eprintln!("Failed to stop session {}: {}", session_id, e);
+                    self.record_process_exit(&session_id, &info, None);
+                }
+            }
+        }
+        
+        Ok(reports)
     }
 
-    /// Get count of active processes
-    pub fn active_count(&self) -> usize {
-        self.processes.lock().map(|p| p.len()).unwrap_or(0)
+    /// Device id and process id of every session mirroring a device's main display
+    pub fn main_display_sessions(&self) -> Result<Vec<(String, u32)>, String> {
+        let processes = self.processes.lock()
+            .map_err(|e| format!("Failed to lock processes: {}", e))?;
+        Ok(processes.values()
+            .filter(|info| info.target == Some(SessionTarget::Display(0)))
+            .map(|info| (info.device_id.clone(), info.process.id()))
+            .collect())
     }
-}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ScrcpyOptions {
-    pub max_size: Option<u32>,
-    pub bit_rate: Option<u32>,
-    pub max_fps: Option<u32>,
-    pub always_on_top: bool,
-    pub stay_awake: bool,
-    pub turn_screen_off: bool,
-}
+    /// Sample the CPU and memory use of every running session.
+    ///
+    /// Returns the session id, device id and usage of each session. The
+    /// processes are read without holding the lock, since reading them can
+    /// mean spawning `ps` per session on BSDs other than macOS.
+    pub fn sample_resources(&self) -> Result<Vec<(String, String, SessionResources)>, String> {
+        let pids: Vec<(String, u32)> = {
+            let processes = self.processes.lock()
+                .map_err(|e| format!("Failed to lock processes: {}", e))?;
+            processes.iter().map(|(id, info)| (id.clone(), info.process.id())).collect()
+        };
+        
+        let usages: Vec<(String, Option<ProcessUsage>)> = pids.into_iter()
+            .map(|(id, pid)| (id, read_usage(pid)))
+            .collect();
+        
+        let mut processes = self.processes.lock()
+            .map_err(|e| format!("Failed to lock processes: {}", e))?;
+        let now = Instant::now();
+        
+        Ok(usages.into_iter()
+            .filter_map(|(id, usage)| {
+                // Skip sessions that ended while sampling
+                let info = processes.get_mut(&id)?;
+                let resources = info.resources.record(usage, now, session_duration(info));
+                Some((id, info.device_id.clone(), resources))
+            })
+            .collect())
+    }
 
-impl Default for ScrcpyOptions {
-    fn default() -> Self {
-        Self {
-            max_size: Some(1920),
-            bit_rate: Some(8000000), // 8Mbps
-            max_fps: Some(60),
-            always_on_top: false,
-            stay_awake: true,
-            turn_screen_off: false,
-        }
+    /// Latest resource sample of a running session
+    pub fn session_resources(&self, session_id: &str) -> Result<Option<SessionResources>, String> {
+        let processes = self.processes.lock()
+            .map_err(|e| format!("Failed to lock processes: {}", e))?;
+        Ok(processes.get(session_id).and_then(|info| info.resources.latest()))
+    }
+
+    /// Get count of active processes
+    pub fn active_count(&self) -> usize {
+        self.processes.lock().map(|p| p.len()).unwrap_or(0)
     }
 }
 
-/// Build the scrcpy command without executing it
-pub fn build_scrcpy_command(
+/// Build a scrcpy command targeting a device, without any mirroring options
+pub fn base_scrcpy_command(
     scrcpy_path: &std::path::Path,
     scrcpy_dir: &std::path::Path,
     adb_dir: Option<&std::path::Path>,
     device_id: Option<&str>,
-    options: &ScrcpyOptions,
 ) -> Command {
     let mut cmd = Command::new(scrcpy_path);
     
@@ -151,8 +661,29 @@ pub fn build_scrcpy_command(
         cmd.arg("-s").arg(id);
     }
     
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(process::CREATION_FLAGS);
+    }
+    
+    cmd
+}
+
+/// Build the scrcpy command without executing it
+pub fn build_scrcpy_command(
+    scrcpy_path: &std::path::Path,
+    scrcpy_dir: &std::path::Path,
+    adb_dir: Option<&std::path::Path>,
+    device_id: Option<&str>,
+    options: &ScrcpyOptions,
+) -> Command {
+    let mut cmd = base_scrcpy_command(scrcpy_path, scrcpy_dir, adb_dir, device_id);
+    
     // Add options
-    if let Some(max_size) = options.max_size {
+    // An explicit camera size already fixes the resolution, and scrcpy
+    // rejects it combined with --max-size
+    if let Some(max_size) = options.max_size.filter(|_| options.camera_size.is_none()) {
         cmd.arg("--max-size").arg(max_size.to_string());
     }
     
@@ -168,112 +699,364 @@ pub fn build_scrcpy_command(
         cmd.arg("--always-on-top");
     }
     
-    if options.stay_awake {
+    // Camera sessions have no device control, which both of these require
+    if options.stay_awake && !options.is_camera() {
         cmd.arg("--stay-awake");
     }
     
-    if options.turn_screen_off {
+    if options.turn_screen_off && !options.is_camera() {
         cmd.arg("--turn-screen-off");
     }
     
-    #[cfg(target_os = "windows")]
-    {
-        use std::os::windows::process::CommandExt;
-        cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+    if let Some(codec) = options.video_codec {
+        cmd.arg("--video-codec").arg(codec.as_arg());
     }
     
-    cmd
-}
-
-/// Execute scrcpy with the given device ID and options
-pub fn execute_scrcpy(
-    app: &tauri::AppHandle,
-    device_id: Option<&str>,
-    options: &ScrcpyOptions,
-) -> Result<Child, String> {
-    let scrcpy_path = utils::get_scrcpy_path(app)?;
-    let scrcpy_dir = utils::get_scrcpy_dir(app)?;
-    let adb_dir = utils::get_adb_dir(app).ok();
+    if let Some(ref encoder) = options.video_encoder {
+        cmd.arg("--video-encoder").arg(encoder);
+    }
     
-    let mut cmd = build_scrcpy_command(
-        &scrcpy_path,
-        &scrcpy_dir,
-        adb_dir.as_deref(),
-        device_id,
-        options
-    );
-    
-    // Spawn the process
-    // Note: We use inherit() to pipe scrcpy output directly to our terminal for debugging.
-    // This allows us to see errors like "Input injection failed" or specific device warnings.
-    cmd.stdout(Stdio::inherit())
-        .stderr(Stdio::inherit())
-        .spawn()
-        .map_err(|e| format!("Failed to start scrcpy: {}", e))
-}
-
-
-
-/// Get scrcpy version
-pub fn get_version(app: &tauri::AppHandle) -> Result<String, String> {
-    let scrcpy_path = utils::get_scrcpy_path(app)?;
-    let scrcpy_dir = utils::get_scrcpy_dir(app)?;
-
-    #[cfg(target_os = "windows")]
-    use std::os::windows::process::CommandExt;
+    if let Some(ref codec_options) = options.video_codec_options {
+        cmd.arg("--video-codec-options").arg(codec_options);
+    }
     
-    let mut cmd = Command::new(scrcpy_path);
-    cmd.current_dir(scrcpy_dir)
-       .arg("--version");
-
-    #[cfg(target_os = "windows")]
-    cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
-
-    let output = cmd.output()
-        .map_err(|e| format!("Failed to execute scrcpy: {}", e))?;
+    if let Some(display_id) = options.display_id {
+        cmd.arg("--display-id").arg(display_id.to_string());
+    }
     
-    if output.status.success() {
-        let version = String::from_utf8_lossy(&output.stdout).to_string();
-        Ok(version.trim().to_string())
-    } else {
-        let error = String::from_utf8_lossy(&output.stderr).to_string();
-        Err(format!("Scrcpy error: {}", error))
+    if let Some(crop) = options.crop {
+        cmd.arg("--crop").arg(crop.as_arg());
     }
-}
-
-/// Check if scrcpy is available
-pub fn check_available(app: &tauri::AppHandle) -> bool {
-    utils::get_scrcpy_path(app).is_ok()
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::path::PathBuf;
-
-    #[test]
-    fn test_default_options() {
-        let options = ScrcpyOptions::default();
-        assert_eq!(options.max_size, Some(1920));
-        assert_eq!(options.bit_rate, Some(8000000));
-        assert_eq!(options.max_fps, Some(60));
-        assert_eq!(options.stay_awake, true);
+    
+    if let Some(orientation) = options.orientation {
+        cmd.arg("--orientation").arg(orientation.as_arg());
     }
-
-    #[test]
-    fn test_command_generation_resolution() {
-        let options = ScrcpyOptions {
-            max_size: Some(1080),
-            ..Default::default()
-        };
-        
-        let cmd = build_scrcpy_command(
-            &PathBuf::from("scrcpy"),
-            &PathBuf::from("."),
-            None,
-            None,
-            &options
-        );
+    
+    if let Some(capture_orientation) = options.capture_orientation_arg() {
+        cmd.arg("--capture-orientation").arg(capture_orientation);
+    }
+    
+    if let Some(angle) = options.angle {
+        cmd.arg("--angle").arg(angle.to_string());
+    }
+    
+    if !options.audio {
+        cmd.arg("--no-audio");
+    }
+    
+    if let Some(codec) = options.audio_codec {
+        cmd.arg("--audio-codec").arg(codec.as_arg());
+    }
+    
+    if let Some(audio_bit_rate) = options.audio_bit_rate {
+        cmd.arg("--audio-bit-rate").arg(audio_bit_rate.to_string());
+    }
+    
+    if let Some(source) = options.audio_source {
+        cmd.arg("--audio-source").arg(source.as_arg());
+    }
+    
+    if options.audio_dup {
+        cmd.arg("--audio-dup");
+    }
+    
+    if let Some(audio_buffer) = options.audio_buffer {
+        cmd.arg("--audio-buffer").arg(audio_buffer.to_string());
+    }
+    
+    if let Some(ref record_path) = options.record_path {
+        cmd.arg("--record").arg(record_path);
+    }
+    
+    if let Some(format) = options.record_format {
+        cmd.arg("--record-format").arg(format.as_arg());
+    }
+    
+    if let Some(orientation) = options.record_orientation {
+        cmd.arg("--record-orientation").arg(orientation.as_arg());
+    }
+    
+    if let Some(time_limit) = options.time_limit {
+        cmd.arg("--time-limit").arg(time_limit.to_string());
+    }
+    
+    if options.no_playback {
+        cmd.arg("--no-playback");
+    }
+    
+    if let Some(source) = options.video_source {
+        cmd.arg("--video-source").arg(source.as_arg());
+    }
+    
+    if let Some(ref camera_id) = options.camera_id {
+        cmd.arg("--camera-id").arg(camera_id);
+    }
+    
+    if let Some(facing) = options.camera_facing {
+        cmd.arg("--camera-facing").arg(facing.as_arg());
+    }
+    
+    if let Some(size) = options.camera_size {
+        cmd.arg("--camera-size").arg(size.as_arg());
+    }
+    
+    if let Some(ref ar) = options.camera_ar {
+        cmd.arg("--camera-ar").arg(ar);
+    }
+    
+    if let Some(camera_fps) = options.camera_fps {
+        cmd.arg("--camera-fps").arg(camera_fps.to_string());
+    }
+    
+    if options.camera_high_speed {
+        cmd.arg("--camera-high-speed");
+    }
+    
+    // The value of --new-display is optional, so it must be attached with '='
+    if let Some(new_display) = options.new_display {
+        let value = new_display.as_arg();
+        if value.is_empty() {
+            cmd.arg("--new-display");
+        } else {
+            cmd.arg(format!("--new-display={}", value));
+        }
+    }
+    
+    if options.no_vd_destroy_content {
+        cmd.arg("--no-vd-destroy-content");
+    }
+    
+    if options.no_vd_system_decorations {
+        cmd.arg("--no-vd-system-decorations");
+    }
+    
+    if let Some(ref start_app) = options.start_app {
+        cmd.arg("--start-app").arg(start_app.as_arg());
+    }
+    
+    if let Some(ref title) = options.window_title {
+        cmd.arg("--window-title").arg(title);
+    }
+    
+    if let Some(x) = options.window_x {
+        cmd.arg("--window-x").arg(x.to_string());
+    }
+    
+    if let Some(y) = options.window_y {
+        cmd.arg("--window-y").arg(y.to_string());
+    }
+    
+    if let Some(width) = options.window_width {
+        cmd.arg("--window-width").arg(width.to_string());
+    }
+    
+    if let Some(height) = options.window_height {
+        cmd.arg("--window-height").arg(height.to_string());
+    }
+    
+    if options.window_borderless {
+        cmd.arg("--window-borderless");
+    }
+    
+    if options.fullscreen {
+        cmd.arg("--fullscreen");
+    }
+    
+    if options.disable_screensaver {
+        cmd.arg("--disable-screensaver");
+    }
+    
+    // Checked by `validate` not to repeat any of the above
+    cmd.args(&options.extra_args);
+    
+    cmd
+}
+
+/// Execute scrcpy with the given device ID and options.
+///
+/// Options the scrcpy in use does not have are dropped first; the returned
+/// warnings say which.
+pub fn execute_scrcpy<R: tauri::Runtime>(
+    app: &tauri::AppHandle<R>,
+    launcher: &dyn Launcher,
+    device_id: Option<&str>,
+    options: &ScrcpyOptions,
+) -> Result<(SessionProcess, Vec<String>), String> {
+    options.validate()?;
+    
+    let (adapted, warnings) = match detect_version(app) {
+        Ok(version) => {
+            let (adapted, warnings) = options.adapt_to_version(&version)?;
+            (Some(adapted), warnings)
+        }
+        Err(e) => {
+            eprintln!("Could not detect scrcpy version, skipping version checks: {}", e);
+            (None, Vec::new())
+        }
+    };
+    let options = adapted.as_ref().unwrap_or(options);
+    
+    let scrcpy_path = utils::get_scrcpy_path(app)?;
+    let scrcpy_dir = utils::get_scrcpy_dir(app)?;
+    let adb_dir = utils::get_adb_dir(app).ok();
+    
+    let process = launcher.spawn(&LaunchRequest {
+        scrcpy_path: &scrcpy_path,
+        scrcpy_dir: &scrcpy_dir,
+        adb_dir: adb_dir.as_deref(),
+        device_id,
+        options,
+    })?;
+    Ok((process, warnings))
+}
+
+/// Wait until a freshly spawned scrcpy reports that it is up.
+///
+/// Returns an error with the captured stderr if scrcpy exits during startup.
+/// A session that neither exits nor reports readiness before `timeout` is
+/// assumed to be running (older releases may not print the expected lines).
+pub fn wait_for_startup(child: &mut dyn ScrcpyProcess, log: &SessionLog, timeout: Duration) -> Result<(), String> {
+    let deadline = Instant::now() + timeout;
+    
+    loop {
+        if log.is_ready() {
+            return Ok(());
+        }
+        
+        let state = child.try_wait()
+            .map_err(|e| format!("Failed to check scrcpy process: {}", e))?;
+        
+        if let ProcessState::Exited(status) = state {
+            // Let the readers drain what scrcpy printed before exiting
+            let drain_deadline = Instant::now() + Duration::from_millis(500);
+            while !log.is_closed() && Instant::now() < drain_deadline {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            
+            let exit = match (status.and_then(|s| s.code()), status.and_then(exit_signal)) {
+                (Some(code), _) => format!("exit code {}", code),
+                (None, Some(signal)) => format!("signal {}", signal),
+                _ => "unknown status".to_string(),
+            };
+            
+            let mut output = log.tail(LogStream::Stderr, 20);
+            if output.is_empty() {
+                output = log.tail(LogStream::Stdout, 20);
+            }
+            
+            return Err(if output.is_empty() {
+                format!("scrcpy exited during startup ({})", exit)
+            } else {
+                format!("scrcpy exited during startup ({}):\n{}", exit, output.join("\n"))
+            });
+        }
+        
+        if Instant::now() >= deadline {
+            return Ok(());
+        }
+        
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Ask a scrcpy process to exit on its own, killing it if it is still
+/// running after `grace`.
+///
+/// A clean exit lets scrcpy finalize recordings and restore device state
+/// (show touches, screen power, stay awake). On Unix this sends SIGTERM; on
+/// Windows CTRL_BREAK, which also reaches sessions without a window.
+pub fn terminate(process: &mut dyn ScrcpyProcess, grace: Duration) -> std::io::Result<ShutdownReport> {
+    if let ProcessState::Exited(status) = process.try_wait()? {
+        return Ok(ShutdownReport::new(ShutdownMethod::AlreadyExited, status));
+    }
+    
+    if let Err(e) = process.request_exit() {
+        eprintln!("Failed to ask scrcpy (pid {}) to exit: {}", process.id(), e);
+    }
+    
+    wait_or_kill(process, Instant::now() + grace)
+}
+
+/// Wait for a process that was asked to exit, killing it at `deadline`
+fn wait_or_kill(process: &mut dyn ScrcpyProcess, deadline: Instant) -> std::io::Result<ShutdownReport> {
+    while Instant::now() < deadline {
+        if let ProcessState::Exited(status) = process.try_wait()? {
+            return Ok(ShutdownReport::new(ShutdownMethod::Graceful, status));
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    
+    if let ProcessState::Exited(status) = process.try_wait()? {
+        return Ok(ShutdownReport::new(ShutdownMethod::Graceful, status));
+    }
+    
+    process.kill()?;
+    let status = process.wait()?;
+    Ok(ShutdownReport::new(ShutdownMethod::Killed, status))
+}
+
+/// Get scrcpy version
+pub fn get_version<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> Result<String, String> {
+    let scrcpy_path = utils::get_scrcpy_path(app)?;
+    let scrcpy_dir = utils::get_scrcpy_dir(app)?;
+
+    #[cfg(target_os = "windows")]
+    use std::os::windows::process::CommandExt;
+    
+    let mut cmd = Command::new(scrcpy_path);
+    cmd.current_dir(scrcpy_dir)
+       .arg("--version");
+
+    #[cfg(target_os = "windows")]
+    cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
+
+    let output = cmd.output()
+        .map_err(|e| format!("Failed to execute scrcpy: {}", e))?;
+    
+    if output.status.success() {
+        let version = String::from_utf8_lossy(&output.stdout).to_string();
+        Ok(version.trim().to_string())
+    } else {
+        let error = String::from_utf8_lossy(&output.stderr).to_string();
+        Err(format!("Scrcpy error: {}", error))
+    }
+}
+
+/// Check if scrcpy is available
+pub fn check_available<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> bool {
+    utils::get_scrcpy_path(app).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::fake::{FakeLauncher, FakeScript};
+    use std::path::{Path, PathBuf};
+    use std::process::{Child, Stdio};
+
+    #[test]
+    fn test_default_options() {
+        let options = ScrcpyOptions::default();
+        assert_eq!(options.max_size, Some(1920));
+        assert_eq!(options.bit_rate, Some(8000000));
+        assert_eq!(options.max_fps, Some(60));
+        assert_eq!(options.stay_awake, true);
+    }
+
+    #[test]
+    fn test_command_generation_resolution() {
+        let options = ScrcpyOptions {
+            max_size: Some(1080),
+            ..Default::default()
+        };
+        
+        let cmd = build_scrcpy_command(
+            &PathBuf::from("scrcpy"),
+            &PathBuf::from("."),
+            None,
+            None,
+            &options
+        );
         
         let args: Vec<&str> = cmd.get_args().map(|s| s.to_str().unwrap()).collect();
         assert!(args.contains(&"--max-size"));
@@ -360,6 +1143,301 @@ mod tests {
         assert!(args.contains(&"device123"));
     }
 
+    #[test]
+    fn test_command_generation_video_codec() {
+        let options = ScrcpyOptions {
+            video_codec: Some(VideoCodec::H265),
+            video_encoder: Some("c2.qti.hevc.encoder".to_string()),
+            video_codec_options: Some("profile=1,level:int=4096".to_string()),
+            ..Default::default()
+        };
+        
+        let cmd = build_scrcpy_command(
+            &PathBuf::from("scrcpy"),
+            &PathBuf::from("."),
+            None,
+            None,
+            &options
+        );
+        
+        let args: Vec<&str> = cmd.get_args().map(|s| s.to_str().unwrap()).collect();
+        assert!(args.windows(2).any(|w| w == ["--video-codec", "h265"]));
+        assert!(args.windows(2).any(|w| w == ["--video-encoder", "c2.qti.hevc.encoder"]));
+        assert!(args.windows(2).any(|w| w == ["--video-codec-options", "profile=1,level:int=4096"]));
+    }
+
+    #[test]
+    fn test_command_generation_display() {
+        let options = ScrcpyOptions {
+            display_id: Some(2),
+            crop: Some(Crop { width: 1224, height: 1440, x: 0, y: 0 }),
+            ..Default::default()
+        };
+        
+        let cmd = build_scrcpy_command(
+            &PathBuf::from("scrcpy"),
+            &PathBuf::from("."),
+            None,
+            None,
+            &options
+        );
+        
+        let args: Vec<&str> = cmd.get_args().map(|s| s.to_str().unwrap()).collect();
+        assert!(args.windows(2).any(|w| w == ["--display-id", "2"]));
+        assert!(args.windows(2).any(|w| w == ["--crop", "1224:1440:0:0"]));
+    }
+
+    #[test]
+    fn test_command_generation_orientation() {
+        let options = ScrcpyOptions {
+            orientation: Some(Orientation::Deg90),
+            capture_orientation: Some(Orientation::Flip180),
+            capture_orientation_locked: true,
+            angle: Some(-12.5),
+            ..Default::default()
+        };
+        
+        let cmd = build_scrcpy_command(
+            &PathBuf::from("scrcpy"),
+            &PathBuf::from("."),
+            None,
+            None,
+            &options
+        );
+        
+        let args: Vec<&str> = cmd.get_args().map(|s| s.to_str().unwrap()).collect();
+        assert!(args.windows(2).any(|w| w == ["--orientation", "90"]));
+        assert!(args.windows(2).any(|w| w == ["--capture-orientation", "@flip180"]));
+        assert!(args.windows(2).any(|w| w == ["--angle", "-12.5"]));
+    }
+
+    #[test]
+    fn test_command_generation_omits_unset_video_options() {
+        let cmd = build_scrcpy_command(
+            &PathBuf::from("scrcpy"),
+            &PathBuf::from("."),
+            None,
+            None,
+            &ScrcpyOptions::default()
+        );
+        
+        let args: Vec<&str> = cmd.get_args().map(|s| s.to_str().unwrap()).collect();
+        for flag in ["--video-codec", "--video-encoder", "--display-id", "--crop", "--orientation", "--capture-orientation", "--angle"] {
+            assert!(!args.contains(&flag), "unexpected {}", flag);
+        }
+    }
+
+    #[test]
+    fn test_command_generation_audio() {
+        let options = ScrcpyOptions {
+            audio_codec: Some(AudioCodec::Aac),
+            audio_bit_rate: Some(192000),
+            audio_source: Some(AudioSource::Playback),
+            audio_dup: true,
+            audio_buffer: Some(80),
+            ..Default::default()
+        };
+        
+        let cmd = build_scrcpy_command(
+            &PathBuf::from("scrcpy"),
+            &PathBuf::from("."),
+            None,
+            None,
+            &options
+        );
+        
+        let args: Vec<&str> = cmd.get_args().map(|s| s.to_str().unwrap()).collect();
+        assert!(args.windows(2).any(|w| w == ["--audio-codec", "aac"]));
+        assert!(args.windows(2).any(|w| w == ["--audio-bit-rate", "192000"]));
+        assert!(args.windows(2).any(|w| w == ["--audio-source", "playback"]));
+        assert!(args.windows(2).any(|w| w == ["--audio-buffer", "80"]));
+        assert!(args.contains(&"--audio-dup"));
+        assert!(!args.contains(&"--no-audio"));
+    }
+
+    #[test]
+    fn test_command_generation_no_audio() {
+        let options = ScrcpyOptions {
+            audio: false,
+            ..Default::default()
+        };
+        
+        let cmd = build_scrcpy_command(
+            &PathBuf::from("scrcpy"),
+            &PathBuf::from("."),
+            None,
+            None,
+            &options
+        );
+        
+        let args: Vec<&str> = cmd.get_args().map(|s| s.to_str().unwrap()).collect();
+        assert!(args.contains(&"--no-audio"));
+    }
+
+    #[test]
+    fn test_command_generation_recording() {
+        let options = ScrcpyOptions {
+            record_path: Some("/tmp/demo.mkv".to_string()),
+            record_format: Some(RecordFormat::Mkv),
+            record_orientation: Some(Orientation::Deg270),
+            time_limit: Some(120),
+            no_playback: true,
+            ..Default::default()
+        };
+        
+        let cmd = build_scrcpy_command(
+            &PathBuf::from("scrcpy"),
+            &PathBuf::from("."),
+            None,
+            None,
+            &options
+        );
+        
+        let args: Vec<&str> = cmd.get_args().map(|s| s.to_str().unwrap()).collect();
+        assert!(args.windows(2).any(|w| w == ["--record", "/tmp/demo.mkv"]));
+        assert!(args.windows(2).any(|w| w == ["--record-format", "mkv"]));
+        assert!(args.windows(2).any(|w| w == ["--record-orientation", "270"]));
+        assert!(args.windows(2).any(|w| w == ["--time-limit", "120"]));
+        assert!(args.contains(&"--no-playback"));
+    }
+
+    #[test]
+    fn test_command_generation_camera() {
+        let options = ScrcpyOptions {
+            video_source: Some(VideoSource::Camera),
+            camera_id: Some("1".to_string()),
+            camera_size: Some(Size { width: 1920, height: 1080 }),
+            camera_fps: Some(120),
+            camera_high_speed: true,
+            ..Default::default()
+        };
+        
+        let cmd = build_scrcpy_command(
+            &PathBuf::from("scrcpy"),
+            &PathBuf::from("."),
+            None,
+            None,
+            &options
+        );
+        
+        let args: Vec<&str> = cmd.get_args().map(|s| s.to_str().unwrap()).collect();
+        assert!(args.windows(2).any(|w| w == ["--video-source", "camera"]));
+        assert!(args.windows(2).any(|w| w == ["--camera-id", "1"]));
+        assert!(args.windows(2).any(|w| w == ["--camera-size", "1920x1080"]));
+        assert!(args.windows(2).any(|w| w == ["--camera-fps", "120"]));
+        assert!(args.contains(&"--camera-high-speed"));
+        
+        // Incompatible with camera capture, even though enabled by default
+        assert!(!args.contains(&"--max-size"));
+        assert!(!args.contains(&"--stay-awake"));
+    }
+
+    #[test]
+    fn test_command_generation_camera_facing() {
+        let options = ScrcpyOptions {
+            video_source: Some(VideoSource::Camera),
+            camera_facing: Some(CameraFacing::Front),
+            camera_ar: Some("4:3".to_string()),
+            ..Default::default()
+        };
+        
+        let cmd = build_scrcpy_command(
+            &PathBuf::from("scrcpy"),
+            &PathBuf::from("."),
+            None,
+            None,
+            &options
+        );
+        
+        let args: Vec<&str> = cmd.get_args().map(|s| s.to_str().unwrap()).collect();
+        assert!(args.windows(2).any(|w| w == ["--camera-facing", "front"]));
+        assert!(args.windows(2).any(|w| w == ["--camera-ar", "4:3"]));
+        assert!(args.windows(2).any(|w| w == ["--max-size", "1920"]));
+    }
+
+    #[test]
+    fn test_command_generation_new_display() {
+        let options = ScrcpyOptions {
+            new_display: Some(NewDisplay { size: Some(Size { width: 1920, height: 1080 }), dpi: Some(420) }),
+            no_vd_system_decorations: true,
+            start_app: Some(StartApp { package: "org.mozilla.firefox".to_string(), force_stop: true, search: false }),
+            ..Default::default()
+        };
+        
+        let cmd = build_scrcpy_command(
+            &PathBuf::from("scrcpy"),
+            &PathBuf::from("."),
+            None,
+            None,
+            &options
+        );
+        
+        let args: Vec<&str> = cmd.get_args().map(|s| s.to_str().unwrap()).collect();
+        assert!(args.contains(&"--new-display=1920x1080/420"));
+        assert!(args.contains(&"--no-vd-system-decorations"));
+        assert!(!args.contains(&"--no-vd-destroy-content"));
+        assert!(args.windows(2).any(|w| w == ["--start-app", "+org.mozilla.firefox"]));
+    }
+
+    #[test]
+    fn test_command_generation_new_display_defaults() {
+        let options = ScrcpyOptions {
+            new_display: Some(NewDisplay::default()),
+            ..Default::default()
+        };
+        
+        let cmd = build_scrcpy_command(
+            &PathBuf::from("scrcpy"),
+            &PathBuf::from("."),
+            None,
+            None,
+            &options
+        );
+        
+        let args: Vec<&str> = cmd.get_args().map(|s| s.to_str().unwrap()).collect();
+        assert!(args.contains(&"--new-display"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_terminate_exits_gracefully() {
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let started = Instant::now();
+        let report = terminate(&mut child, Duration::from_secs(5)).unwrap();
+        
+        // SIGTERM ends `sleep` well before the grace period elapses
+        assert_eq!(report.method, ShutdownMethod::Graceful);
+        assert_eq!(report.signal, Some(15));
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_terminate_kills_after_grace() {
+        // The shell ignores SIGTERM, so only the kill ends it
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg("trap '' TERM; sleep 30 & wait; sleep 30")
+            .spawn()
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        
+        let report = terminate(&mut child, Duration::from_millis(300)).unwrap();
+        assert_eq!(report.method, ShutdownMethod::Killed);
+        assert_eq!(report.signal, Some(9));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_terminate_already_exited() {
+        let mut child = Command::new("true").spawn().unwrap();
+        child.wait().unwrap();
+        
+        let report = terminate(&mut child, Duration::from_secs(1)).unwrap();
+        assert_eq!(report.method, ShutdownMethod::AlreadyExited);
+        assert_eq!(report.exit_code, Some(0));
+    }
+
     #[test]
     fn test_scrcpy_state_management() {
         let state = ScrcpyState::new();
@@ -368,13 +1446,291 @@ mod tests {
         assert_eq!(state.active_count(), 0);
         assert_eq!(state.get_active_sessions().unwrap().len(), 0);
         
-        // We can't easily create a real Child process in tests without spawning something,
-        // but we can verify the state container logic if we could construct a ProcessInfo.
-        // However, ProcessInfo requires a Child which has no public constructor.
-        // So we'll just test the empty state behavior here.
-        
         assert!(!state.is_running("session1"));
         assert!(state.get_process_info("session1").unwrap().is_none());
+        
+        let launcher = FakeLauncher::new();
+        let state = ScrcpyState::with_launcher(launcher.clone());
+        state.add_process("session1".to_string(), fake_info(&launcher, "device1")).unwrap();
+        assert!(state.is_running("session1"));
+        assert_eq!(state.active_count(), 1);
+        assert_eq!(state.get_process_info("session1").unwrap().unwrap().0, "device1");
+    }
+
+    /// A fake scrcpy tracked as a running session, for tests of the state alone.
+    /// The start, stop and restart flows are tested in `commands::scrcpy`.
+    fn fake_info(launcher: &FakeLauncher, device_id: &str) -> ProcessInfo {
+        let options = ScrcpyOptions::default();
+        let process = launcher.spawn(&LaunchRequest {
+            scrcpy_path: Path::new("scrcpy"),
+            scrcpy_dir: Path::new("."),
+            adb_dir: None,
+            device_id: Some(device_id),
+            options: &options,
+        }).unwrap();
+        
+        ProcessInfo {
+            process,
+            device_id: device_id.to_string(),
+            started_at: std::time::SystemTime::now(),
+            target: options.session_target(),
+            recording_id: None,
+            log: SessionLog::new(),
+            resources: ResourceMonitor::new(),
+        }
+    }
+
+    #[test]
+    fn test_fake_crash_is_cleaned_up() {
+        let launcher = FakeLauncher::new();
+        let state = ScrcpyState::with_launcher(launcher.clone());
+        let info = ProcessInfo {
+            recording_id: Some("rec_1".to_string()),
+            ..fake_info(&launcher, "device1")
+        };
+        state.add_process("s1".to_string(), info).unwrap();
+        
+        state.cleanup_finished().unwrap();
+        assert!(state.is_running("s1"));
+        
+        launcher.last().exit(1);
+        state.cleanup_finished().unwrap();
+        assert!(!state.is_running("s1"));
+        
+        let exit = state.session_exit("s1").unwrap();
+        assert_eq!(exit.code, Some(1));
+        assert_eq!(exit.reason, ExitReason::Failed);
+        // Kept for the session monitor to finalize
+        assert_eq!(state.finished_recording("s1").as_deref(), Some("rec_1"));
+    }
+
+    #[test]
+    fn test_fake_stop_all_kills_stubborn_sessions() {
+        let launcher = FakeLauncher::new();
+        let state = ScrcpyState::with_launcher(launcher.clone());
+        state.add_process("polite".to_string(), fake_info(&launcher, "device1")).unwrap();
+        launcher.push_script(FakeScript {
+            ignore_exit_request: true,
+            ..Default::default()
+        });
+        state.add_process("stubborn".to_string(), fake_info(&launcher, "device2")).unwrap();
+        
+        let reports: HashMap<String, ShutdownReport> = state.stop_all(Duration::from_millis(200))
+            .unwrap()
+            .into_iter()
+            .collect();
+        assert_eq!(reports["polite"].method, ShutdownMethod::Graceful);
+        assert_eq!(reports["stubborn"].method, ShutdownMethod::Killed);
+        assert_eq!(state.session_exit("stubborn").unwrap().reason, ExitReason::Stopped);
+        assert_eq!(state.active_count(), 0);
+    }
+
+    #[cfg(unix)]
+    fn spawn_captured(script: &str) -> (Child, SessionLog) {
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let log = SessionLog::new();
+        capture_output(&mut child, &log, |_, _| {});
+        (child, log)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_startup_ready() {
+        let (mut child, log) = spawn_captured("echo 'INFO: Renderer: opengl'; sleep 30");
+        let started = Instant::now();
+        assert!(wait_for_startup(&mut child, &log, Duration::from_secs(10)).is_ok());
+        assert!(started.elapsed() < Duration::from_secs(5));
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_startup_crash_reports_stderr() {
+        let (mut child, log) = spawn_captured(
+            "echo 'INFO: scrcpy 3.3.3'; echo 'ERROR: Could not find any ADB device' >&2; exit 1"
+        );
+        let err = wait_for_startup(&mut child, &log, Duration::from_secs(10)).unwrap_err();
+        assert!(err.contains("exit code 1"));
+        assert!(err.contains("Could not find any ADB device"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_startup_timeout_assumes_running() {
+        let (mut child, log) = spawn_captured("sleep 30");
+        assert!(wait_for_startup(&mut child, &log, Duration::from_millis(200)).is_ok());
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_logs_outlive_session() {
+        let state = ScrcpyState::new();
+        let log = SessionLog::new();
+        log.push(LogStream::Stderr, "ERROR: Server connection failed");
+        
+        let info = ProcessInfo {
+            process: Command::new("true").spawn().unwrap().into(),
+            device_id: "device1".to_string(),
+            started_at: std::time::SystemTime::now(),
+            target: Some(SessionTarget::Display(0)),
+            recording_id: None,
+            log,
+            resources: ResourceMonitor::new(),
+        };
+        state.add_process("session1".to_string(), info).unwrap();
+        assert_eq!(state.session_logs("session1").unwrap().unwrap().lines.len(), 1);
+        
+        let mut info = state.remove_process("session1").unwrap().unwrap();
+        info.process.wait().unwrap();
+        
+        let logs = state.session_logs("session1").unwrap().unwrap();
+        assert_eq!(logs.failure, Some(SessionFailure::ServerConnectionFailed));
+        assert!(state.session_logs("session2").unwrap().is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_poll_session_reports_exit() {
+        let state = ScrcpyState::new();
+        let log = SessionLog::new();
+        log.push(LogStream::Stderr, "WARN: Device disconnected");
+        
+        let info = ProcessInfo {
+            process: Command::new("sh").arg("-c").arg("exit 2").spawn().unwrap().into(),
+            device_id: "device1".to_string(),
+            started_at: std::time::SystemTime::now(),
+            target: Some(SessionTarget::Display(0)),
+            recording_id: None,
+            log,
+            resources: ResourceMonitor::new(),
+        };
+        state.add_process("session1".to_string(), info).unwrap();
+        
+        let started = Instant::now();
+        let exit = loop {
+            match state.poll_session("session1", |_| false).unwrap() {
+                SessionPoll::Running => {
+                    assert!(started.elapsed() < Duration::from_secs(5));
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                SessionPoll::Exited(_, exit) => break exit,
+                SessionPoll::NotTracked => panic!("session should still be tracked"),
+            }
+        };
+        
+        assert_eq!(exit.code, Some(2));
+        assert_eq!(exit.reason, ExitReason::Failed);
+        assert_eq!(exit.failure, Some(SessionFailure::DeviceDisconnected));
+        assert!(!state.is_running("session1"));
+        assert!(matches!(state.poll_session("session1", |_| false).unwrap(), SessionPoll::NotTracked));
+        assert_eq!(state.session_exit("session1").unwrap().reason, ExitReason::Failed);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_clean_exit_is_closed() {
+        let state = ScrcpyState::new();
+        let info = ProcessInfo {
+            process: Command::new("true").spawn().unwrap().into(),
+            device_id: "device1".to_string(),
+            started_at: std::time::SystemTime::now(),
+            target: Some(SessionTarget::Display(0)),
+            recording_id: None,
+            log: SessionLog::new(),
+            resources: ResourceMonitor::new(),
+        };
+        state.add_process("session1".to_string(), info).unwrap();
+        
+        let started = Instant::now();
+        while state.is_running("session1") {
+            assert!(started.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(10));
+            state.cleanup_finished().unwrap();
+        }
+        
+        let exit = state.session_exit("session1").unwrap();
+        assert_eq!(exit.code, Some(0));
+        assert_eq!(exit.reason, ExitReason::Closed);
+        
+        // Removing the session afterwards keeps the known exit
+        state.record_exit("session1", &SessionLog::new(), None);
+        assert!(state.session_exit("session1").is_some());
+    }
+
+    #[test]
+    fn test_claim_target() {
+        let launcher = FakeLauncher::new();
+        let state = ScrcpyState::with_launcher(launcher.clone());
+        let display = SessionTarget::Display(0);
+        
+        let reservation = match state.claim_target("device1", &display).unwrap() {
+            TargetClaim::Claimed(reservation) => reservation,
+            _ => panic!("target should be free"),
+        };
+        // A second start while the first is still starting up
+        assert!(matches!(state.claim_target("device1", &display).unwrap(), TargetClaim::Starting));
+        assert!(matches!(state.claim_target("device2", &display).unwrap(), TargetClaim::Claimed(_)));
+        
+        // A failed start gives the target back
+        drop(reservation);
+        assert!(matches!(state.claim_target("device1", &display).unwrap(), TargetClaim::Claimed(_)));
+        
+        state.add_process("s1".to_string(), fake_info(&launcher, "device1")).unwrap();
+        assert!(matches!(state.claim_target("device1", &display).unwrap(), TargetClaim::Taken(id) if id == "s1"));
+    }
+
+    #[test]
+    fn test_restart_marks() {
+        let state = ScrcpyState::new();
+        assert!(!state.is_restarting("session1"));
+        
+        state.begin_restart("session1", "device1", Some(SessionTarget::Display(0)));
+        assert!(state.is_restarting("session1"));
+        
+        // Cancelling (e.g. from stop_mirroring) makes the monitor's end_restart fail
+        assert!(state.end_restart("session1"));
+        assert!(!state.end_restart("session1"));
+        assert!(!state.is_restarting("session1"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find_session_by_target() {
+        let state = ScrcpyState::new();
+        let info = ProcessInfo {
+            process: Command::new("sleep").arg("30").spawn().unwrap().into(),
+            device_id: "device1".to_string(),
+            started_at: std::time::SystemTime::now(),
+            target: Some(SessionTarget::Display(0)),
+            recording_id: None,
+            log: SessionLog::new(),
+            resources: ResourceMonitor::new(),
+        };
+        state.add_process("session1".to_string(), info).unwrap();
+        state.begin_restart("session2", "device1", Some(SessionTarget::Camera("front".to_string())));
+        
+        let display = SessionTarget::Display(0);
+        assert_eq!(state.find_session("device1", &display).unwrap().as_deref(), Some("session1"));
+        assert_eq!(state.find_session("device2", &display).unwrap(), None);
+        assert_eq!(state.find_session("device1", &SessionTarget::Display(1)).unwrap(), None);
+        
+        // Sessions waiting to be relaunched still hold their target
+        let camera = SessionTarget::Camera("front".to_string());
+        assert_eq!(state.find_session("device1", &camera).unwrap().as_deref(), Some("session2"));
+        assert_eq!(state.sessions_for_device("device1").unwrap().len(), 2);
+        
+        let mut info = state.remove_process("session1").unwrap().unwrap();
+        info.process.kill().unwrap();
+        info.process.wait().unwrap();
     }
 
     #[test]
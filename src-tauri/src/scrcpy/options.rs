@@ -0,0 +1,1453 @@
+use std::fmt;
+use serde::{Serialize, Deserialize};
+use super::args::validate_extra_args;
+use super::capabilities::Capability;
+use super::list::CameraInfo;
+use super::version::ScrcpyVersion;
+
+/// Video codec used by the device encoder (`--video-codec`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    H264,
+    H265,
+    Av1,
+}
+
+impl VideoCodec {
+    /// Value passed to scrcpy on the command line
+    pub fn as_arg(&self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "h264",
+            VideoCodec::H265 => "h265",
+            VideoCodec::Av1 => "av1",
+        }
+    }
+}
+
+/// Audio codec used by the device encoder (`--audio-codec`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioCodec {
+    Opus,
+    Aac,
+    Flac,
+    Raw,
+}
+
+impl AudioCodec {
+    /// Value passed to scrcpy on the command line
+    pub fn as_arg(&self) -> &'static str {
+        match self {
+            AudioCodec::Opus => "opus",
+            AudioCodec::Aac => "aac",
+            AudioCodec::Flac => "flac",
+            AudioCodec::Raw => "raw",
+        }
+    }
+}
+
+/// Audio captured on the device (`--audio-source`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AudioSource {
+    /// Whole audio output; disables playback on the device
+    Output,
+    /// Audio playback, keeping it on the device with `audio_dup`
+    Playback,
+    Mic,
+    MicUnprocessed,
+    MicCamcorder,
+    MicVoiceRecognition,
+    MicVoiceCommunication,
+    VoiceCall,
+    VoiceCallUplink,
+    VoiceCallDownlink,
+    VoicePerformance,
+}
+
+impl AudioSource {
+    /// Value passed to scrcpy on the command line
+    pub fn as_arg(&self) -> &'static str {
+        match self {
+            AudioSource::Output => "output",
+            AudioSource::Playback => "playback",
+            AudioSource::Mic => "mic",
+            AudioSource::MicUnprocessed => "mic-unprocessed",
+            AudioSource::MicCamcorder => "mic-camcorder",
+            AudioSource::MicVoiceRecognition => "mic-voice-recognition",
+            AudioSource::MicVoiceCommunication => "mic-voice-communication",
+            AudioSource::VoiceCall => "voice-call",
+            AudioSource::VoiceCallUplink => "voice-call-uplink",
+            AudioSource::VoiceCallDownlink => "voice-call-downlink",
+            AudioSource::VoicePerformance => "voice-performance",
+        }
+    }
+
+    /// First scrcpy release accepting this source
+    pub fn min_version(&self) -> ScrcpyVersion {
+        match self {
+            AudioSource::Output | AudioSource::Mic => ScrcpyVersion::new(2, 0, 0),
+            AudioSource::Playback => ScrcpyVersion::new(2, 6, 0),
+            _ => ScrcpyVersion::new(3, 0, 0),
+        }
+    }
+}
+
+/// Container used for recordings (`--record-format`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordFormat {
+    Mp4,
+    Mkv,
+    M4a,
+    Mka,
+    Opus,
+    Aac,
+    Flac,
+    Wav,
+}
+
+impl RecordFormat {
+    /// Value passed to scrcpy on the command line
+    pub fn as_arg(&self) -> &'static str {
+        match self {
+            RecordFormat::Mp4 => "mp4",
+            RecordFormat::Mkv => "mkv",
+            RecordFormat::M4a => "m4a",
+            RecordFormat::Mka => "mka",
+            RecordFormat::Opus => "opus",
+            RecordFormat::Aac => "aac",
+            RecordFormat::Flac => "flac",
+            RecordFormat::Wav => "wav",
+        }
+    }
+
+    /// Format scrcpy infers from a file extension
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "mp4" => Some(RecordFormat::Mp4),
+            "mkv" => Some(RecordFormat::Mkv),
+            "m4a" => Some(RecordFormat::M4a),
+            "mka" => Some(RecordFormat::Mka),
+            "opus" => Some(RecordFormat::Opus),
+            "aac" => Some(RecordFormat::Aac),
+            "flac" => Some(RecordFormat::Flac),
+            "wav" => Some(RecordFormat::Wav),
+            _ => None,
+        }
+    }
+
+    /// Whether the container can hold a video stream
+    pub fn has_video(&self) -> bool {
+        matches!(self, RecordFormat::Mp4 | RecordFormat::Mkv)
+    }
+}
+
+/// What the device streams (`--video-source`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoSource {
+    Display,
+    Camera,
+}
+
+impl VideoSource {
+    /// Value passed to scrcpy on the command line
+    pub fn as_arg(&self) -> &'static str {
+        match self {
+            VideoSource::Display => "display",
+            VideoSource::Camera => "camera",
+        }
+    }
+}
+
+/// Direction a device camera faces (`--camera-facing`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CameraFacing {
+    Front,
+    Back,
+    External,
+}
+
+impl CameraFacing {
+    /// Value passed to scrcpy on the command line
+    pub fn as_arg(&self) -> &'static str {
+        match self {
+            CameraFacing::Front => "front",
+            CameraFacing::Back => "back",
+            CameraFacing::External => "external",
+        }
+    }
+
+    /// Parse the facing name printed by `scrcpy --list-cameras`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim() {
+            "front" => Some(CameraFacing::Front),
+            "back" => Some(CameraFacing::Back),
+            "external" => Some(CameraFacing::External),
+            _ => None,
+        }
+    }
+}
+
+/// Width and height in pixels, of a camera capture (`--camera-size`) or a display
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Size {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Size {
+    /// Value passed to scrcpy on the command line (`<width>x<height>`)
+    pub fn as_arg(&self) -> String {
+        format!("{}x{}", self.width, self.height)
+    }
+
+    /// Parse a `<width>x<height>` size
+    pub fn parse(value: &str) -> Option<Self> {
+        let (width, height) = value.trim().split_once('x')?;
+        Some(Self {
+            width: width.parse().ok()?,
+            height: height.parse().ok()?,
+        })
+    }
+}
+
+/// Virtual display created for the session (`--new-display`, scrcpy 3.0+)
+///
+/// Unset fields default to the size and density of the main display.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NewDisplay {
+    pub size: Option<Size>,
+    pub dpi: Option<u32>,
+}
+
+impl NewDisplay {
+    /// Value passed to scrcpy (`[<width>x<height>][/<dpi>]`), empty for the defaults
+    pub fn as_arg(&self) -> String {
+        let mut value = self.size.map(|s| s.as_arg()).unwrap_or_default();
+        if let Some(dpi) = self.dpi {
+            value.push_str(&format!("/{}", dpi));
+        }
+        value
+    }
+}
+
+/// App started on the device when the session begins (`--start-app`, scrcpy 3.0+)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StartApp {
+    /// Exact package name, or the start of the app name when `search` is set
+    pub package: String,
+    /// Force-stop the app before starting it (`+` prefix)
+    #[serde(default)]
+    pub force_stop: bool,
+    /// Select the app by name instead of package, case-insensitive (`?` prefix)
+    #[serde(default)]
+    pub search: bool,
+}
+
+impl StartApp {
+    /// Value passed to scrcpy on the command line, with its prefixes
+    pub fn as_arg(&self) -> String {
+        let mut value = String::new();
+        if self.force_stop {
+            value.push('+');
+        }
+        if self.search {
+            value.push('?');
+        }
+        value.push_str(&self.package);
+        value
+    }
+}
+
+/// What a session mirrors on its device.
+///
+/// Two sessions of the same device conflict when they have the same target.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SessionTarget {
+    /// An existing display, by id
+    Display(u32),
+    /// A camera, by id or facing ("default" when unspecified)
+    Camera(String),
+}
+
+impl fmt::Display for SessionTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionTarget::Display(id) => write!(f, "display {}", id),
+            SessionTarget::Camera(camera) => write!(f, "camera {}", camera),
+        }
+    }
+}
+
+/// Clockwise rotation, optionally preceded by a horizontal flip
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Orientation {
+    #[serde(rename = "0")]
+    Deg0,
+    #[serde(rename = "90")]
+    Deg90,
+    #[serde(rename = "180")]
+    Deg180,
+    #[serde(rename = "270")]
+    Deg270,
+    #[serde(rename = "flip0")]
+    Flip0,
+    #[serde(rename = "flip90")]
+    Flip90,
+    #[serde(rename = "flip180")]
+    Flip180,
+    #[serde(rename = "flip270")]
+    Flip270,
+}
+
+impl Orientation {
+    /// Whether this is a plain rotation (no flip)
+    pub fn is_rotation(&self) -> bool {
+        matches!(self, Orientation::Deg0 | Orientation::Deg90 | Orientation::Deg180 | Orientation::Deg270)
+    }
+
+    /// Value passed to scrcpy on the command line
+    pub fn as_arg(&self) -> &'static str {
+        match self {
+            Orientation::Deg0 => "0",
+            Orientation::Deg90 => "90",
+            Orientation::Deg180 => "180",
+            Orientation::Deg270 => "270",
+            Orientation::Flip0 => "flip0",
+            Orientation::Flip90 => "flip90",
+            Orientation::Flip180 => "flip180",
+            Orientation::Flip270 => "flip270",
+        }
+    }
+}
+
+/// Region of the device screen to mirror, in the device natural orientation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Crop {
+    pub width: u32,
+    pub height: u32,
+    pub x: u32,
+    pub y: u32,
+}
+
+impl Crop {
+    /// Value passed to scrcpy on the command line (`width:height:x:y`)
+    pub fn as_arg(&self) -> String {
+        format!("{}:{}:{}:{}", self.width, self.height, self.x, self.y)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScrcpyOptions {
+    pub max_size: Option<u32>,
+    pub bit_rate: Option<u32>,
+    pub max_fps: Option<u32>,
+    pub always_on_top: bool,
+    pub stay_awake: bool,
+    pub turn_screen_off: bool,
+    pub video_codec: Option<VideoCodec>,
+    pub video_encoder: Option<String>,
+    pub video_codec_options: Option<String>,
+    pub display_id: Option<u32>,
+    pub crop: Option<Crop>,
+    /// Display and record orientation (`--orientation`)
+    pub orientation: Option<Orientation>,
+    /// Orientation applied on the device before encoding (`--capture-orientation`)
+    pub capture_orientation: Option<Orientation>,
+    /// Lock the capture orientation (the `@` prefix); without an orientation,
+    /// locks to the initial device orientation
+    #[serde(default)]
+    pub capture_orientation_locked: bool,
+    /// Custom clockwise rotation of the video content, in degrees
+    pub angle: Option<f32>,
+    /// Forward device audio to the computer (`--no-audio` when disabled)
+    #[serde(default = "default_true")]
+    pub audio: bool,
+    pub audio_codec: Option<AudioCodec>,
+    pub audio_bit_rate: Option<u32>,
+    pub audio_source: Option<AudioSource>,
+    /// Keep playing audio on the device while capturing it (playback source only)
+    #[serde(default)]
+    pub audio_dup: bool,
+    /// Audio buffering delay, in milliseconds
+    pub audio_buffer: Option<u32>,
+    /// File to record the session to (`--record`)
+    #[serde(alias = "record_file")]
+    pub record_path: Option<String>,
+    /// Recording container; inferred from the file extension when unset
+    pub record_format: Option<RecordFormat>,
+    /// Rotation applied to the recording only (flips are not allowed)
+    pub record_orientation: Option<Orientation>,
+    /// Stop the session after this many seconds
+    pub time_limit: Option<u32>,
+    /// Record without opening a window or playing audio (`--no-playback`)
+    #[serde(default)]
+    pub no_playback: bool,
+    /// Mirror the display (default) or a camera
+    pub video_source: Option<VideoSource>,
+    /// Camera to mirror, as listed by `list_cameras`
+    pub camera_id: Option<String>,
+    pub camera_facing: Option<CameraFacing>,
+    /// Explicit capture size; takes precedence over `max_size`
+    pub camera_size: Option<Size>,
+    /// Aspect ratio used to pick the capture size: "sensor", "<num>:<den>" or "<value>"
+    pub camera_ar: Option<String>,
+    pub camera_fps: Option<u32>,
+    #[serde(default)]
+    pub camera_high_speed: bool,
+    /// Mirror a new virtual display instead of an existing one
+    pub new_display: Option<NewDisplay>,
+    pub start_app: Option<StartApp>,
+    /// Move apps to the main display when the virtual display is closed
+    #[serde(default)]
+    pub no_vd_destroy_content: bool,
+    /// Create the virtual display without system decorations
+    #[serde(default)]
+    pub no_vd_system_decorations: bool,
+    /// Title of the mirroring window; scrcpy uses the device model when unset
+    #[serde(default)]
+    pub window_title: Option<String>,
+    /// Position of the window; centered when unset
+    #[serde(default)]
+    pub window_x: Option<i16>,
+    #[serde(default)]
+    pub window_y: Option<i16>,
+    /// Size of the window; fitted to the video when unset
+    #[serde(default)]
+    pub window_width: Option<u16>,
+    #[serde(default)]
+    pub window_height: Option<u16>,
+    #[serde(default)]
+    pub window_borderless: bool,
+    #[serde(default)]
+    pub fullscreen: bool,
+    /// Keep the computer's screensaver from starting while mirroring
+    #[serde(default)]
+    pub disable_screensaver: bool,
+    /// Arguments for scrcpy options the app does not model, appended as given
+    #[serde(default)]
+    pub extra_args: Vec<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl Default for ScrcpyOptions {
+    fn default() -> Self {
+        Self {
+            max_size: Some(1920),
+            bit_rate: Some(8000000), // 8Mbps
+            max_fps: Some(60),
+            always_on_top: false,
+            stay_awake: true,
+            turn_screen_off: false,
+            video_codec: None,
+            video_encoder: None,
+            video_codec_options: None,
+            display_id: None,
+            crop: None,
+            orientation: None,
+            capture_orientation: None,
+            capture_orientation_locked: false,
+            angle: None,
+            audio: true,
+            audio_codec: None,
+            audio_bit_rate: None,
+            audio_source: None,
+            audio_dup: false,
+            audio_buffer: None,
+            record_path: None,
+            record_format: None,
+            record_orientation: None,
+            time_limit: None,
+            no_playback: false,
+            video_source: None,
+            camera_id: None,
+            camera_facing: None,
+            camera_size: None,
+            camera_ar: None,
+            camera_fps: None,
+            camera_high_speed: false,
+            new_display: None,
+            start_app: None,
+            no_vd_destroy_content: false,
+            no_vd_system_decorations: false,
+            window_title: None,
+            window_x: None,
+            window_y: None,
+            window_width: None,
+            window_height: None,
+            window_borderless: false,
+            fullscreen: false,
+            disable_screensaver: false,
+            extra_args: Vec::new(),
+        }
+    }
+}
+
+impl ScrcpyOptions {
+    /// Check option values before they are handed to scrcpy
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(encoder) = &self.video_encoder {
+            if encoder.trim().is_empty() || encoder.contains(char::is_whitespace) {
+                return Err(format!("Invalid video encoder name: '{}'", encoder));
+            }
+        }
+
+        if let Some(codec_options) = &self.video_codec_options {
+            validate_codec_options(codec_options)?;
+        }
+
+        if let Some(crop) = &self.crop {
+            if crop.width == 0 || crop.height == 0 {
+                return Err(format!(
+                    "Invalid crop {}: width and height must be greater than 0",
+                    crop.as_arg()
+                ));
+            }
+        }
+
+        if let Some(angle) = self.angle {
+            if !angle.is_finite() {
+                return Err(format!("Invalid rotation angle: {}", angle));
+            }
+        }
+
+        if self.audio_dup {
+            if !self.audio {
+                return Err("Audio duplication requires audio forwarding to be enabled".to_string());
+            }
+            if self.audio_source != Some(AudioSource::Playback) {
+                return Err("Audio duplication is only available with the playback audio source".to_string());
+            }
+        }
+
+        if self.audio_bit_rate == Some(0) {
+            return Err("Audio bit rate must be greater than 0".to_string());
+        }
+
+        self.validate_recording()?;
+        self.validate_camera()?;
+        self.validate_virtual_display()?;
+
+        if let Some(ref start_app) = self.start_app {
+            let name = start_app.package.trim();
+            if name.is_empty() || (!start_app.search && name.contains(char::is_whitespace)) {
+                return Err(format!("Invalid app package name: '{}'", start_app.package));
+            }
+        }
+
+        validate_extra_args(&self.extra_args, None)?;
+
+        Ok(())
+    }
+
+    fn validate_virtual_display(&self) -> Result<(), String> {
+        let Some(new_display) = self.new_display else {
+            if self.no_vd_destroy_content || self.no_vd_system_decorations {
+                return Err("Virtual display options require a new display".to_string());
+            }
+            return Ok(());
+        };
+
+        if self.is_camera() {
+            return Err("A new display is only available with the display video source".to_string());
+        }
+
+        if self.display_id.is_some() {
+            return Err("Cannot specify both a display id and a new display".to_string());
+        }
+
+        if let Some(size) = new_display.size {
+            if size.width == 0 || size.height == 0 {
+                return Err(format!("Invalid new display size: {}", size.as_arg()));
+            }
+        }
+
+        if new_display.dpi == Some(0) {
+            return Err("New display density must be greater than 0".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Target mirrored by these options, or None for a new virtual display
+    /// (each session gets its own, so they never conflict)
+    pub fn session_target(&self) -> Option<SessionTarget> {
+        if self.is_camera() {
+            let camera = self.camera_id.clone()
+                .or_else(|| self.camera_facing.map(|f| f.as_arg().to_string()))
+                .unwrap_or_else(|| "default".to_string());
+            return Some(SessionTarget::Camera(camera));
+        }
+        
+        if self.new_display.is_some() {
+            return None;
+        }
+        
+        Some(SessionTarget::Display(self.display_id.unwrap_or(0)))
+    }
+
+    /// Like `session_target`, with the camera resolved to the id scrcpy
+    /// opens, so the same camera asked for by id, by facing or by default
+    /// is the same target. Falls back to `session_target` when `cameras`
+    /// has no match.
+    pub fn resolve_session_target(&self, cameras: &[CameraInfo]) -> Option<SessionTarget> {
+        if self.is_camera() && self.camera_id.is_none() {
+            // scrcpy opens the first camera listed, of the requested facing if any
+            let camera = cameras.iter()
+                .find(|camera| self.camera_facing.is_none() || camera.facing == self.camera_facing);
+            if let Some(camera) = camera {
+                return Some(SessionTarget::Camera(camera.id.clone()));
+            }
+        }
+
+        self.session_target()
+    }
+
+    /// Whether the session streams a device camera
+    pub fn is_camera(&self) -> bool {
+        self.video_source == Some(VideoSource::Camera)
+    }
+
+    fn validate_camera(&self) -> Result<(), String> {
+        let camera_options_set = self.camera_id.is_some()
+            || self.camera_facing.is_some()
+            || self.camera_size.is_some()
+            || self.camera_ar.is_some()
+            || self.camera_fps.is_some()
+            || self.camera_high_speed;
+
+        if !self.is_camera() {
+            if camera_options_set {
+                return Err("Camera options are only available with the camera video source".to_string());
+            }
+            return Ok(());
+        }
+
+        if self.display_id.is_some() {
+            return Err("A display id cannot be used with the camera video source".to_string());
+        }
+
+        if self.camera_id.is_some() && self.camera_facing.is_some() {
+            return Err("Cannot specify both a camera id and a camera facing".to_string());
+        }
+
+        if let Some(ref camera_id) = self.camera_id {
+            if camera_id.trim().is_empty() || camera_id.contains(char::is_whitespace) {
+                return Err(format!("Invalid camera id: '{}'", camera_id));
+            }
+        }
+
+        if let Some(size) = self.camera_size {
+            if size.width == 0 || size.height == 0 {
+                return Err(format!("Invalid camera size: {}", size.as_arg()));
+            }
+            if self.camera_ar.is_some() {
+                return Err("Cannot specify both a camera size and a camera aspect ratio".to_string());
+            }
+        }
+
+        if let Some(ref ar) = self.camera_ar {
+            if !is_valid_aspect_ratio(ar) {
+                return Err(format!(
+                    "Invalid camera aspect ratio '{}' (expected \"sensor\", \"<num>:<den>\" or \"<value>\")",
+                    ar
+                ));
+            }
+        }
+
+        if self.camera_fps == Some(0) {
+            return Err("Camera frame rate must be greater than 0".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Recording format, explicit or inferred from the record path
+    pub fn effective_record_format(&self) -> Option<RecordFormat> {
+        self.record_format.or_else(|| {
+            let path = self.record_path.as_ref()?;
+            let extension = std::path::Path::new(path).extension()?.to_str()?;
+            RecordFormat::from_extension(extension)
+        })
+    }
+
+    fn validate_recording(&self) -> Result<(), String> {
+        match &self.record_path {
+            Some(path) => {
+                if path.trim().is_empty() {
+                    return Err("Recording path must not be empty".to_string());
+                }
+
+                let format = self.effective_record_format().ok_or_else(|| format!(
+                    "Cannot determine recording format for '{}': use a known extension or set a record format",
+                    path
+                ))?;
+
+                if !format.has_video() && self.record_orientation.is_some() {
+                    return Err(format!("Record orientation has no effect on audio-only format '{}'", format.as_arg()));
+                }
+
+                if !format.has_video() && !self.audio {
+                    return Err(format!("Audio-only format '{}' requires audio forwarding", format.as_arg()));
+                }
+            }
+            None => {
+                if self.record_format.is_some() || self.record_orientation.is_some() {
+                    return Err("Recording options require a recording path".to_string());
+                }
+                if self.no_playback {
+                    return Err("Disabling playback requires a recording path".to_string());
+                }
+            }
+        }
+
+        if let Some(orientation) = self.record_orientation {
+            if !orientation.is_rotation() {
+                return Err(format!(
+                    "Invalid record orientation '{}': only 0, 90, 180 and 270 are supported",
+                    orientation.as_arg()
+                ));
+            }
+        }
+
+        if self.time_limit == Some(0) {
+            return Err("Time limit must be greater than 0".to_string());
+        }
+
+        Ok(())
+    }
+
+    /// Check that the scrcpy version in use supports the selected options
+    pub fn validate_for_version(&self, version: &ScrcpyVersion) -> Result<(), String> {
+        if self.has_audio_options() && !Capability::Audio.is_supported_by(version) {
+            return Err(format!("Audio options require scrcpy {} or newer (found {})", Capability::Audio.min_version(), version));
+        }
+
+        let video_codec_options_set = self.video_codec.is_some()
+            || self.video_encoder.is_some()
+            || self.video_codec_options.is_some();
+
+        if video_codec_options_set && !Capability::VideoCodec.is_supported_by(version) {
+            return Err(format!("Video codec options require scrcpy {} or newer (found {})", Capability::VideoCodec.min_version(), version));
+        }
+
+        if self.audio_codec == Some(AudioCodec::Flac) && !Capability::FlacAudio.is_supported_by(version) {
+            return Err(format!("The FLAC audio codec requires scrcpy {} or newer (found {})", Capability::FlacAudio.min_version(), version));
+        }
+
+        if let Some(source) = self.audio_source {
+            if *version < source.min_version() {
+                return Err(format!(
+                    "Audio source '{}' requires scrcpy {} or newer (found {})",
+                    source.as_arg(), source.min_version(), version
+                ));
+            }
+        }
+
+        if self.audio_dup && !Capability::AudioDup.is_supported_by(version) {
+            return Err(format!("Audio duplication requires scrcpy {} or newer (found {})", Capability::AudioDup.min_version(), version));
+        }
+
+        if self.video_source.is_some() && !Capability::Camera.is_supported_by(version) {
+            return Err(format!("Selecting the video source requires scrcpy {} or newer (found {})", Capability::Camera.min_version(), version));
+        }
+
+        if self.no_playback && !Capability::NoPlayback.is_supported_by(version) {
+            return Err(format!("Recording without playback requires scrcpy {} or newer (found {})", Capability::NoPlayback.min_version(), version));
+        }
+
+        if self.new_display.is_some() && !Capability::NewDisplay.is_supported_by(version) {
+            return Err(format!("Virtual displays require scrcpy {} or newer (found {})", Capability::NewDisplay.min_version(), version));
+        }
+
+        if self.start_app.is_some() && !Capability::StartApp.is_supported_by(version) {
+            return Err(format!("Starting an app requires scrcpy {} or newer (found {})", Capability::StartApp.min_version(), version));
+        }
+
+        let capture_orientation_set = self.capture_orientation.is_some() || self.capture_orientation_locked;
+        if capture_orientation_set && !Capability::CaptureOrientation.is_supported_by(version) {
+            return Err(format!("The capture orientation requires scrcpy {} or newer (found {})", Capability::CaptureOrientation.min_version(), version));
+        }
+
+        if self.angle.is_some() && !Capability::Angle.is_supported_by(version) {
+            return Err(format!("Rotating by an angle requires scrcpy {} or newer (found {})", Capability::Angle.min_version(), version));
+        }
+
+        if self.orientation.is_some() && !Capability::Orientation.is_supported_by(version) {
+            return Err(format!("The orientation requires scrcpy {} or newer (found {})", Capability::Orientation.min_version(), version));
+        }
+
+        if self.record_orientation.is_some() && !Capability::RecordOrientation.is_supported_by(version) {
+            return Err(format!("The record orientation requires scrcpy {} or newer (found {})", Capability::RecordOrientation.min_version(), version));
+        }
+
+        if self.time_limit.is_some() && !Capability::TimeLimit.is_supported_by(version) {
+            return Err(format!("The time limit requires scrcpy {} or newer (found {})", Capability::TimeLimit.min_version(), version));
+        }
+
+        validate_extra_args(&self.extra_args, Some(version))?;
+
+        Ok(())
+    }
+
+    /// These options adapted to the scrcpy version in use, with a warning for
+    /// each option dropped.
+    ///
+    /// Options that only add to a session (audio on a release without audio,
+    /// FLAC, audio duplication) are dropped; options that change what is
+    /// mirrored or recorded are refused by `validate_for_version`.
+    pub fn adapt_to_version(&self, version: &ScrcpyVersion) -> Result<(ScrcpyOptions, Vec<String>), String> {
+        let mut options = self.clone();
+        let mut warnings = Vec::new();
+
+        if !Capability::Audio.is_supported_by(version) {
+            // Releases without audio never forward it, so turning it off needs no flag
+            let only_disabled = !options.audio
+                && options.audio_codec.is_none()
+                && options.audio_bit_rate.is_none()
+                && options.audio_source.is_none()
+                && options.audio_buffer.is_none()
+                && !options.audio_dup;
+            if options.has_audio_options() && !only_disabled {
+                warnings.push(format!("scrcpy {} does not forward audio; audio options were ignored", version));
+            }
+            options.audio = true;
+            options.audio_codec = None;
+            options.audio_bit_rate = None;
+            options.audio_source = None;
+            options.audio_buffer = None;
+            options.audio_dup = false;
+        }
+
+        if options.audio_codec == Some(AudioCodec::Flac) && !Capability::FlacAudio.is_supported_by(version) {
+            warnings.push(format!("scrcpy {} has no FLAC audio codec; using the default codec", version));
+            options.audio_codec = None;
+        }
+
+        if options.audio_dup && !Capability::AudioDup.is_supported_by(version) {
+            warnings.push(format!("scrcpy {} cannot keep audio playing on the device; audio duplication was ignored", version));
+            options.audio_dup = false;
+        }
+
+        options.validate_for_version(version)?;
+        Ok((options, warnings))
+    }
+
+    fn has_audio_options(&self) -> bool {
+        !self.audio
+            || self.audio_codec.is_some()
+            || self.audio_bit_rate.is_some()
+            || self.audio_source.is_some()
+            || self.audio_buffer.is_some()
+            || self.audio_dup
+    }
+
+    /// Value for `--capture-orientation`, if any
+    pub fn capture_orientation_arg(&self) -> Option<String> {
+        match (self.capture_orientation, self.capture_orientation_locked) {
+            (Some(orientation), true) => Some(format!("@{}", orientation.as_arg())),
+            (Some(orientation), false) => Some(orientation.as_arg().to_string()),
+            (None, true) => Some("@".to_string()),
+            (None, false) => None,
+        }
+    }
+
+    /// These options with `overrides` applied on top
+    pub fn with_overrides(&self, overrides: &OptionOverrides) -> Result<ScrcpyOptions, String> {
+        let mut fields = self.to_overrides();
+
+        for (key, value) in overrides {
+            let field = option_field(key);
+            if !fields.contains_key(field) {
+                return Err(format!("Unknown option: '{}'", key));
+            }
+            fields.insert(field.to_string(), value.clone());
+        }
+
+        serde_json::from_value(serde_json::Value::Object(fields))
+            .map_err(|e| format!("Invalid option overrides: {}", e))
+    }
+
+    /// Every field as an override, replacing all layers below
+    pub fn to_overrides(&self) -> OptionOverrides {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::Object(fields)) => fields,
+            _ => OptionOverrides::new(),
+        }
+    }
+}
+
+/// Options set by one layer of configuration (settings, preset, device, call), by field name.
+///
+/// Fields left out keep the value of the layers below; `null` clears an optional field.
+pub type OptionOverrides = serde_json::Map<String, serde_json::Value>;
+
+/// Older names of fields, still accepted in overrides (the serde aliases of `ScrcpyOptions`)
+const OPTION_ALIASES: &[(&str, &str)] = &[("record_file", "record_path")];
+
+/// Field of `ScrcpyOptions` an override key sets
+pub fn option_field(key: &str) -> &str {
+    OPTION_ALIASES.iter()
+        .find(|(alias, _)| *alias == key)
+        .map_or(key, |(_, field)| field)
+}
+
+/// Check a `--camera-ar` value: "sensor", "<num>:<den>" or a positive number
+fn is_valid_aspect_ratio(value: &str) -> bool {
+    if value == "sensor" {
+        return true;
+    }
+
+    match value.split_once(':') {
+        Some((num, den)) => matches!(
+            (num.parse::<u32>(), den.parse::<u32>()),
+            (Ok(n), Ok(d)) if n > 0 && d > 0
+        ),
+        None => value.parse::<f32>().map(|v| v.is_finite() && v > 0.0).unwrap_or(false),
+    }
+}
+
+/// Validate a `key[:type]=value[,...]` MediaFormat option list
+fn validate_codec_options(codec_options: &str) -> Result<(), String> {
+    if codec_options.trim().is_empty() {
+        return Err("Codec options must not be empty".to_string());
+    }
+
+    for entry in codec_options.split(',') {
+        let (key, value) = entry.split_once('=')
+            .ok_or_else(|| format!("Invalid codec option '{}': expected key[:type]=value", entry))?;
+
+        let (name, kind) = match key.split_once(':') {
+            Some((name, kind)) => (name, kind),
+            None => (key, "int"),
+        };
+
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(format!("Invalid codec option key in '{}'", entry));
+        }
+
+        let valid_value = match kind {
+            "int" => value.parse::<i32>().is_ok(),
+            "long" => value.parse::<i64>().is_ok(),
+            "float" => value.parse::<f32>().is_ok(),
+            "string" => true,
+            _ => return Err(format!(
+                "Invalid codec option type '{}' in '{}' (expected int, long, float or string)",
+                kind, entry
+            )),
+        };
+
+        if !valid_value {
+            return Err(format!("Invalid {} value in codec option '{}'", kind, entry));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_options_are_valid() {
+        assert!(ScrcpyOptions::default().validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate_video_encoder() {
+        let options = ScrcpyOptions {
+            video_encoder: Some("c2.qti.avc.encoder".to_string()),
+            ..Default::default()
+        };
+        assert!(options.validate().is_ok());
+
+        let options = ScrcpyOptions {
+            video_encoder: Some("  ".to_string()),
+            ..Default::default()
+        };
+        assert!(options.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_codec_options() {
+        assert!(validate_codec_options("profile=1,level=4096").is_ok());
+        assert!(validate_codec_options("i-frame-interval:float=0.5").is_ok());
+        assert!(validate_codec_options("vendor.qti-ext-enc-low-latency.enable:int=1").is_ok());
+        assert!(validate_codec_options("color-format:string=yuv").is_ok());
+
+        assert!(validate_codec_options("").is_err());
+        assert!(validate_codec_options("profile").is_err());
+        assert!(validate_codec_options("profile=high").is_err());
+        assert!(validate_codec_options("profile:double=1").is_err());
+        assert!(validate_codec_options("=1").is_err());
+    }
+
+    #[test]
+    fn test_validate_crop() {
+        let options = ScrcpyOptions {
+            crop: Some(Crop { width: 0, height: 1080, x: 0, y: 0 }),
+            ..Default::default()
+        };
+        assert!(options.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_angle() {
+        let options = ScrcpyOptions {
+            angle: Some(f32::NAN),
+            ..Default::default()
+        };
+        assert!(options.validate().is_err());
+    }
+
+    #[test]
+    fn test_capture_orientation_arg() {
+        let mut options = ScrcpyOptions::default();
+        assert_eq!(options.capture_orientation_arg(), None);
+
+        options.capture_orientation_locked = true;
+        assert_eq!(options.capture_orientation_arg().as_deref(), Some("@"));
+
+        options.capture_orientation = Some(Orientation::Flip90);
+        assert_eq!(options.capture_orientation_arg().as_deref(), Some("@flip90"));
+
+        options.capture_orientation_locked = false;
+        assert_eq!(options.capture_orientation_arg().as_deref(), Some("flip90"));
+    }
+
+    #[test]
+    fn test_options_deserialize_without_new_fields() {
+        // The frontend only sends the original subset of fields
+        let json = r#"{
+            "max_size": 1080,
+            "bit_rate": 4000000,
+            "max_fps": 30,
+            "always_on_top": false,
+            "stay_awake": true,
+            "turn_screen_off": false
+        }"#;
+        let options: ScrcpyOptions = serde_json::from_str(json).unwrap();
+        assert_eq!(options.max_size, Some(1080));
+        assert!(options.video_codec.is_none());
+        assert!(!options.capture_orientation_locked);
+    }
+
+    #[test]
+    fn test_validate_audio_dup() {
+        let options = ScrcpyOptions {
+            audio_dup: true,
+            audio_source: Some(AudioSource::Playback),
+            ..Default::default()
+        };
+        assert!(options.validate().is_ok());
+
+        let options = ScrcpyOptions {
+            audio_dup: true,
+            audio_source: Some(AudioSource::Output),
+            ..Default::default()
+        };
+        assert!(options.validate().is_err());
+
+        let options = ScrcpyOptions {
+            audio: false,
+            audio_dup: true,
+            audio_source: Some(AudioSource::Playback),
+            ..Default::default()
+        };
+        assert!(options.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_for_version_audio() {
+        let v1 = ScrcpyVersion::new(1, 25, 0);
+        let v2 = ScrcpyVersion::new(2, 0, 0);
+        let v3 = ScrcpyVersion::new(3, 3, 3);
+
+        // Defaults emit no audio flags, so they work everywhere
+        assert!(ScrcpyOptions::default().validate_for_version(&v1).is_ok());
+
+        let muted = ScrcpyOptions { audio: false, ..Default::default() };
+        assert!(muted.validate_for_version(&v1).is_err());
+        assert!(muted.validate_for_version(&v2).is_ok());
+
+        let flac = ScrcpyOptions { audio_codec: Some(AudioCodec::Flac), ..Default::default() };
+        assert!(flac.validate_for_version(&v2).is_err());
+        assert!(flac.validate_for_version(&v3).is_ok());
+
+        let playback = ScrcpyOptions {
+            audio_source: Some(AudioSource::Playback),
+            audio_dup: true,
+            ..Default::default()
+        };
+        assert!(playback.validate_for_version(&v2).is_err());
+        assert!(playback.validate_for_version(&ScrcpyVersion::new(2, 6, 0)).is_ok());
+
+        let voice = ScrcpyOptions { audio_source: Some(AudioSource::VoiceCall), ..Default::default() };
+        assert!(voice.validate_for_version(&ScrcpyVersion::new(2, 7, 0)).is_err());
+        assert!(voice.validate_for_version(&v3).is_ok());
+    }
+
+    #[test]
+    fn test_validate_for_version_orientation() {
+        let v2_0 = ScrcpyVersion::new(2, 0, 0);
+        let v2_1 = ScrcpyVersion::new(2, 1, 0);
+
+        let rotated = ScrcpyOptions { orientation: Some(Orientation::Deg90), ..Default::default() };
+        assert_eq!(
+            rotated.validate_for_version(&v2_0).unwrap_err(),
+            "The orientation requires scrcpy 2.1.0 or newer (found 2.0.0)"
+        );
+        assert!(rotated.validate_for_version(&v2_1).is_ok());
+
+        let recording = ScrcpyOptions {
+            record_path: Some("session.mp4".to_string()),
+            record_orientation: Some(Orientation::Deg180),
+            ..Default::default()
+        };
+        assert!(recording.validate_for_version(&v2_0).is_err());
+        assert!(recording.validate_for_version(&v2_1).is_ok());
+
+        let limited = ScrcpyOptions { time_limit: Some(60), ..Default::default() };
+        assert!(limited.validate_for_version(&v2_0).is_err());
+        assert!(limited.validate_for_version(&v2_1).is_ok());
+    }
+
+    #[test]
+    fn test_adapt_to_version() {
+        let v1 = ScrcpyVersion::new(1, 25, 0);
+
+        // No audio on 1.x either way, so muting needs no flag and no warning
+        let muted = ScrcpyOptions { audio: false, ..Default::default() };
+        let (adapted, warnings) = muted.adapt_to_version(&v1).unwrap();
+        assert!(adapted.audio);
+        assert!(warnings.is_empty());
+
+        let opus = ScrcpyOptions { audio_codec: Some(AudioCodec::Opus), audio_bit_rate: Some(64_000), ..Default::default() };
+        let (adapted, warnings) = opus.adapt_to_version(&v1).unwrap();
+        assert_eq!(adapted.audio_codec, None);
+        assert_eq!(adapted.audio_bit_rate, None);
+        assert_eq!(warnings, vec!["scrcpy 1.25.0 does not forward audio; audio options were ignored"]);
+
+        let flac = ScrcpyOptions { audio_codec: Some(AudioCodec::Flac), ..Default::default() };
+        let (adapted, warnings) = flac.adapt_to_version(&ScrcpyVersion::new(2, 2, 0)).unwrap();
+        assert_eq!(adapted.audio_codec, None);
+        assert_eq!(warnings.len(), 1);
+        let (adapted, warnings) = flac.adapt_to_version(&ScrcpyVersion::new(2, 3, 0)).unwrap();
+        assert_eq!(adapted.audio_codec, Some(AudioCodec::Flac));
+        assert!(warnings.is_empty());
+
+        let playback = ScrcpyOptions {
+            audio_source: Some(AudioSource::Playback),
+            audio_dup: true,
+            ..Default::default()
+        };
+        // Duplication can go, but capturing playback audio is the point of the session
+        assert!(playback.adapt_to_version(&ScrcpyVersion::new(2, 4, 0)).is_err());
+
+        let angle = ScrcpyOptions { angle: Some(12.5), ..Default::default() };
+        assert!(angle.adapt_to_version(&ScrcpyVersion::new(2, 7, 0)).is_err());
+        assert!(angle.adapt_to_version(&ScrcpyVersion::new(3, 0, 0)).is_ok());
+
+        let encoder = ScrcpyOptions { video_encoder: Some("c2.android.avc.encoder".to_string()), ..Default::default() };
+        assert!(encoder.adapt_to_version(&v1).is_err());
+    }
+
+    #[test]
+    fn test_audio_enabled_when_field_missing() {
+        let options: ScrcpyOptions = serde_json::from_str(r#"{
+            "always_on_top": false,
+            "stay_awake": true,
+            "turn_screen_off": false
+        }"#).unwrap();
+        assert!(options.audio);
+        assert!(!options.audio_dup);
+    }
+
+    #[test]
+    fn test_record_file_alias() {
+        // The frontend type historically named this field `record_file`
+        let options: ScrcpyOptions = serde_json::from_str(r#"{
+            "always_on_top": false,
+            "stay_awake": true,
+            "turn_screen_off": false,
+            "record_file": "demo.mkv"
+        }"#).unwrap();
+        assert_eq!(options.record_path.as_deref(), Some("demo.mkv"));
+    }
+
+    #[test]
+    fn test_effective_record_format() {
+        let options = ScrcpyOptions {
+            record_path: Some("C:\\Videos\\demo.MKV".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(options.effective_record_format(), Some(RecordFormat::Mkv));
+
+        let options = ScrcpyOptions {
+            record_path: Some("demo.bin".to_string()),
+            record_format: Some(RecordFormat::Mp4),
+            ..Default::default()
+        };
+        assert_eq!(options.effective_record_format(), Some(RecordFormat::Mp4));
+    }
+
+    #[test]
+    fn test_validate_recording() {
+        let valid = ScrcpyOptions {
+            record_path: Some("demo.mp4".to_string()),
+            record_orientation: Some(Orientation::Deg90),
+            time_limit: Some(60),
+            no_playback: true,
+            ..Default::default()
+        };
+        assert!(valid.validate().is_ok());
+
+        let unknown_format = ScrcpyOptions {
+            record_path: Some("demo".to_string()),
+            ..Default::default()
+        };
+        assert!(unknown_format.validate().is_err());
+
+        let flipped = ScrcpyOptions {
+            record_path: Some("demo.mp4".to_string()),
+            record_orientation: Some(Orientation::Flip90),
+            ..Default::default()
+        };
+        assert!(flipped.validate().is_err());
+
+        let no_target = ScrcpyOptions {
+            no_playback: true,
+            ..Default::default()
+        };
+        assert!(no_target.validate().is_err());
+
+        let muted_audio_file = ScrcpyOptions {
+            record_path: Some("demo.opus".to_string()),
+            audio: false,
+            ..Default::default()
+        };
+        assert!(muted_audio_file.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_camera() {
+        let camera = ScrcpyOptions {
+            video_source: Some(VideoSource::Camera),
+            camera_facing: Some(CameraFacing::Back),
+            camera_ar: Some("16:9".to_string()),
+            camera_fps: Some(60),
+            camera_high_speed: true,
+            ..Default::default()
+        };
+        assert!(camera.validate().is_ok());
+
+        let not_camera = ScrcpyOptions {
+            camera_id: Some("0".to_string()),
+            ..Default::default()
+        };
+        assert!(not_camera.validate().is_err());
+
+        let id_and_facing = ScrcpyOptions {
+            video_source: Some(VideoSource::Camera),
+            camera_id: Some("0".to_string()),
+            camera_facing: Some(CameraFacing::Front),
+            ..Default::default()
+        };
+        assert!(id_and_facing.validate().is_err());
+
+        let size_and_ar = ScrcpyOptions {
+            video_source: Some(VideoSource::Camera),
+            camera_size: Some(Size { width: 1920, height: 1080 }),
+            camera_ar: Some("sensor".to_string()),
+            ..Default::default()
+        };
+        assert!(size_and_ar.validate().is_err());
+
+        let with_display = ScrcpyOptions {
+            video_source: Some(VideoSource::Camera),
+            display_id: Some(0),
+            ..Default::default()
+        };
+        assert!(with_display.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_virtual_display() {
+        let virtual_display = ScrcpyOptions {
+            new_display: Some(NewDisplay { size: Some(Size { width: 1920, height: 1080 }), dpi: None }),
+            no_vd_destroy_content: true,
+            start_app: Some(StartApp { package: "org.mozilla.firefox".to_string(), force_stop: true, search: false }),
+            ..Default::default()
+        };
+        assert!(virtual_display.validate().is_ok());
+
+        let with_display_id = ScrcpyOptions {
+            new_display: Some(NewDisplay::default()),
+            display_id: Some(0),
+            ..Default::default()
+        };
+        assert!(with_display_id.validate().is_err());
+
+        let camera = ScrcpyOptions {
+            video_source: Some(VideoSource::Camera),
+            new_display: Some(NewDisplay::default()),
+            ..Default::default()
+        };
+        assert!(camera.validate().is_err());
+
+        let without_display = ScrcpyOptions {
+            no_vd_system_decorations: true,
+            ..Default::default()
+        };
+        assert!(without_display.validate().is_err());
+
+        let zero_dpi = ScrcpyOptions {
+            new_display: Some(NewDisplay { size: None, dpi: Some(0) }),
+            ..Default::default()
+        };
+        assert!(zero_dpi.validate().is_err());
+    }
+
+    #[test]
+    fn test_start_app_arg() {
+        let mut start_app = StartApp { package: "firefox".to_string(), force_stop: false, search: true };
+        assert_eq!(start_app.as_arg(), "?firefox");
+        start_app.force_stop = true;
+        assert_eq!(start_app.as_arg(), "+?firefox");
+
+        let spaces = ScrcpyOptions {
+            start_app: Some(StartApp { package: "org.mozilla firefox".to_string(), force_stop: false, search: false }),
+            ..Default::default()
+        };
+        assert!(spaces.validate().is_err());
+
+        let v2 = ScrcpyVersion::new(2, 7, 0);
+        let options = ScrcpyOptions { start_app: spaces.start_app.clone(), ..Default::default() };
+        assert!(options.validate_for_version(&v2).is_err());
+        assert!(options.validate_for_version(&ScrcpyVersion::new(3, 0, 0)).is_ok());
+    }
+
+    #[test]
+    fn test_new_display_arg() {
+        assert_eq!(NewDisplay::default().as_arg(), "");
+        assert_eq!(NewDisplay { size: None, dpi: Some(240) }.as_arg(), "/240");
+        assert_eq!(
+            NewDisplay { size: Some(Size { width: 1920, height: 1080 }), dpi: Some(420) }.as_arg(),
+            "1920x1080/420"
+        );
+    }
+
+    #[test]
+    fn test_session_target() {
+        assert_eq!(ScrcpyOptions::default().session_target(), Some(SessionTarget::Display(0)));
+
+        let second_display = ScrcpyOptions { display_id: Some(2), ..Default::default() };
+        assert_eq!(second_display.session_target(), Some(SessionTarget::Display(2)));
+
+        let front_camera = ScrcpyOptions {
+            video_source: Some(VideoSource::Camera),
+            camera_facing: Some(CameraFacing::Front),
+            ..Default::default()
+        };
+        assert_eq!(front_camera.session_target(), Some(SessionTarget::Camera("front".to_string())));
+
+        let virtual_display = ScrcpyOptions { new_display: Some(NewDisplay::default()), ..Default::default() };
+        assert_eq!(virtual_display.session_target(), None);
+    }
+
+    #[test]
+    fn test_resolve_session_target() {
+        let cameras = crate::scrcpy::parse_camera_list(
+            "[server] INFO: List of cameras:\n    --camera-id=0    (back, 4000x3000, fps=[15, 30])\n    --camera-id=1    (front, 3264x2448, fps=[15, 30])\n",
+        );
+        let camera = |id: Option<&str>, facing: Option<CameraFacing>| ScrcpyOptions {
+            video_source: Some(VideoSource::Camera),
+            camera_id: id.map(str::to_string),
+            camera_facing: facing,
+            ..Default::default()
+        };
+
+        // The same camera three ways
+        let back = Some(SessionTarget::Camera("0".to_string()));
+        assert_eq!(camera(None, None).resolve_session_target(&cameras), back);
+        assert_eq!(camera(None, Some(CameraFacing::Back)).resolve_session_target(&cameras), back);
+        assert_eq!(camera(Some("0"), None).resolve_session_target(&cameras), back);
+
+        assert_eq!(
+            camera(None, Some(CameraFacing::Front)).resolve_session_target(&cameras),
+            Some(SessionTarget::Camera("1".to_string()))
+        );
+        // No external camera listed, nothing to resolve against
+        assert_eq!(
+            camera(None, Some(CameraFacing::External)).resolve_session_target(&cameras),
+            Some(SessionTarget::Camera("external".to_string()))
+        );
+        assert_eq!(ScrcpyOptions::default().resolve_session_target(&cameras), Some(SessionTarget::Display(0)));
+    }
+
+    #[test]
+    fn test_aspect_ratio_values() {
+        assert!(is_valid_aspect_ratio("sensor"));
+        assert!(is_valid_aspect_ratio("4:3"));
+        assert!(is_valid_aspect_ratio("1.6"));
+        assert!(!is_valid_aspect_ratio("0:3"));
+        assert!(!is_valid_aspect_ratio("wide"));
+        assert!(!is_valid_aspect_ratio("-1"));
+    }
+
+    #[test]
+    fn test_camera_size_parse() {
+        assert_eq!(Size::parse("1920x1080"), Some(Size { width: 1920, height: 1080 }));
+        assert_eq!(Size::parse("1920"), None);
+        assert_eq!(Size { width: 640, height: 480 }.as_arg(), "640x480");
+    }
+
+    #[test]
+    fn test_enum_serialization() {
+        assert_eq!(serde_json::to_string(&AudioSource::MicVoiceCommunication).unwrap(), "\"mic-voice-communication\"");
+        assert_eq!(serde_json::to_string(&AudioCodec::Opus).unwrap(), "\"opus\"");
+        assert_eq!(serde_json::to_string(&VideoCodec::H265).unwrap(), "\"h265\"");
+        assert_eq!(serde_json::to_string(&Orientation::Deg270).unwrap(), "\"270\"");
+        let orientation: Orientation = serde_json::from_str("\"flip180\"").unwrap();
+        assert_eq!(orientation, Orientation::Flip180);
+    }
+
+    #[test]
+    fn test_with_overrides() {
+        let overrides = serde_json::json!({
+            "max_size": 1024,
+            "bit_rate": null,
+            "video_codec": "h265",
+        });
+        let options = ScrcpyOptions::default()
+            .with_overrides(overrides.as_object().unwrap())
+            .unwrap();
+        assert_eq!(options.max_size, Some(1024));
+        assert_eq!(options.bit_rate, None);
+        assert_eq!(options.video_codec, Some(VideoCodec::H265));
+        // Untouched fields keep their value
+        assert_eq!(options.max_fps, Some(60));
+
+        let unknown = serde_json::json!({ "max_sise": 1024 });
+        assert_eq!(
+            ScrcpyOptions::default().with_overrides(unknown.as_object().unwrap()).unwrap_err(),
+            "Unknown option: 'max_sise'"
+        );
+
+        // Older field names set the field they were renamed to
+        let alias = serde_json::json!({ "record_file": "/tmp/demo.mp4" });
+        let options = ScrcpyOptions::default().with_overrides(alias.as_object().unwrap()).unwrap();
+        assert_eq!(options.record_path.as_deref(), Some("/tmp/demo.mp4"));
+
+        let required = serde_json::json!({ "stay_awake": null });
+        assert!(ScrcpyOptions::default().with_overrides(required.as_object().unwrap()).is_err());
+    }
+}
@@ -0,0 +1,398 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ExitStatus};
+#[cfg(not(target_os = "linux"))]
+use std::process::Command;
+use std::time::Duration;
+use serde::{Serialize, Deserialize};
+
+/// What to do on startup with scrcpy processes left running by a previous run of the app
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OrphanPolicy {
+    /// Stop them, as if the app had shut down cleanly
+    #[default]
+    Terminate,
+    /// Track them again as running sessions
+    Adopt,
+}
+
+/// Whether a tracked process is still running
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessState {
+    Running,
+    /// The status is None when it cannot be read (adopted processes)
+    Exited(Option<ExitStatus>),
+}
+
+/// A running scrcpy process, as seen by session tracking.
+///
+/// Implemented by `Child` for processes spawned by the app, by
+/// `AdoptedProcess` for survivors of a previous run, and by fakes in tests.
+pub trait ScrcpyProcess: Send + std::fmt::Debug {
+    fn id(&self) -> u32;
+
+    /// Whether the process was adopted, so its exit status cannot be read
+    fn is_adopted(&self) -> bool {
+        false
+    }
+
+    /// Check whether the process exited, without blocking
+    fn try_wait(&mut self) -> std::io::Result<ProcessState>;
+
+    /// Ask the process to exit on its own
+    fn request_exit(&mut self) -> Result<(), String> {
+        request_exit(self.id())
+    }
+
+    fn kill(&mut self) -> std::io::Result<()>;
+
+    /// Block until the process exits
+    fn wait(&mut self) -> std::io::Result<Option<ExitStatus>>;
+
+    /// Take the piped stdout, so it can be read on another thread
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>> {
+        None
+    }
+
+    /// Take the piped stderr, so it can be read on another thread
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>> {
+        None
+    }
+}
+
+/// A scrcpy process owned by a session
+pub type SessionProcess = Box<dyn ScrcpyProcess>;
+
+impl From<Child> for SessionProcess {
+    fn from(child: Child) -> Self {
+        Box::new(child)
+    }
+}
+
+impl ScrcpyProcess for Child {
+    fn id(&self) -> u32 {
+        Child::id(self)
+    }
+
+    fn try_wait(&mut self) -> std::io::Result<ProcessState> {
+        Ok(match Child::try_wait(self)? {
+            Some(status) => ProcessState::Exited(Some(status)),
+            None => ProcessState::Running,
+        })
+    }
+
+    fn kill(&mut self) -> std::io::Result<()> {
+        Child::kill(self)
+    }
+
+    fn wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+        Child::wait(self).map(Some)
+    }
+
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.stdout.take().map(|stdout| Box::new(stdout) as Box<dyn Read + Send>)
+    }
+
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.stderr.take().map(|stderr| Box::new(stderr) as Box<dyn Read + Send>)
+    }
+}
+
+/// A process left over from a previous run and adopted on startup, by pid.
+/// Only whether it is alive can be observed, not its exit status.
+#[derive(Debug)]
+pub struct AdoptedProcess(pub u32);
+
+impl ScrcpyProcess for AdoptedProcess {
+    fn id(&self) -> u32 {
+        self.0
+    }
+
+    fn is_adopted(&self) -> bool {
+        true
+    }
+
+    fn try_wait(&mut self) -> std::io::Result<ProcessState> {
+        Ok(if is_alive(self.0) {
+            ProcessState::Running
+        } else {
+            ProcessState::Exited(None)
+        })
+    }
+
+    fn kill(&mut self) -> std::io::Result<()> {
+        force_kill(self.0)
+    }
+
+    fn wait(&mut self) -> std::io::Result<Option<ExitStatus>> {
+        while is_alive(self.0) {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        Ok(None)
+    }
+}
+
+/// Executable and command line of a running process, as far as they can be read
+#[derive(Debug, Clone, Default)]
+pub struct ProcessDetails {
+    pub exe: Option<PathBuf>,
+    pub command_line: String,
+}
+
+/// Check whether a process with this pid is running
+#[cfg(target_os = "linux")]
+pub fn is_alive(pid: u32) -> bool {
+    // Zombies still have a /proc entry; the state follows the parenthesized name
+    match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+        Ok(stat) => stat.rsplit_once(')')
+            .map(|(_, rest)| !rest.trim_start().starts_with('Z'))
+            .unwrap_or(true),
+        Err(_) => false,
+    }
+}
+
+/// Check whether a process with this pid is running
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn is_alive(pid: u32) -> bool {
+    // Signal 0 only checks the pid; EPERM means it exists but is not ours
+    match send_signal(pid, 0) {
+        Ok(()) => true,
+        Err(e) => e.raw_os_error() == Some(libc::EPERM),
+    }
+}
+
+/// Check whether a process with this pid is running
+#[cfg(target_os = "windows")]
+pub fn is_alive(pid: u32) -> bool {
+    use std::os::windows::process::CommandExt;
+
+    Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/NH", "/FO", "CSV"])
+        .creation_flags(0x08000000) // CREATE_NO_WINDOW
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).contains(&format!("\"{}\"", pid)))
+        .unwrap_or(false)
+}
+
+/// Read the executable and command line of a process
+#[cfg(target_os = "linux")]
+pub fn process_details(pid: u32) -> Option<ProcessDetails> {
+    let cmdline = std::fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+    let command_line = cmdline.split(|b| *b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    Some(ProcessDetails {
+        exe: std::fs::read_link(format!("/proc/{}/exe", pid)).ok(),
+        command_line,
+    })
+}
+
+/// Read the executable and command line of a process
+#[cfg(all(unix, not(target_os = "linux")))]
+pub fn process_details(pid: u32) -> Option<ProcessDetails> {
+    let ps = |field: &str| -> Option<String> {
+        let output = Command::new("ps")
+            .args(["-p", &pid.to_string(), "-o", field])
+            .output()
+            .ok()?;
+        let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (output.status.success() && !text.is_empty()).then_some(text)
+    };
+
+    // comm is the full executable path on macOS
+    let command_line = ps("command=")?;
+    Some(ProcessDetails {
+        exe: ps("comm=").map(PathBuf::from),
+        command_line,
+    })
+}
+
+/// Read the executable and command line of a process
+#[cfg(target_os = "windows")]
+pub fn process_details(pid: u32) -> Option<ProcessDetails> {
+    use std::os::windows::process::CommandExt;
+
+    let script = format!(
+        "$p = Get-CimInstance Win32_Process -Filter 'ProcessId={}'; if ($p) {{ $p.ExecutablePath; $p.CommandLine }}",
+        pid
+    );
+    let output = Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .creation_flags(0x08000000) // CREATE_NO_WINDOW
+        .output()
+        .ok()?;
+
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut lines = text.lines().map(str::trim);
+    let exe = lines.next().filter(|line| !line.is_empty())?;
+    Some(ProcessDetails {
+        exe: Some(PathBuf::from(exe)),
+        command_line: lines.next().unwrap_or_default().to_string(),
+    })
+}
+
+/// Whether a running process is the scrcpy at `exe_path` mirroring `device_id`.
+///
+/// Guards against pids reused by unrelated processes after a reboot or crash.
+pub fn is_scrcpy_process(pid: u32, exe_path: &Path, device_id: &str) -> bool {
+    let Some(details) = process_details(pid) else {
+        return false;
+    };
+
+    let exe_matches = match details.exe {
+        Some(ref exe) => same_path(exe, exe_path),
+        // The executable can be hidden from us; fall back to the command line
+        None => exe_path.file_name()
+            .map(|name| details.command_line.contains(&*name.to_string_lossy()))
+            .unwrap_or(false),
+    };
+
+    exe_matches && details.command_line.contains(device_id)
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    let a = a.canonicalize().unwrap_or_else(|_| a.to_path_buf());
+    let b = b.canonicalize().unwrap_or_else(|_| b.to_path_buf());
+
+    if cfg!(target_os = "windows") {
+        a.to_string_lossy().eq_ignore_ascii_case(&b.to_string_lossy())
+    } else {
+        a == b
+    }
+}
+
+/// Flags scrcpy is started with: no console window, and a process group of
+/// its own so `request_exit` can send it CTRL_BREAK
+#[cfg(target_os = "windows")]
+pub(crate) const CREATION_FLAGS: u32 = 0x08000000 | 0x00000200; // CREATE_NO_WINDOW | CREATE_NEW_PROCESS_GROUP
+
+/// Send a polite termination request to a process
+#[cfg(unix)]
+fn request_exit(pid: u32) -> Result<(), String> {
+    send_signal(pid, libc::SIGTERM).map_err(|e| format!("Failed to send termination request: {}", e))
+}
+
+/// Send a polite termination request to a process.
+///
+/// scrcpy handles CTRL_BREAK like closing its window, which a `no_playback`
+/// session does not have. The break goes through the hidden console of the
+/// process group scrcpy leads (see `CREATION_FLAGS`), which the app attaches
+/// to for the call.
+#[cfg(target_os = "windows")]
+fn request_exit(pid: u32) -> Result<(), String> {
+    use std::sync::Mutex;
+    use windows_sys::Win32::System::Console::{
+        AttachConsole, FreeConsole, GenerateConsoleCtrlEvent, SetConsoleCtrlHandler, CTRL_BREAK_EVENT,
+    };
+
+    // A process is attached to one console at a time
+    static CONSOLE_LOCK: Mutex<()> = Mutex::new(());
+    let _lock = CONSOLE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    // SAFETY: plain calls on the console of this process; the app never
+    // handles console events itself, so ignoring them is harmless
+    let sent = unsafe {
+        FreeConsole();
+        if AttachConsole(pid) == 0 {
+            return Err(format!("Failed to attach to the console of scrcpy: {}", std::io::Error::last_os_error()));
+        }
+        SetConsoleCtrlHandler(None, 1);
+        let sent = GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, pid) != 0;
+        let error = std::io::Error::last_os_error();
+        FreeConsole();
+        sent.then_some(()).ok_or(error)
+    };
+
+    sent.map_err(|e| format!("Failed to send CTRL_BREAK to scrcpy: {}", e))
+}
+
+#[cfg(unix)]
+fn send_signal(pid: u32, signal: libc::c_int) -> std::io::Result<()> {
+    let pid = libc::pid_t::try_from(pid).map_err(std::io::Error::other)?;
+    // SAFETY: kill takes no pointers
+    if unsafe { libc::kill(pid, signal) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+/// Kill a process that is not our child
+#[cfg(unix)]
+fn force_kill(pid: u32) -> std::io::Result<()> {
+    match send_signal(pid, libc::SIGKILL) {
+        Err(e) if is_alive(pid) => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Kill a process that is not our child
+#[cfg(target_os = "windows")]
+fn force_kill(pid: u32) -> std::io::Result<()> {
+    use std::os::windows::process::CommandExt;
+
+    let output = Command::new("taskkill")
+        .args(["/F", "/PID", &pid.to_string()])
+        .creation_flags(0x08000000) // CREATE_NO_WINDOW
+        .output()?;
+
+    if output.status.success() || !is_alive(pid) {
+        Ok(())
+    } else {
+        Err(std::io::Error::other(String::from_utf8_lossy(&output.stderr).trim().to_string()))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    fn which(program: &str) -> PathBuf {
+        let output = Command::new("sh").arg("-c").arg(format!("command -v {}", program)).output().unwrap();
+        PathBuf::from(String::from_utf8_lossy(&output.stdout).trim())
+    }
+
+    #[test]
+    fn test_adopted_process_lifecycle() {
+        let mut child = Command::new("sleep").arg("30").spawn().unwrap();
+        let mut adopted = AdoptedProcess(child.id());
+
+        assert!(adopted.is_adopted());
+        assert_eq!(adopted.try_wait().unwrap(), ProcessState::Running);
+
+        adopted.kill().unwrap();
+        // Reap it as its real parent so it does not linger as a zombie
+        child.wait().unwrap();
+        assert_eq!(adopted.try_wait().unwrap(), ProcessState::Exited(None));
+        assert_eq!(adopted.wait().unwrap(), None);
+    }
+
+    #[test]
+    fn test_windowless_process_exits_gracefully() {
+        // Like a recording session with no_playback, there is no window to close
+        let mut child = Command::new("sleep").arg("32").spawn().unwrap();
+
+        let report = crate::scrcpy::terminate(&mut child, Duration::from_secs(5)).unwrap();
+        assert_eq!(report.method, crate::scrcpy::ShutdownMethod::Graceful);
+        assert!(!is_alive(child.id()));
+    }
+
+    #[test]
+    fn test_identifies_scrcpy_process() {
+        let mut child = Command::new("sleep").arg("31").spawn().unwrap();
+        let pid = child.id();
+
+        assert!(is_scrcpy_process(pid, &which("sleep"), "31"));
+        assert!(!is_scrcpy_process(pid, &which("sleep"), "other-device"));
+        assert!(!is_scrcpy_process(pid, &which("sh"), "31"));
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+        assert!(!is_alive(pid));
+        assert!(!is_scrcpy_process(pid, &which("sleep"), "31"));
+    }
+}
@@ -0,0 +1,327 @@
+use std::time::{Duration, Instant};
+use chrono::Utc;
+use serde::{Serialize, Deserialize};
+
+/// A session whose CPU usage stays below `IDLE_CPU_PERCENT` this long is reported as frozen.
+///
+/// A mirrored screen that does not change costs almost nothing to decode,
+/// but scrcpy still polls its sockets and input; next to nothing for this
+/// long means it is stuck.
+pub const FROZEN_AFTER: Duration = Duration::from_secs(30);
+
+/// CPU usage below which a session counts as idle; timer wakeups of a stuck
+/// process still show up as a few milliseconds now and then
+pub const IDLE_CPU_PERCENT: f64 = 0.5;
+
+/// Cumulative CPU time and memory of a process at one point in time
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProcessUsage {
+    /// User plus system time since the process started
+    pub cpu_time: Duration,
+    pub rss_bytes: u64,
+}
+
+/// Resource usage of a session, as reported to the UI
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionResources {
+    /// Share of one core used since the previous sample; above 100 on several cores.
+    /// None until two samples were taken, or if the process could not be read.
+    pub cpu_percent: Option<f64>,
+    pub rss_bytes: Option<u64>,
+    pub uptime_secs: f64,
+    /// How long CPU usage has been below `IDLE_CPU_PERCENT`
+    pub idle_secs: f64,
+    /// Idle for at least `FROZEN_AFTER`
+    pub frozen: bool,
+    /// RFC3339 time of the sample
+    pub sampled_at: String,
+}
+
+/// Successive resource samples of one scrcpy process
+#[derive(Debug, Clone, Default)]
+pub struct ResourceMonitor {
+    previous: Option<(Instant, Duration)>,
+    idle_since: Option<Instant>,
+    latest: Option<SessionResources>,
+}
+
+impl ResourceMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a sample taken at `now`, returning the resulting usage
+    pub fn record(&mut self, usage: Option<ProcessUsage>, now: Instant, uptime_secs: f64) -> SessionResources {
+        let cpu_percent = usage.and_then(|usage| {
+            let previous = self.previous.replace((now, usage.cpu_time));
+            let (at, cpu_time) = previous?;
+            let wall = now.checked_duration_since(at)?.as_secs_f64();
+            (wall > 0.0).then(|| usage.cpu_time.saturating_sub(cpu_time).as_secs_f64() / wall * 100.0)
+        });
+
+        match cpu_percent {
+            Some(percent) if percent < IDLE_CPU_PERCENT => {
+                self.idle_since.get_or_insert(now);
+            }
+            Some(_) => self.idle_since = None,
+            // Unknown usage neither confirms nor clears an idle streak
+            None => {}
+        }
+
+        let idle = self.idle_since
+            .and_then(|since| now.checked_duration_since(since))
+            .unwrap_or_default();
+
+        let resources = SessionResources {
+            cpu_percent,
+            rss_bytes: usage.map(|usage| usage.rss_bytes),
+            uptime_secs,
+            idle_secs: idle.as_secs_f64(),
+            frozen: self.idle_since.is_some() && idle >= FROZEN_AFTER,
+            sampled_at: Utc::now().to_rfc3339(),
+        };
+        self.latest = Some(resources.clone());
+        resources
+    }
+
+    /// The most recent sample, if any
+    pub fn latest(&self) -> Option<SessionResources> {
+        self.latest.clone()
+    }
+}
+
+/// Read the CPU time and memory of a process
+#[cfg(target_os = "linux")]
+pub fn read_usage(pid: u32) -> Option<ProcessUsage> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let status = std::fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+
+    // SAFETY: sysconf only reads a configuration value
+    let ticks_per_sec = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+
+    Some(ProcessUsage {
+        cpu_time: parse_proc_stat_cpu(&stat, u64::try_from(ticks_per_sec).ok().filter(|&t| t > 0)?)?,
+        rss_bytes: parse_proc_status_rss(&status)?,
+    })
+}
+
+/// Read the CPU time and memory of a process
+#[cfg(target_os = "macos")]
+pub fn read_usage(pid: u32) -> Option<ProcessUsage> {
+    let mut info: libc::proc_taskinfo = unsafe { std::mem::zeroed() };
+    let size = std::mem::size_of::<libc::proc_taskinfo>() as libc::c_int;
+
+    // SAFETY: the buffer is a proc_taskinfo of the size passed
+    let written = unsafe {
+        libc::proc_pidinfo(
+            i32::try_from(pid).ok()?,
+            libc::PROC_PIDTASKINFO,
+            0,
+            &mut info as *mut libc::proc_taskinfo as *mut libc::c_void,
+            size,
+        )
+    };
+    if written != size {
+        return None;
+    }
+
+    // Task times are in Mach absolute time units, which are not nanoseconds on Apple silicon
+    #[allow(deprecated)]
+    let mut timebase = libc::mach_timebase_info { numer: 0, denom: 0 };
+    // SAFETY: mach_timebase_info fills the struct it is given
+    #[allow(deprecated)]
+    let timebase_ok = unsafe { libc::mach_timebase_info(&mut timebase) } == 0;
+    if !timebase_ok || timebase.denom == 0 {
+        return None;
+    }
+
+    let ticks = info.pti_total_user as u128 + info.pti_total_system as u128;
+    let nanos = ticks * timebase.numer as u128 / timebase.denom as u128;
+
+    Some(ProcessUsage {
+        cpu_time: Duration::from_nanos(u64::try_from(nanos).ok()?),
+        rss_bytes: info.pti_resident_size,
+    })
+}
+
+/// Read the CPU time and memory of a process
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "macos"))))]
+pub fn read_usage(pid: u32) -> Option<ProcessUsage> {
+    let output = std::process::Command::new("ps")
+        .args(["-p", &pid.to_string(), "-o", "time=,rss="])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    let text = String::from_utf8_lossy(&output.stdout);
+    let mut fields = text.split_whitespace();
+    let cpu_time = parse_cpu_time(fields.next()?)?;
+    let rss_kb: u64 = fields.next()?.parse().ok()?;
+
+    Some(ProcessUsage {
+        cpu_time,
+        rss_bytes: rss_kb * 1024,
+    })
+}
+
+/// Read the CPU time and memory of a process
+#[cfg(target_os = "windows")]
+pub fn read_usage(pid: u32) -> Option<ProcessUsage> {
+    use windows_sys::Win32::Foundation::{CloseHandle, FILETIME};
+    use windows_sys::Win32::System::ProcessStatus::{GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS};
+    use windows_sys::Win32::System::Threading::{GetProcessTimes, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION};
+
+    // SAFETY: the handle is checked and closed below
+    let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+    if process.is_null() {
+        return None;
+    }
+
+    let mut creation = FILETIME::default();
+    let mut exit = FILETIME::default();
+    let mut kernel = FILETIME::default();
+    let mut user = FILETIME::default();
+    let mut memory = PROCESS_MEMORY_COUNTERS {
+        cb: std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32,
+        ..Default::default()
+    };
+
+    // SAFETY: every pointer is to a local of the expected type
+    let (times_ok, memory_ok) = unsafe {
+        let times_ok = GetProcessTimes(process, &mut creation, &mut exit, &mut kernel, &mut user) != 0;
+        let memory_ok = GetProcessMemoryInfo(process, &mut memory, memory.cb) != 0;
+        CloseHandle(process);
+        (times_ok, memory_ok)
+    };
+    if !times_ok || !memory_ok {
+        return None;
+    }
+
+    // FILETIME durations count 100 ns intervals
+    let intervals = |time: FILETIME| ((time.dwHighDateTime as u64) << 32) | time.dwLowDateTime as u64;
+
+    Some(ProcessUsage {
+        cpu_time: Duration::from_nanos((intervals(kernel) + intervals(user)) * 100),
+        rss_bytes: memory.WorkingSetSize as u64,
+    })
+}
+
+/// User plus system time from `/proc/<pid>/stat`, whose times are in clock ticks
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_proc_stat_cpu(stat: &str, ticks_per_sec: u64) -> Option<Duration> {
+    // The name can contain spaces and parentheses, so count fields after the last ')'.
+    // utime and stime are fields 14 and 15, i.e. 12 and 13 after the state.
+    let (_, rest) = stat.rsplit_once(')')?;
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    let utime: u64 = fields.get(11)?.parse().ok()?;
+    let stime: u64 = fields.get(12)?.parse().ok()?;
+
+    Some(Duration::from_millis((utime + stime) * 1000 / ticks_per_sec))
+}
+
+/// Resident memory from `/proc/<pid>/status`
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_proc_status_rss(status: &str) -> Option<u64> {
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// CPU time printed by `ps -o time`: `[DD-][HH:]MM:SS[.ss]`
+#[cfg_attr(not(all(unix, not(any(target_os = "linux", target_os = "macos")))), allow(dead_code))]
+fn parse_cpu_time(value: &str) -> Option<Duration> {
+    let (days, clock) = match value.split_once('-') {
+        Some((days, clock)) => (days.parse::<f64>().ok()?, clock),
+        None => (0.0, value),
+    };
+
+    let mut seconds = 0.0;
+    for part in clock.split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+
+    Some(Duration::from_secs_f64(days * 86_400.0 + seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(cpu_ms: u64) -> Option<ProcessUsage> {
+        Some(ProcessUsage {
+            cpu_time: Duration::from_millis(cpu_ms),
+            rss_bytes: 50 * 1024 * 1024,
+        })
+    }
+
+    #[test]
+    fn test_cpu_percent_between_samples() {
+        let mut monitor = ResourceMonitor::new();
+        let start = Instant::now();
+
+        let first = monitor.record(usage(1000), start, 1.0);
+        assert_eq!(first.cpu_percent, None);
+        assert_eq!(first.rss_bytes, Some(50 * 1024 * 1024));
+
+        // 500ms of CPU over 2s of wall time
+        let second = monitor.record(usage(1500), start + Duration::from_secs(2), 3.0);
+        assert_eq!(second.cpu_percent, Some(25.0));
+        assert!(!second.frozen);
+        assert_eq!(monitor.latest(), Some(second));
+    }
+
+    #[test]
+    fn test_frozen_after_idle_period() {
+        let mut monitor = ResourceMonitor::new();
+        let start = Instant::now();
+
+        monitor.record(usage(1000), start, 0.0);
+        let idle = monitor.record(usage(1000), start + Duration::from_secs(2), 2.0);
+        assert_eq!(idle.cpu_percent, Some(0.0));
+        assert_eq!(idle.idle_secs, 0.0);
+        assert!(!idle.frozen);
+
+        // An unreadable sample does not break the streak, nor does a wakeup
+        monitor.record(None, start + Duration::from_secs(10), 10.0);
+        let wakeup = monitor.record(usage(1004), start + Duration::from_secs(12), 12.0);
+        assert!(wakeup.cpu_percent.unwrap() < IDLE_CPU_PERCENT);
+        assert_eq!(wakeup.idle_secs, 10.0);
+
+        let frozen = monitor.record(usage(1004), start + Duration::from_secs(40), 40.0);
+        assert_eq!(frozen.idle_secs, 38.0);
+        assert!(frozen.frozen);
+
+        let busy = monitor.record(usage(1204), start + Duration::from_secs(42), 42.0);
+        assert!(!busy.frozen);
+        assert_eq!(busy.idle_secs, 0.0);
+    }
+
+    #[test]
+    fn test_parse_proc_files() {
+        let stat = "4242 (scrcpy (main)) S 1 4242 4242 0 -1 4194304 3000 0 0 0 250 130 0 0 20 0 12 0 100 0 0";
+        assert_eq!(parse_proc_stat_cpu(stat, 100), Some(Duration::from_millis(3800)));
+        assert_eq!(parse_proc_stat_cpu(stat, 250), Some(Duration::from_millis(1520)));
+
+        let status = "Name:\tscrcpy\nVmPeak:\t  900000 kB\nVmRSS:\t   81234 kB\nThreads:\t12\n";
+        assert_eq!(parse_proc_status_rss(status), Some(81234 * 1024));
+        assert_eq!(parse_proc_status_rss("Name:\tkthreadd\n"), None);
+    }
+
+    #[test]
+    fn test_parse_ps_cpu_time() {
+        assert_eq!(parse_cpu_time("0:01.50"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_cpu_time("75:00.00"), Some(Duration::from_secs(4500)));
+        assert_eq!(parse_cpu_time("01:02:03"), Some(Duration::from_secs(3723)));
+        assert_eq!(parse_cpu_time("1-00:00:01"), Some(Duration::from_secs(86_401)));
+        assert_eq!(parse_cpu_time("n/a"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_read_own_usage() {
+        let usage = read_usage(std::process::id()).unwrap();
+        assert!(usage.rss_bytes > 0);
+    }
+}
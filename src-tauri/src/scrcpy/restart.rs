@@ -0,0 +1,105 @@
+use std::time::Duration;
+use serde::{Serialize, Deserialize};
+use super::ExitReason;
+
+/// Delay before restarting with `RestartPolicy::Always`
+pub const DEFAULT_RESTART_BACKOFF: Duration = Duration::from_secs(2);
+
+/// Upper bound for the exponential backoff between restarts
+pub const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(60);
+
+/// A relaunched session that runs at least this long resets the retry count
+pub const STABLE_RUN: Duration = Duration::from_secs(60);
+
+/// What to do when a session's scrcpy process exits on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RestartPolicy {
+    #[default]
+    Never,
+    /// Restart after abnormal exits, up to `max_retries` times in a row.
+    /// The delay starts at `backoff_ms` and doubles after each attempt.
+    OnFailure { max_retries: u32, backoff_ms: u64 },
+    /// Restart after every abnormal exit, with no retry limit. Closing the
+    /// scrcpy window still ends the session.
+    Always,
+}
+
+impl RestartPolicy {
+    /// Whether a session that ended for `reason` should be relaunched,
+    /// given the number of consecutive restarts already attempted
+    pub fn should_restart(&self, reason: ExitReason, attempts: u32) -> bool {
+        // Closing the window is as deliberate as stopping from the app
+        if matches!(reason, ExitReason::Stopped | ExitReason::Closed) {
+            return false;
+        }
+
+        match self {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnFailure { max_retries, .. } => {
+                reason == ExitReason::Failed && attempts < *max_retries
+            }
+            RestartPolicy::Always => true,
+        }
+    }
+
+    /// Delay before the restart following `attempts` consecutive restarts
+    pub fn delay(&self, attempts: u32) -> Duration {
+        let base = match self {
+            RestartPolicy::OnFailure { backoff_ms, .. } => Duration::from_millis(*backoff_ms),
+            _ => DEFAULT_RESTART_BACKOFF,
+        };
+
+        base.checked_mul(2u32.saturating_pow(attempts))
+            .unwrap_or(MAX_RESTART_BACKOFF)
+            .min(MAX_RESTART_BACKOFF)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_never_restarts() {
+        assert!(!RestartPolicy::Never.should_restart(ExitReason::Failed, 0));
+        assert!(!RestartPolicy::Never.should_restart(ExitReason::Closed, 0));
+    }
+
+    #[test]
+    fn test_on_failure_respects_max_retries() {
+        let policy = RestartPolicy::OnFailure { max_retries: 2, backoff_ms: 500 };
+        assert!(policy.should_restart(ExitReason::Failed, 0));
+        assert!(policy.should_restart(ExitReason::Failed, 1));
+        assert!(!policy.should_restart(ExitReason::Failed, 2));
+        assert!(!policy.should_restart(ExitReason::Closed, 0));
+    }
+
+    #[test]
+    fn test_stopped_sessions_are_not_restarted() {
+        assert!(!RestartPolicy::Always.should_restart(ExitReason::Stopped, 0));
+        assert!(!RestartPolicy::Always.should_restart(ExitReason::Closed, 0));
+        assert!(RestartPolicy::Always.should_restart(ExitReason::Failed, 10));
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_cap() {
+        let policy = RestartPolicy::OnFailure { max_retries: 10, backoff_ms: 1000 };
+        assert_eq!(policy.delay(0), Duration::from_secs(1));
+        assert_eq!(policy.delay(1), Duration::from_secs(2));
+        assert_eq!(policy.delay(3), Duration::from_secs(8));
+        assert_eq!(policy.delay(40), MAX_RESTART_BACKOFF);
+        assert_eq!(RestartPolicy::Always.delay(0), DEFAULT_RESTART_BACKOFF);
+    }
+
+    #[test]
+    fn test_policy_serialization() {
+        let policy: RestartPolicy = serde_json::from_str(
+            r#"{"type":"on_failure","max_retries":3,"backoff_ms":1000}"#
+        ).unwrap();
+        assert_eq!(policy, RestartPolicy::OnFailure { max_retries: 3, backoff_ms: 1000 });
+
+        let policy: RestartPolicy = serde_json::from_str(r#"{"type":"always"}"#).unwrap();
+        assert_eq!(policy, RestartPolicy::Always);
+    }
+}
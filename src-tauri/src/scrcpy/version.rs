@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use serde::{Serialize, Deserialize};
+use crate::utils;
+
+/// Versions already detected, keyed by scrcpy executable path
+static VERSION_CACHE: Mutex<Option<HashMap<PathBuf, ScrcpyVersion>>> = Mutex::new(None);
+
+/// Parsed scrcpy release version
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ScrcpyVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+impl ScrcpyVersion {
+    pub const fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self { major, minor, patch }
+    }
+
+    /// Parse the output of `scrcpy --version` (e.g. "scrcpy 3.3.3 <https://...>")
+    pub fn parse(output: &str) -> Result<Self, String> {
+        let first_line = output.lines().next().unwrap_or_default();
+
+        let token = first_line
+            .split_whitespace()
+            .find(|t| t.starts_with(|c: char| c.is_ascii_digit()))
+            .ok_or_else(|| format!("No version number in scrcpy output: '{}'", first_line))?;
+
+        // Drop pre-release suffixes such as "2.4-rc1"
+        let numeric = token.split(|c: char| !c.is_ascii_digit() && c != '.').next().unwrap_or(token);
+
+        let mut parts = numeric.split('.').map(|p| p.parse::<u32>());
+        let mut next = |name: &str| -> Result<u32, String> {
+            match parts.next() {
+                Some(Ok(n)) => Ok(n),
+                Some(Err(_)) => Err(format!("Invalid {} version in '{}'", name, token)),
+                None => Ok(0),
+            }
+        };
+
+        let major = next("major")?;
+        let minor = next("minor")?;
+        let patch = next("patch")?;
+
+        Ok(Self { major, minor, patch })
+    }
+}
+
+impl fmt::Display for ScrcpyVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Detect the version of the scrcpy executable in use (cached per path)
+pub fn detect_version<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> Result<ScrcpyVersion, String> {
+    let scrcpy_path = utils::get_scrcpy_path(app)?;
+
+    if let Ok(cache) = VERSION_CACHE.lock() {
+        if let Some(version) = cache.as_ref().and_then(|c| c.get(&scrcpy_path)) {
+            return Ok(*version);
+        }
+    }
+
+    let version = ScrcpyVersion::parse(&super::get_version(app)?)?;
+
+    if let Ok(mut cache) = VERSION_CACHE.lock() {
+        cache.get_or_insert_with(HashMap::new).insert(scrcpy_path, version);
+    }
+
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_release_version() {
+        let output = "scrcpy 3.3.3 <https://github.com/Genymobile/scrcpy>\n\nDependencies (compiled / linked):\n - SDL: 2.32.8 / 2.32.8";
+        assert_eq!(ScrcpyVersion::parse(output).unwrap(), ScrcpyVersion::new(3, 3, 3));
+    }
+
+    #[test]
+    fn test_parse_two_part_version() {
+        assert_eq!(ScrcpyVersion::parse("scrcpy 2.0 <https://github.com/Genymobile/scrcpy>").unwrap(), ScrcpyVersion::new(2, 0, 0));
+        assert_eq!(ScrcpyVersion::parse("scrcpy 1.25").unwrap(), ScrcpyVersion::new(1, 25, 0));
+    }
+
+    #[test]
+    fn test_parse_prerelease_version() {
+        assert_eq!(ScrcpyVersion::parse("scrcpy 2.4-rc1").unwrap(), ScrcpyVersion::new(2, 4, 0));
+    }
+
+    #[test]
+    fn test_parse_invalid_version() {
+        assert!(ScrcpyVersion::parse("").is_err());
+        assert!(ScrcpyVersion::parse("scrcpy dev").is_err());
+    }
+
+    #[test]
+    fn test_version_ordering() {
+        assert!(ScrcpyVersion::new(2, 0, 0) < ScrcpyVersion::new(2, 6, 0));
+        assert!(ScrcpyVersion::new(2, 7, 0) < ScrcpyVersion::new(3, 0, 0));
+        assert!(ScrcpyVersion::new(3, 3, 3) >= ScrcpyVersion::new(3, 0, 0));
+        assert_eq!(ScrcpyVersion::new(3, 3, 3).to_string(), "3.3.3");
+    }
+}
@@ -0,0 +1,217 @@
+use serde::{Serialize, Deserialize};
+use super::OptionOverrides;
+
+/// Position and size of a scrcpy window, in screen pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl WindowGeometry {
+    /// The geometry as window options, unless scrcpy could not take it
+    /// (it stores positions in 16 bits)
+    pub fn to_overrides(self) -> Option<OptionOverrides> {
+        let x = i16::try_from(self.x).ok()?;
+        let y = i16::try_from(self.y).ok()?;
+        let width = u16::try_from(self.width).ok()?;
+        let height = u16::try_from(self.height).ok()?;
+
+        let mut overrides = OptionOverrides::new();
+        overrides.insert("window_x".to_string(), x.into());
+        overrides.insert("window_y".to_string(), y.into());
+        overrides.insert("window_width".to_string(), width.into());
+        overrides.insert("window_height".to_string(), height.into());
+        Some(overrides)
+    }
+
+    /// Whether the window was visible on screen, not minimized or collapsed
+    fn is_visible(&self) -> bool {
+        // Windows moves minimized windows to -32000
+        self.width > 0 && self.height > 0 && self.x > -32000 && self.y > -32000
+    }
+}
+
+/// Read the geometry of the window of a process, through xdotool (X11 only)
+#[cfg(target_os = "linux")]
+pub fn read_window_geometry(pid: u32) -> Option<WindowGeometry> {
+    let output = std::process::Command::new("xdotool")
+        .args(["search", "--pid", &pid.to_string(), "getwindowgeometry", "--shell"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    parse_xdotool_geometry(&String::from_utf8_lossy(&output.stdout)).filter(WindowGeometry::is_visible)
+}
+
+/// Read the geometry of the window of a process, through System Events.
+///
+/// System Events gives the frame of the window; scrcpy places its content, so
+/// the title bar is taken off (its height comes from AppKit).
+#[cfg(target_os = "macos")]
+pub fn read_window_geometry(pid: u32) -> Option<WindowGeometry> {
+    let script = format!(
+        "use framework \"AppKit\"
+use scripting additions
+tell application \"System Events\"
+    set theWindow to first window of (first process whose unix id is {})
+    set {{x, y}} to position of theWindow
+    set {{width, height}} to size of theWindow
+    set titled to exists (first button of theWindow whose subrole is \"AXCloseButton\")
+end tell
+set titleBar to 0
+if titled then
+    set frame to current application's NSWindow's frameRectForContentRect:{{{{0, 0}}, {{100, 100}}}} styleMask:(current application's NSWindowStyleMaskTitled)
+    set titleBar to ((item 2 of item 2 of frame) - 100) as integer
+end if
+return {{x, y + titleBar, width, height - titleBar}}",
+        pid
+    );
+    let output = std::process::Command::new("osascript")
+        .args(["-e", &script])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+
+    parse_geometry_numbers(&String::from_utf8_lossy(&output.stdout)).filter(WindowGeometry::is_visible)
+}
+
+/// Read the geometry of the main window of a process: the position of its
+/// client area on screen and its size, which is what scrcpy places
+#[cfg(target_os = "windows")]
+pub fn read_window_geometry(pid: u32) -> Option<WindowGeometry> {
+    use windows_sys::core::BOOL;
+    use windows_sys::Win32::Foundation::{HWND, LPARAM, POINT, RECT};
+    use windows_sys::Win32::Graphics::Gdi::ClientToScreen;
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        EnumWindows, GetClientRect, GetWindow, GetWindowThreadProcessId, IsWindowVisible, GW_OWNER,
+    };
+
+    struct Search {
+        pid: u32,
+        window: HWND,
+    }
+
+    unsafe extern "system" fn visit(window: HWND, search: LPARAM) -> BOOL {
+        // SAFETY: `search` is the `Search` EnumWindows was given below, alive while it runs
+        let search = unsafe { &mut *(search as *mut Search) };
+        let mut pid = 0;
+        // SAFETY: `window` comes from EnumWindows and `pid` is a local
+        let is_main = unsafe {
+            GetWindowThreadProcessId(window, &mut pid);
+            pid == search.pid && IsWindowVisible(window) != 0 && GetWindow(window, GW_OWNER).is_null()
+        };
+        if is_main {
+            search.window = window;
+            return 0; // Stop enumerating
+        }
+        1
+    }
+
+    let mut search = Search { pid, window: std::ptr::null_mut() };
+    // SAFETY: the callback only uses `search` during the call
+    unsafe { EnumWindows(Some(visit), &mut search as *mut Search as LPARAM) };
+    if search.window.is_null() {
+        return None;
+    }
+
+    let mut client = RECT::default();
+    let mut origin = POINT::default();
+    // SAFETY: the window handle was just found and the pointers are to locals
+    let found = unsafe {
+        GetClientRect(search.window, &mut client) != 0 && ClientToScreen(search.window, &mut origin) != 0
+    };
+    if !found {
+        return None;
+    }
+
+    Some(WindowGeometry {
+        x: origin.x,
+        y: origin.y,
+        width: u32::try_from(client.right - client.left).ok()?,
+        height: u32::try_from(client.bottom - client.top).ok()?,
+    })
+    .filter(WindowGeometry::is_visible)
+}
+
+/// Geometry printed by `xdotool getwindowgeometry --shell` (`X=..`, `Y=..`,
+/// `WIDTH=..`, `HEIGHT=..` lines), for the first window found
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn parse_xdotool_geometry(output: &str) -> Option<WindowGeometry> {
+    let value = |key: &str| {
+        output.lines()
+            .find_map(|line| line.trim().strip_prefix(key)?.strip_prefix('='))
+            .map(str::trim)
+    };
+
+    Some(WindowGeometry {
+        x: value("X")?.parse().ok()?,
+        y: value("Y")?.parse().ok()?,
+        width: value("WIDTH")?.parse().ok()?,
+        height: value("HEIGHT")?.parse().ok()?,
+    })
+}
+
+/// Geometry printed as `x, y, width, height` by osascript
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn parse_geometry_numbers(output: &str) -> Option<WindowGeometry> {
+    let mut numbers = output
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty());
+    let mut next = || numbers.next()?.parse::<i64>().ok();
+
+    let geometry = WindowGeometry {
+        x: i32::try_from(next()?).ok()?,
+        y: i32::try_from(next()?).ok()?,
+        width: u32::try_from(next()?).ok()?,
+        height: u32::try_from(next()?).ok()?,
+    };
+    numbers.next().is_none().then_some(geometry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_xdotool_geometry() {
+        let output = "WINDOW=54525956\nX=1920\nY=-24\nWIDTH=540\nHEIGHT=1170\nSCREEN=0\n";
+        assert_eq!(
+            parse_xdotool_geometry(output),
+            Some(WindowGeometry { x: 1920, y: -24, width: 540, height: 1170 })
+        );
+        assert_eq!(parse_xdotool_geometry("WINDOW=54525956\nX=10\n"), None);
+        assert_eq!(parse_xdotool_geometry(""), None);
+    }
+
+    #[test]
+    fn test_parse_geometry_numbers() {
+        let expected = Some(WindowGeometry { x: 100, y: 40, width: 540, height: 1170 });
+        assert_eq!(parse_geometry_numbers("100, 40, 540, 1170\n"), expected);
+
+        assert_eq!(parse_geometry_numbers("100, 40, 540"), None);
+        assert_eq!(parse_geometry_numbers("100, 40, -540, 1170"), None);
+        assert_eq!(parse_geometry_numbers("missing value"), None);
+    }
+
+    #[test]
+    fn test_geometry_overrides() {
+        let minimized = WindowGeometry { x: -32000, y: -32000, width: 160, height: 28 };
+        assert!(!minimized.is_visible());
+
+        let geometry = WindowGeometry { x: -1080, y: 0, width: 540, height: 1170 };
+        assert!(geometry.is_visible());
+        let overrides = geometry.to_overrides().unwrap();
+        assert_eq!(overrides["window_x"], -1080);
+        assert_eq!(overrides["window_height"], 1170);
+
+        // Beyond what scrcpy accepts
+        assert!(WindowGeometry { x: 40000, y: 0, width: 540, height: 1170 }.to_overrides().is_none());
+    }
+}
@@ -2,7 +2,7 @@ use std::path::PathBuf;
 use tauri::Manager;
 
 /// Get the base resource path, with fallback for development mode
-fn get_resource_base_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+fn get_resource_base_path<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
     // First, try the standard resource directory (for production builds)
     if let Ok(resource_path) = app.path().resource_dir() {
         let prod_path = resource_path.join("resources");
@@ -31,8 +31,35 @@ fn get_resource_base_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
     Err("Could not find resources directory".to_string())
 }
 
-/// Get the path to the bundled ADB executable
-pub fn get_adb_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+/// Environment variable pointing debug builds at another adb executable instead
+/// of the bundled one, such as the fake adb used by the tests
+#[cfg(debug_assertions)]
+pub const ADB_PATH_OVERRIDE: &str = "MIRIN_ADB_PATH";
+
+/// The adb executable set through `ADB_PATH_OVERRIDE`, if any
+#[cfg(debug_assertions)]
+fn get_adb_override() -> Option<Result<PathBuf, String>> {
+    let adb_path = PathBuf::from(std::env::var_os(ADB_PATH_OVERRIDE).filter(|value| !value.is_empty())?);
+
+    if !adb_path.exists() {
+        return Some(Err(format!("ADB executable from {} not found at: {:?}", ADB_PATH_OVERRIDE, adb_path)));
+    }
+
+    Some(Ok(adb_path))
+}
+
+/// Release builds always run the bundled adb
+#[cfg(not(debug_assertions))]
+fn get_adb_override() -> Option<Result<PathBuf, String>> {
+    None
+}
+
+/// Get the path to the bundled ADB executable, unless overridden by `ADB_PATH_OVERRIDE`
+pub fn get_adb_path<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+    if let Some(adb_path) = get_adb_override() {
+        return adb_path;
+    }
+
     let resource_path = get_resource_base_path(app)?;
     
     let adb_path = resource_path.join("adb").join("adb.exe");
@@ -45,7 +72,7 @@ pub fn get_adb_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
 }
 
 /// Get the path to the bundled scrcpy executable
-pub fn get_scrcpy_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+pub fn get_scrcpy_path<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
     let resource_path = get_resource_base_path(app)?;
     
     let scrcpy_path = resource_path.join("scrcpy").join("scrcpy.exe");
@@ -59,7 +86,7 @@ pub fn get_scrcpy_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
 
 /// Get the path to the scrcpy-server file
 #[allow(dead_code)]
-pub fn get_scrcpy_server_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+pub fn get_scrcpy_server_path<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
     let resource_path = get_resource_base_path(app)?;
     
     let server_path = resource_path.join("scrcpy").join("scrcpy-server");
@@ -72,7 +99,13 @@ pub fn get_scrcpy_server_path(app: &tauri::AppHandle) -> Result<PathBuf, String>
 }
 
 /// Get the directory containing ADB executables and libraries
-pub fn get_adb_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+pub fn get_adb_dir<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
+    if let Some(adb_path) = get_adb_override() {
+        return adb_path?.parent()
+            .map(|dir| dir.to_path_buf())
+            .ok_or_else(|| "Failed to get parent directory".to_string());
+    }
+
     let resource_path = get_resource_base_path(app)?;
     
     let adb_dir = resource_path.join("adb");
@@ -85,7 +118,7 @@ pub fn get_adb_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
 }
 
 /// Get the directory containing scrcpy executables and libraries
-pub fn get_scrcpy_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+pub fn get_scrcpy_dir<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> Result<PathBuf, String> {
     let resource_path = get_resource_base_path(app)?;
     
     let scrcpy_dir = resource_path.join("scrcpy");
@@ -0,0 +1,105 @@
+//! Stand-in for adb that replays canned responses, for tests. See `adb::fake`.
+//!
+//! Reads `fake_adb.json` next to its own executable:
+//!
+//! ```json
+//! { "rules": [
+//!     { "args": "devices -l", "responses": [{ "stdout": "List of devices attached\n" }] },
+//!     { "args": "-s * tcpip 5555", "responses": [{ "exit_code": 1, "stderr": "error: device not found" }] }
+//! ] }
+//! ```
+//!
+//! The first rule whose `args` match the command line answers it; `*` matches
+//! any single word. Each call to a rule plays its next response, and the
+//! last one repeats. Every call is appended to `fake_adb.log`.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Debug, Deserialize)]
+struct Script {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Rule {
+    args: String,
+    responses: Vec<Response>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Response {
+    stdout: String,
+    stderr: String,
+    exit_code: i32,
+    delay_ms: u64,
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command_line = args.join(" ");
+
+    let dir = match std::env::current_exe().ok().and_then(|exe| exe.parent().map(Path::to_path_buf)) {
+        Some(dir) => dir,
+        None => fail("fake adb: cannot locate its own directory"),
+    };
+
+    if let Ok(mut log) = fs::OpenOptions::new().create(true).append(true).open(dir.join("fake_adb.log")) {
+        let _ = writeln!(log, "{}", command_line);
+    }
+
+    let script: Script = match fs::read_to_string(dir.join("fake_adb.json")) {
+        Ok(json) => serde_json::from_str(&json)
+            .unwrap_or_else(|e| fail(&format!("fake adb: invalid script: {}", e))),
+        Err(e) => fail(&format!("fake adb: cannot read script: {}", e)),
+    };
+
+    let Some((index, rule)) = script.rules.iter().enumerate().find(|(_, rule)| matches(&rule.args, &command_line)) else {
+        fail(&format!("fake adb: no response for '{}'", command_line));
+    };
+
+    let call = next_call(&dir.join("fake_adb.state"), index);
+    let Some(response) = rule.responses.get(call).or(rule.responses.last()) else {
+        fail(&format!("fake adb: rule '{}' has no responses", rule.args));
+    };
+
+    std::thread::sleep(Duration::from_millis(response.delay_ms));
+    print!("{}", response.stdout);
+    eprint!("{}", response.stderr);
+    std::process::exit(response.exit_code);
+}
+
+/// Compare word by word, since `adb shell` commands may come as one argument or several
+fn matches(pattern: &str, command_line: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split_whitespace().collect();
+    let words: Vec<&str> = command_line.split_whitespace().collect();
+    pattern.len() == words.len()
+        && pattern.iter().zip(words).all(|(expected, word)| *expected == "*" || *expected == word)
+}
+
+/// Number of earlier calls to the rule, recording this one
+fn next_call(state_path: &Path, rule: usize) -> usize {
+    let mut counts: HashMap<usize, usize> = fs::read_to_string(state_path)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+
+    let count = counts.entry(rule).or_insert(0);
+    let call = *count;
+    *count += 1;
+
+    if let Ok(json) = serde_json::to_string(&counts) {
+        let _ = fs::write(state_path, json);
+    }
+    call
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(1);
+}
@@ -1,8 +1,8 @@
 import { useState } from "react";
-import type { Device, MirrorSession, ScrcpyOptions, Settings } from "../types";
+import type { Device, MirrorSession } from "../types";
 import { MirrorButton } from "./MirrorButton";
 import { MirrorStatus } from "./MirrorStatus";
-import { scrcpyService, settingsService } from "../services";
+import { scrcpyService } from "../services";
 
 interface DeviceCardProps {
   device: Device;
@@ -32,16 +32,8 @@ export function DeviceCard({ device, activeSession, onConnect, onDisconnect, onE
     try {
       setLoading(true);
       setError(null);
-      const settings: Settings = await settingsService.loadSettings();
-      const options: Partial<ScrcpyOptions> = {
-        max_size: settings.resolution === 'default' ? undefined : parseInt(settings.resolution),
-        bit_rate: settings.bitrate,
-        max_fps: settings.maxFps,
-        always_on_top: settings.alwaysOnTop,
-        stay_awake: settings.stayAwake,
-        turn_screen_off: settings.turnScreenOff,
-      };
-      await scrcpyService.startMirroring(device.id, options);
+      // The backend applies the saved settings
+      await scrcpyService.startMirroring(device.id);
       onSessionUpdate();
     } catch (err) {
       setError(err instanceof Error ? err.message : String(err));
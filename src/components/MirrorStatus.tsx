@@ -88,6 +88,39 @@ export function MirrorStatus({ sessionId, deviceName, onCrashDetected }: MirrorS
     };
   }, [sessionId, checkStatus]);
 
+  // React to session exits as soon as the backend reports them
+  useEffect(() => {
+    if (!sessionId) return;
+
+    let unlisten: (() => void) | undefined;
+    let cancelled = false;
+
+    scrcpyService.onSessionExited((event) => {
+      if (event.session_id !== sessionId || !isMountedRef.current) return;
+
+      const nextStatus = (event.reason === "Failed" ? "Error" : "Stopped") as SessionStatus;
+      if (event.reason === "Failed") {
+        setCrashed(true);
+        console.warn(`Session ${sessionId} ended abnormally`, event);
+        onCrashDetected?.();
+      }
+
+      previousStatusRef.current = nextStatus;
+      setStatus(nextStatus);
+    }).then((fn) => {
+      if (cancelled) {
+        fn();
+      } else {
+        unlisten = fn;
+      }
+    });
+
+    return () => {
+      cancelled = true;
+      unlisten?.();
+    };
+  }, [sessionId, onCrashDetected]);
+
   if (!sessionId || !status) {
     return null;
   }
@@ -98,6 +131,11 @@ export function MirrorStatus({ sessionId, deviceName, onCrashDetected }: MirrorS
       icon: "●",
       label: "Mirroring Active",
     },
+    Reconnecting: {
+      color: "bg-yellow-100 text-yellow-800 border-yellow-300",
+      icon: "↻",
+      label: "Reconnecting",
+    },
     Stopped: {
       color: crashed ? "bg-orange-100 text-orange-800 border-orange-300" : "bg-gray-100 text-gray-800 border-gray-300",
       icon: crashed ? "⚠" : "○",
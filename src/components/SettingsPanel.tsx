@@ -1,5 +1,5 @@
 import { useState, useEffect } from 'react';
-import { Settings, DEFAULT_SETTINGS } from '../types';
+import { Settings, DEFAULT_SETTINGS, OrphanPolicy, QualityMode, SessionPolicy } from '../types';
 import { settingsService } from '../services';
 
 interface SettingsPanelProps {
@@ -112,6 +112,24 @@ export function SettingsPanel({ onSettingsChange }: SettingsPanelProps) {
       )}
 
       <div className="space-y-6">
+        {/* Quality Mode */}
+        <div className="bg-gray-50 p-4 rounded-lg">
+          <label className="block text-sm font-semibold text-gray-900 mb-2">
+            Quality
+          </label>
+          <select
+            value={settings.quality ?? 'Manual'}
+            onChange={(e) => updateSetting('quality', e.target.value as QualityMode)}
+            className="input"
+          >
+            <option value="Manual">Use the values below</option>
+            <option value="Auto">Auto (measure the connection first)</option>
+          </select>
+          <p className="text-xs text-gray-600 mt-2">
+            Auto picks resolution, bitrate and FPS for each session from the measured speed of the link
+          </p>
+        </div>
+
         {/* Resolution Settings */}
         <div className="bg-gray-50 p-4 rounded-lg">
           <label className="block text-sm font-semibold text-gray-900 mb-2">
@@ -196,6 +214,40 @@ export function SettingsPanel({ onSettingsChange }: SettingsPanelProps) {
           </p>
         </div>
 
+        {/* Session Policy */}
+        <div className="bg-gray-50 p-4 rounded-lg">
+          <label className="block text-sm font-semibold text-gray-900 mb-2">
+            When a device is already mirrored
+          </label>
+          <select
+            value={settings.sessionPolicy ?? 'Reject'}
+            onChange={(e) => updateSetting('sessionPolicy', e.target.value as SessionPolicy)}
+            className="input"
+          >
+            <option value="Reject">Show an error</option>
+            <option value="Reuse">Keep the existing session</option>
+            <option value="Replace">Restart mirroring</option>
+          </select>
+          <p className="text-xs text-gray-600 mt-2">
+            Sessions on other displays or cameras of the same device are always allowed
+          </p>
+        </div>
+
+        {/* Orphan Policy */}
+        <div className="bg-gray-50 p-4 rounded-lg">
+          <label className="block text-sm font-semibold text-gray-900 mb-2">
+            Mirroring windows left open after a crash
+          </label>
+          <select
+            value={settings.orphanPolicy ?? 'Terminate'}
+            onChange={(e) => updateSetting('orphanPolicy', e.target.value as OrphanPolicy)}
+            className="input"
+          >
+            <option value="Terminate">Close them on startup</option>
+            <option value="Adopt">Keep them as active sessions</option>
+          </select>
+        </div>
+
         {/* Toggle Settings */}
         <div className="bg-gradient-to-br from-primary-50 to-blue-50 p-4 rounded-lg border border-primary-100">
           <h3 className="text-sm font-bold text-gray-900 mb-4 flex items-center gap-2">
@@ -245,6 +297,19 @@ export function SettingsPanel({ onSettingsChange }: SettingsPanelProps) {
                 className="w-5 h-5 text-primary-600 rounded focus:ring-2 focus:ring-primary-500 cursor-pointer"
               />
             </label>
+
+            <label className="flex items-center justify-between cursor-pointer p-3 bg-white rounded-lg hover:shadow-md transition-shadow">
+              <div>
+                <div className="text-sm font-semibold text-gray-900">Remember Windows</div>
+                <div className="text-xs text-gray-600 mt-0.5">Reopen each device's window where it was last, titled with its name</div>
+              </div>
+              <input
+                type="checkbox"
+                checked={settings.rememberWindows ?? true}
+                onChange={(e) => updateSetting('rememberWindows', e.target.checked)}
+                className="w-5 h-5 text-primary-600 rounded focus:ring-2 focus:ring-primary-500 cursor-pointer"
+              />
+            </label>
           </div>
         </div>
 
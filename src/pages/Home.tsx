@@ -4,8 +4,8 @@ import { DeviceTable } from "../components/DeviceTable";
 
 // import { IPInputDialog } from "../components/IPInputDialog";
 import { useToast } from "../components/ToastProvider";
-import { deviceService, scrcpyService, settingsService } from "../services";
-import type { Device, MirrorSession, ScrcpyOptions, Settings } from "../types";
+import { deviceService, scrcpyService } from "../services";
+import type { Device, MirrorSession } from "../types";
 
 const DEVICE_POLL_INTERVAL = 3000;
 const SAVE_DEBOUNCE_MS = 500;
@@ -135,16 +135,8 @@ export function Home() {
   // Start mirroring
   const handleStartMirroring = async (device: Device) => {
     try {
-      const settings: Settings = await settingsService.loadSettings();
-      const options: Partial<ScrcpyOptions> = {
-        max_size: settings.resolution === "default" ? undefined : parseInt(settings.resolution),
-        bit_rate: settings.bitrate,
-        max_fps: settings.maxFps,
-        always_on_top: settings.alwaysOnTop,
-        stay_awake: settings.stayAwake,
-        turn_screen_off: settings.turnScreenOff,
-      };
-      await scrcpyService.startMirroring(device.id, options);
+      // The backend applies the saved settings
+      await scrcpyService.startMirroring(device.id);
       toast.success(`Started mirroring ${device.name}`);
       loadData();
     } catch (err) {
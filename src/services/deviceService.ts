@@ -1,5 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
-import type { Device } from "../types";
+import type { Device, ScrcpyOptions } from "../types";
 
 /**
  * Service for device-related operations
@@ -60,4 +60,11 @@ export const deviceService = {
   async removeSavedDevice(deviceId: string): Promise<boolean> {
     return await invoke<boolean>("remove_saved_device", { deviceId });
   },
+
+  /**
+   * Set the options used for a saved device only, or clear them with null
+   */
+  async setDeviceOverrides(deviceId: string, overrides: Partial<ScrcpyOptions> | null): Promise<Device> {
+    return await invoke<Device>("set_device_overrides", { deviceId, overrides });
+  },
 };
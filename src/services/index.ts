@@ -1,4 +1,5 @@
 export * from "./deviceService";
 export * from "./scrcpyService";
+export * from "./presetService";
 export * from "./resourceService";
 export * from "./settingsService";
@@ -0,0 +1,56 @@
+import { invoke } from "@tauri-apps/api/core";
+import type { Preset, PresetLibrary, PresetImport } from "../types/tauri-commands";
+
+/**
+ * Service for named mirroring option presets
+ */
+export const presetService = {
+  /**
+   * List all saved presets and the default one
+   */
+  async listPresets(): Promise<PresetLibrary> {
+    return await invoke<PresetLibrary>("list_presets");
+  },
+
+  /**
+   * Create a preset, or update the one with the same name
+   */
+  async savePreset(preset: Preset): Promise<Preset> {
+    return await invoke<Preset>("save_preset", { preset });
+  },
+
+  /**
+   * Rename a preset, keeping it the default if it was
+   */
+  async renamePreset(name: string, newName: string): Promise<Preset> {
+    return await invoke<Preset>("rename_preset", { name, newName });
+  },
+
+  /**
+   * Delete a preset; resolves to false if it did not exist
+   */
+  async deletePreset(name: string): Promise<boolean> {
+    return await invoke<boolean>("delete_preset", { name });
+  },
+
+  /**
+   * Mark a preset as the default, or clear the default
+   */
+  async setDefaultPreset(name?: string): Promise<boolean> {
+    return await invoke<boolean>("set_default_preset", { name });
+  },
+
+  /**
+   * Export presets (all when no names are given) to a JSON file to share
+   */
+  async exportPresets(path: string, names?: string[]): Promise<number> {
+    return await invoke<number>("export_presets", { path, names });
+  },
+
+  /**
+   * Import presets from a file written by exportPresets; taken names are skipped unless overwriting
+   */
+  async importPresets(path: string, overwrite?: boolean): Promise<PresetImport> {
+    return await invoke<PresetImport>("import_presets", { path, overwrite });
+  },
+};
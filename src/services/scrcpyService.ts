@@ -1,5 +1,6 @@
 import { invoke } from "@tauri-apps/api/core";
-import type { ScrcpyOptions, MirrorSession, SessionStatus } from "../types/tauri-commands";
+import { listen, type UnlistenFn } from "@tauri-apps/api/event";
+import type { ScrcpyOptions, MirrorSession, SessionStatus, Recording, ShutdownReport, CameraInfo, EncoderInfo, DisplayInfo, AppInfo, SessionLogs, SessionLogEvent, SessionExitedEvent, SessionErrorEvent, RestartPolicy, SessionRestartEvent, SessionPolicy, HistoryFilter, SessionHistory, SessionResources, SessionResourceUsage, ParsedScrcpyArgs, EffectiveOptions, QualityDecision, SessionQualityEvent, SessionWarningEvent, CommandPreview, ScrcpyCapabilities, WindowGeometry } from "../types/tauri-commands";
 
 export interface ProcessStats {
   active_sessions: number;
@@ -11,27 +12,44 @@ export interface ProcessStats {
  */
 export const scrcpyService = {
   /**
-   * Start screen mirroring for a device
+   * Start screen mirroring for a device. Options are layered: saved settings,
+   * the preset (or the default preset), the device's overrides, then `options`
    */
   async startMirroring(
     deviceId: string,
-    options?: Partial<ScrcpyOptions>
+    options?: Partial<ScrcpyOptions>,
+    restartPolicy?: RestartPolicy,
+    sessionPolicy?: SessionPolicy,
+    preset?: string
   ): Promise<string> {
-    return await invoke<string>("start_mirroring", { deviceId, options });
+    return await invoke<string>("start_mirroring", { deviceId, options, restartPolicy, sessionPolicy, preset });
   },
 
   /**
-   * Stop screen mirroring session
+   * Launch an app on a new virtual display in its own window (scrcpy 3.0+).
+   * Options are layered as in `startMirroring`
    */
-  async stopMirroring(sessionId: string): Promise<boolean> {
-    return await invoke<boolean>("stop_mirroring", { sessionId });
+  async launchAppInWindow(
+    deviceId: string,
+    packageName: string,
+    options?: Partial<ScrcpyOptions>,
+    preset?: string
+  ): Promise<string> {
+    return await invoke<string>("launch_app_in_window", { deviceId, package: packageName, options, preset });
+  },
+
+  /**
+   * Stop screen mirroring session, killing it only after the grace period
+   */
+  async stopMirroring(sessionId: string, gracePeriodMs?: number): Promise<ShutdownReport> {
+    return await invoke<ShutdownReport>("stop_mirroring", { sessionId, gracePeriodMs });
   },
 
   /**
    * Stop all active mirroring sessions
    */
-  async stopAllMirroring(): Promise<number> {
-    return await invoke<number>("stop_all_mirroring");
+  async stopAllMirroring(gracePeriodMs?: number): Promise<number> {
+    return await invoke<number>("stop_all_mirroring", { gracePeriodMs });
   },
 
   /**
@@ -69,6 +87,183 @@ export const scrcpyService = {
     return await invoke<string>("get_scrcpy_version");
   },
 
+  /**
+   * Get the features the scrcpy in use supports, to grey out settings it would reject
+   */
+  async getCapabilities(): Promise<ScrcpyCapabilities> {
+    return await invoke<ScrcpyCapabilities>("get_scrcpy_capabilities");
+  },
+
+  /**
+   * Get the scrcpy command line startMirroring would run with the same
+   * options and preset, quoted for a terminal, with the warnings the session
+   * would report
+   */
+  async previewCommand(deviceId: string, options?: Partial<ScrcpyOptions>, preset?: string): Promise<CommandPreview> {
+    return await invoke<CommandPreview>("preview_scrcpy_command", { deviceId, options, preset });
+  },
+
+  /**
+   * Show the options starting a session would use, and which layer set each one
+   */
+  async getEffectiveOptions(deviceId: string, preset?: string, options?: Partial<ScrcpyOptions>): Promise<EffectiveOptions> {
+    return await invoke<EffectiveOptions>("get_effective_options", { deviceId, preset, options });
+  },
+
+  /**
+   * Measure the link to a device and show the quality the auto mode would pick
+   */
+  async measureQuality(deviceId: string): Promise<QualityDecision> {
+    return await invoke<QualityDecision>("measure_quality", { deviceId });
+  },
+
+  /**
+   * Get where each device's window was last, by device id
+   */
+  async getWindowGeometries(): Promise<Record<string, WindowGeometry>> {
+    return await invoke<Record<string, WindowGeometry>>("get_window_geometries");
+  },
+
+  /**
+   * Forget where a device's window was, so it opens centered again
+   */
+  async forgetWindowGeometry(deviceId: string): Promise<boolean> {
+    return await invoke<boolean>("forget_window_geometry", { deviceId });
+  },
+
+  /**
+   * Turn a pasted scrcpy command line into options; unsupported flags are listed, not dropped silently
+   */
+  async parseArgs(commandLine: string): Promise<ParsedScrcpyArgs> {
+    return await invoke<ParsedScrcpyArgs>("parse_scrcpy_args", { commandLine });
+  },
+
+  /**
+   * List the cameras of a device (with capture sizes unless disabled)
+   */
+  async listCameras(deviceId: string, includeSizes?: boolean): Promise<CameraInfo[]> {
+    return await invoke<CameraInfo[]>("list_cameras", { deviceId, includeSizes });
+  },
+
+  /**
+   * List the video and audio encoders of a device
+   */
+  async listEncoders(deviceId: string): Promise<EncoderInfo[]> {
+    return await invoke<EncoderInfo[]>("list_encoders", { deviceId });
+  },
+
+  /**
+   * List the displays of a device
+   */
+  async listDisplays(deviceId: string): Promise<DisplayInfo[]> {
+    return await invoke<DisplayInfo[]>("list_displays", { deviceId });
+  },
+
+  /**
+   * List the apps installed on a device
+   */
+  async listApps(deviceId: string): Promise<AppInfo[]> {
+    return await invoke<AppInfo[]>("list_apps", { deviceId });
+  },
+
+  /**
+   * Get the captured scrcpy output of a running or recently ended session
+   */
+  async getSessionLogs(sessionId: string): Promise<SessionLogs> {
+    return await invoke<SessionLogs>("get_session_logs", { sessionId });
+  },
+
+  /**
+   * Get past and running sessions, newest first, with totals for the filter
+   */
+  async getSessionHistory(filter?: HistoryFilter): Promise<SessionHistory> {
+    return await invoke<SessionHistory>("get_session_history", { filter });
+  },
+
+  /**
+   * Subscribe to scrcpy output lines of all sessions
+   */
+  async onSessionLog(handler: (event: SessionLogEvent) => void): Promise<UnlistenFn> {
+    return await listen<SessionLogEvent>("session-log", (event) => handler(event.payload));
+  },
+
+  /**
+   * Subscribe to sessions being started
+   */
+  async onSessionStarted(handler: (session: MirrorSession) => void): Promise<UnlistenFn> {
+    return await listen<MirrorSession>("session-started", (event) => handler(event.payload));
+  },
+
+  /**
+   * Subscribe to sessions ending, whether stopped, closed or failed
+   */
+  async onSessionExited(handler: (event: SessionExitedEvent) => void): Promise<UnlistenFn> {
+    return await listen<SessionExitedEvent>("session-exited", (event) => handler(event.payload));
+  },
+
+  /**
+   * Subscribe to sessions ending abnormally
+   */
+  async onSessionError(handler: (event: SessionErrorEvent) => void): Promise<UnlistenFn> {
+    return await listen<SessionErrorEvent>("session-error", (event) => handler(event.payload));
+  },
+
+  /**
+   * Subscribe to the auto quality mode picking the options of new sessions
+   */
+  async onSessionQuality(handler: (event: SessionQualityEvent) => void): Promise<UnlistenFn> {
+    return await listen<SessionQualityEvent>("session-quality", (event) => handler(event.payload));
+  },
+
+  /**
+   * Subscribe to options dropped from sessions because the scrcpy in use lacks them
+   */
+  async onSessionWarning(handler: (event: SessionWarningEvent) => void): Promise<UnlistenFn> {
+    return await listen<SessionWarningEvent>("session-warning", (event) => handler(event.payload));
+  },
+
+  /**
+   * Subscribe to sessions being relaunched by their restart policy
+   */
+  async onSessionRestarting(handler: (event: SessionRestartEvent) => void): Promise<UnlistenFn> {
+    return await listen<SessionRestartEvent>("session-restarting", (event) => handler(event.payload));
+  },
+
+  /**
+   * Get the latest CPU, memory and uptime sample of a running session
+   */
+  async getSessionResources(sessionId: string): Promise<SessionResources> {
+    return await invoke<SessionResources>("get_session_resources", { sessionId });
+  },
+
+  /**
+   * Subscribe to the periodic resource samples of all running sessions
+   */
+  async onSessionResources(handler: (usage: SessionResourceUsage[]) => void): Promise<UnlistenFn> {
+    return await listen<SessionResourceUsage[]>("session-resources", (event) => handler(event.payload));
+  },
+
+  /**
+   * List recordings in the local library
+   */
+  async listRecordings(): Promise<Recording[]> {
+    return await invoke<Recording[]>("list_recordings");
+  },
+
+  /**
+   * Rename a recording file
+   */
+  async renameRecording(recordingId: string, newName: string): Promise<Recording> {
+    return await invoke<Recording>("rename_recording", { recordingId, newName });
+  },
+
+  /**
+   * Remove a recording from the library (and delete its file by default)
+   */
+  async deleteRecording(recordingId: string, deleteFile?: boolean): Promise<boolean> {
+    return await invoke<boolean>("delete_recording", { recordingId, deleteFile });
+  },
+
   /**
    * Test scrcpy execution (gets version to verify it works)
    */
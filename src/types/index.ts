@@ -1,4 +1,5 @@
 export * from "./tauri-commands";
+import type { OrphanPolicy, QualityMode, ScrcpyOptions, SessionPolicy } from "./tauri-commands";
 
 // Device models matching Rust backend types
 export type ConnectionType = 'USB' | 'Wireless';
@@ -12,6 +13,7 @@ export interface Device {
   connection_type: ConnectionType;
   status: DeviceStatus;
   ip_address?: string;
+  option_overrides?: Partial<ScrcpyOptions>;
 }
 
 // Scrcpy mirror options
@@ -32,6 +34,10 @@ export interface Settings {
   alwaysOnTop: boolean;
   stayAwake: boolean;
   turnScreenOff: boolean;
+  rememberWindows?: boolean;
+  quality?: QualityMode;
+  sessionPolicy?: SessionPolicy;
+  orphanPolicy?: OrphanPolicy;
 }
 
 // Default settings
@@ -42,4 +48,8 @@ export const DEFAULT_SETTINGS: Settings = {
   alwaysOnTop: false,
   stayAwake: true,
   turnScreenOff: false,
+  rememberWindows: true,
+  quality: 'Manual',
+  sessionPolicy: 'Reject',
+  orphanPolicy: 'Terminate',
 };
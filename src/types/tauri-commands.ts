@@ -22,6 +22,93 @@ export interface Device {
   connection_type: ConnectionType;
   status: DeviceStatus;
   ip_address?: string;
+  /** Options for this device only, over the preset (saved devices) */
+  option_overrides?: Partial<ScrcpyOptions>;
+}
+
+export type VideoCodec = "h264" | "h265" | "av1";
+
+export type Orientation =
+  | "0" | "90" | "180" | "270"
+  | "flip0" | "flip90" | "flip180" | "flip270";
+
+export type AudioCodec = "opus" | "aac" | "flac" | "raw";
+
+export type AudioSource =
+  | "output" | "playback" | "mic"
+  | "mic-unprocessed" | "mic-camcorder"
+  | "mic-voice-recognition" | "mic-voice-communication"
+  | "voice-call" | "voice-call-uplink" | "voice-call-downlink"
+  | "voice-performance";
+
+export type RecordFormat =
+  | "mp4" | "mkv" | "m4a" | "mka"
+  | "opus" | "aac" | "flac" | "wav";
+
+export type VideoSource = "display" | "camera";
+
+export type CameraFacing = "front" | "back" | "external";
+
+/** Width and height in pixels, of a camera capture or a display */
+export interface Size {
+  width: number;
+  height: number;
+}
+
+export interface HighSpeedSize {
+  size: Size;
+  fps: number[];
+}
+
+export interface CameraInfo {
+  id: string;
+  facing?: CameraFacing;
+  sensor_size?: Size;
+  fps: number[];
+  sizes: Size[];
+  high_speed_sizes: HighSpeedSize[];
+}
+
+export type EncoderKind = "Video" | "Audio";
+
+export type EncoderType = "hw" | "sw" | "hybrid";
+
+export interface EncoderInfo {
+  kind: EncoderKind;
+  codec: string;
+  name: string;
+  encoder_type?: EncoderType;
+  vendor: boolean;
+  alias_for?: string;
+}
+
+export interface DisplayInfo {
+  id: number;
+  size?: Size;
+}
+
+export interface NewDisplay {
+  size?: Size;
+  dpi?: number;
+}
+
+export interface StartApp {
+  package: string;
+  force_stop?: boolean;
+  search?: boolean;
+}
+
+export interface AppInfo {
+  package: string;
+  label: string;
+  system: boolean;
+}
+
+export interface Crop {
+  width: number;
+  height: number;
+  x: number;
+  y: number;
 }
 
 export interface ScrcpyOptions {
@@ -34,18 +121,339 @@ export interface ScrcpyOptions {
   show_touches: boolean;
   stay_awake: boolean;
   turn_screen_off: boolean;
-  record_file?: string;
+  record_path?: string;
+  video_codec?: VideoCodec;
+  video_encoder?: string;
+  video_codec_options?: string;
+  display_id?: number;
+  crop?: Crop;
+  orientation?: Orientation;
+  capture_orientation?: Orientation;
+  capture_orientation_locked?: boolean;
+  angle?: number;
+  audio?: boolean;
+  audio_codec?: AudioCodec;
+  audio_bit_rate?: number;
+  audio_source?: AudioSource;
+  audio_dup?: boolean;
+  audio_buffer?: number;
+  record_format?: RecordFormat;
+  record_orientation?: Orientation;
+  time_limit?: number;
+  no_playback?: boolean;
+  video_source?: VideoSource;
+  camera_id?: string;
+  camera_facing?: CameraFacing;
+  camera_size?: Size;
+  camera_ar?: string;
+  camera_fps?: number;
+  camera_high_speed?: boolean;
+  new_display?: NewDisplay;
+  start_app?: StartApp;
+  no_vd_destroy_content?: boolean;
+  no_vd_system_decorations?: boolean;
+  /** Title of the mirroring window; scrcpy uses the device model when unset */
+  window_title?: string;
+  /** Position of the window; centered when unset */
+  window_x?: number;
+  window_y?: number;
+  /** Size of the window; fitted to the video when unset */
+  window_width?: number;
+  window_height?: number;
+  window_borderless?: boolean;
+  /** Keep the computer's screensaver from starting while mirroring */
+  disable_screensaver?: boolean;
+  /** Arguments for scrcpy options the app does not model, appended as given */
+  extra_args?: string[];
+}
+
+/** A scrcpy command line turned back into options */
+export interface ParsedScrcpyArgs {
+  options: Partial<ScrcpyOptions>;
+  /** Device selected with -s/--serial */
+  device_id?: string;
+  /** Arguments with no equivalent in ScrcpyOptions, as written */
+  unsupported: string[];
+}
+
+export interface Recording {
+  id: string;
+  session_id: string;
+  device_id: string;
+  file_path: string;
+  format?: RecordFormat;
+  options: ScrcpyOptions;
+  started_at: string;
+  stopped_at?: string;
+  duration_secs?: number;
+  size_bytes?: number;
 }
 
 export enum SessionStatus {
   Running = "Running",
+  Reconnecting = "Reconnecting",
   Stopped = "Stopped",
   Error = "Error",
 }
 
+export type ShutdownMethod = "AlreadyExited" | "Graceful" | "Killed";
+
+export interface ShutdownReport {
+  method: ShutdownMethod;
+  exit_code?: number;
+  signal?: number;
+}
+
 export interface MirrorSession {
   session_id: string;
   device_id: string;
   status: SessionStatus;
   started_at: string;
 }
+
+export type LogStream = "stdout" | "stderr";
+
+export interface LogLine {
+  stream: LogStream;
+  text: string;
+  timestamp: string;
+}
+
+export type SessionFailure =
+  | "NoAdbDevice"
+  | "EncoderNotFound"
+  | "ServerConnectionFailed"
+  | "DeviceDisconnected";
+
+export interface SessionLogs {
+  lines: LogLine[];
+  dropped: number;
+  failure?: SessionFailure;
+}
+
+/** Payload of the `session-log` event */
+export interface SessionLogEvent {
+  session_id: string;
+  line: LogLine;
+  failure?: SessionFailure;
+}
+
+export type ExitReason = "Closed" | "Stopped" | "Failed";
+
+export interface SessionExit {
+  code?: number;
+  signal?: number;
+  duration_secs: number;
+  reason: ExitReason;
+  failure?: SessionFailure;
+}
+
+/** Payload of the `session-exited` event */
+export interface SessionExitedEvent extends SessionExit {
+  session_id: string;
+  device_id: string;
+}
+
+/** Payload of the `session-error` event */
+export interface SessionErrorEvent {
+  session_id: string;
+  device_id: string;
+  failure?: SessionFailure;
+  message: string;
+}
+
+export type RestartPolicy =
+  | { type: "never" }
+  | { type: "on_failure"; max_retries: number; backoff_ms: number }
+  | { type: "always" };
+
+/** Payload of the `session-restarting` event */
+export interface SessionRestartEvent {
+  session_id: string;
+  device_id: string;
+  attempt: number;
+  delay_ms: number;
+  reason: ExitReason;
+  error?: string;
+}
+
+export interface SessionResources {
+  /** Share of one core since the previous sample; unset until two samples exist */
+  cpu_percent?: number;
+  rss_bytes?: number;
+  uptime_secs: number;
+  /** How long CPU usage has been at zero */
+  idle_secs: number;
+  /** Idle long enough that scrcpy is probably stuck */
+  frozen: boolean;
+  sampled_at: string;
+}
+
+/** Entry of the `session-resources` event payload */
+export interface SessionResourceUsage extends SessionResources {
+  session_id: string;
+  device_id: string;
+}
+
+/** One run of scrcpy; restarted sessions have one record per run */
+export interface SessionRecord {
+  session_id: string;
+  device_id: string;
+  model?: string;
+  options: ScrcpyOptions;
+  started_at: string;
+  ended_at?: string;
+  duration_secs?: number;
+  exit_code?: number;
+  signal?: number;
+  /** Unset while running, or if the app quit before the session ended */
+  reason?: ExitReason;
+  failure?: SessionFailure;
+}
+
+export interface HistoryFilter {
+  device_id?: string;
+  /** RFC3339 bounds on the start time */
+  since?: string;
+  until?: string;
+  reason?: ExitReason;
+  failure?: SessionFailure;
+  limit?: number;
+}
+
+export interface HistoryTotals {
+  started: number;
+  closed: number;
+  stopped: number;
+  failed: number;
+  unfinished: number;
+  total_duration_secs: number;
+}
+
+export interface SessionHistory {
+  sessions: SessionRecord[];
+  totals: HistoryTotals;
+}
+
+/** What starting a second session on the same device display or camera does */
+export type SessionPolicy = "Reject" | "Reuse" | "Replace";
+
+/** What happens on startup to scrcpy processes left running by a crash */
+export type OrphanPolicy = "Terminate" | "Adopt";
+
+/** A named set of mirroring options, such as "Low bandwidth wireless" */
+export interface Preset {
+  name: string;
+  description?: string;
+  /** Options the preset sets; the others keep the value of the saved settings */
+  options: Partial<ScrcpyOptions>;
+}
+
+/** All saved presets, as stored in presets.json and in exported files */
+export interface PresetLibrary {
+  presets: Preset[];
+  /** Used by startMirroring when not given a preset */
+  default_preset?: string;
+}
+
+export interface SkippedPreset {
+  name: string;
+  reason: string;
+}
+
+export interface PresetImport {
+  imported: string[];
+  skipped: SkippedPreset[];
+}
+
+/** Configuration layer an option value came from, lowest first */
+export type OptionSource = "Settings" | "Preset" | "Auto" | "Window" | "Device" | "Explicit";
+
+/** Where a device's window was last, in screen pixels */
+export interface WindowGeometry {
+  x: number;
+  y: number;
+  width: number;
+  height: number;
+}
+
+/** Options a session on a device would start with, and where each value came from */
+export interface EffectiveOptions {
+  options: ScrcpyOptions;
+  preset?: string;
+  /** Layer that last set each field; fields not listed keep the built-in default */
+  sources: Partial<Record<keyof ScrcpyOptions, OptionSource>>;
+  /** Why scrcpy would refuse to start with these options */
+  error?: string;
+  /** What the auto quality mode measured and picked, when it is on */
+  quality?: QualityDecision;
+}
+
+/** How resolution, bitrate and FPS are chosen: from the settings, or measured per session */
+export type QualityMode = "Manual" | "Auto";
+
+export type QualityTier = "High" | "Medium" | "Low" | "Minimal";
+
+/** Speed of the adb link to a device */
+export interface LinkMeasurement {
+  latency_ms: number;
+  throughput_bps: number;
+}
+
+/** Quality picked by the auto mode, and why */
+export interface QualityDecision {
+  connection_type: ConnectionType;
+  /** Missing when the link could not be measured */
+  measurement?: LinkMeasurement;
+  tier: QualityTier;
+  bit_rate: number;
+  max_size: number;
+  max_fps: number;
+  /** Explanation for the user, such as "Wireless link measured 9.6 Mbps ..." */
+  reason: string;
+}
+
+/** Payload of the `session-quality` event */
+export interface SessionQualityEvent {
+  session_id: string;
+  decision: QualityDecision;
+}
+
+/** Payload of the `session-warning` event, for an option the scrcpy in use drops */
+export interface SessionWarningEvent {
+  session_id: string;
+  message: string;
+}
+
+/** Command line a session would run, with the options the scrcpy in use drops */
+export interface CommandPreview {
+  command: string;
+  warnings: string[];
+}
+
+/** Parsed scrcpy release version */
+export interface ScrcpyVersion {
+  major: number;
+  minor: number;
+  patch: number;
+}
+
+/** A scrcpy feature that only some releases have */
+export type Capability =
+  | "Audio" | "VideoCodec" | "NoPlayback" | "Orientation" | "RecordOrientation"
+  | "TimeLimit" | "Camera" | "FlacAudio"
+  | "UhidKeyboard" | "UhidMouse" | "AudioDup" | "Gamepad"
+  | "NewDisplay" | "StartApp" | "CaptureOrientation" | "Angle";
+
+export interface CapabilityInfo {
+  capability: Capability;
+  /** First scrcpy release with the feature */
+  min_version: ScrcpyVersion;
+  supported: boolean;
+}
+
+/** What the scrcpy in use can do */
+export interface ScrcpyCapabilities {
+  version: ScrcpyVersion;
+  capabilities: CapabilityInfo[];
+}